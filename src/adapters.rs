@@ -0,0 +1,374 @@
+use crate::{
+    case::fold, reader::LinesReader, Case, Error, JsonPointer, KeywordHits, Keywords, LineFilter,
+    MarkerRules, Markers, TimeBucket,
+};
+#[cfg(feature = "wasm")]
+use crate::{Plugged, Plugin};
+use regex::Regex;
+use std::{io::Read, iter::Take, time::Duration};
+
+// WalkItem is an item of a walk WalkExt adapts: a line, or the Result of
+// reading one as LineIter yields, whose error adapters pass through as is
+pub trait WalkItem: Sized {
+    // What an adapter yields in place of the item once it turns the line
+    // into a T
+    type Map<T>;
+
+    // The line, or None for an error.
+    fn line(&self) -> Option<&str>;
+
+    fn into_line(self) -> Result<String, Error>;
+
+    // Turns the line into a T, passing an error through.
+    fn map_line<T, F: FnOnce(String) -> T>(self, f: F) -> Self::Map<T>;
+
+    // Like map_line, dropping the item when f returns None. Errors are kept.
+    fn filter_map_line<T, F: FnOnce(String) -> Option<T>>(self, f: F) -> Option<Self::Map<T>>;
+
+    // Wraps a value worked out from the lines, such as a marker between
+    // them, or the error reading them stopped at. Only items that can hold
+    // an error are ever given one.
+    fn settle<T>(value: T, error: Option<Error>) -> Self::Map<T>;
+}
+
+impl WalkItem for String {
+    type Map<T> = T;
+
+    fn line(&self) -> Option<&str> {
+        Some(self)
+    }
+
+    fn into_line(self) -> Result<String, Error> {
+        Ok(self)
+    }
+
+    fn map_line<T, F: FnOnce(String) -> T>(self, f: F) -> T {
+        f(self)
+    }
+
+    fn filter_map_line<T, F: FnOnce(String) -> Option<T>>(self, f: F) -> Option<T> {
+        f(self)
+    }
+
+    fn settle<T>(value: T, _: Option<Error>) -> T {
+        value
+    }
+}
+
+impl WalkItem for Result<String, Error> {
+    type Map<T> = Result<T, Error>;
+
+    fn line(&self) -> Option<&str> {
+        self.as_deref().ok()
+    }
+
+    fn into_line(self) -> Result<String, Error> {
+        self
+    }
+
+    fn map_line<T, F: FnOnce(String) -> T>(self, f: F) -> Result<T, Error> {
+        self.map(f)
+    }
+
+    fn filter_map_line<T, F: FnOnce(String) -> Option<T>>(self, f: F) -> Option<Result<T, Error>> {
+        match self {
+            Ok(line) => f(line).map(Ok),
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn settle<T>(value: T, error: Option<Error>) -> Result<T, Error> {
+        match error {
+            Some(e) => Err(e),
+            None => Ok(value),
+        }
+    }
+}
+
+// The item an adapter over I yields with its lines turned into T
+type MapItem<I, T> = <<I as Iterator>::Item as WalkItem>::Map<T>;
+
+// Reads the lines of items up to the first error, which is stored in error.
+fn lines_until_error<'a, I>(
+    items: I,
+    error: &'a mut Option<Error>,
+) -> impl Iterator<Item = String> + 'a
+where
+    I: Iterator<Item: WalkItem> + 'a,
+{
+    items.map_while(|item| match item.into_line() {
+        Ok(line) => Some(line),
+        Err(e) => {
+            *error = Some(e);
+            None
+        }
+    })
+}
+
+// WalkExt adds chainable adapters to any iterator of lines, or of the
+// Results LineIter yields, so options can be applied after opening instead
+// of all being set on the builder. Adapters skip or transform lines and pass
+// errors through in place, wrapping what they yield in a Result when the
+// lines were.
+pub trait WalkExt: Iterator<Item: WalkItem> + Sized {
+    // Keeps only the lines matching the regular expression.
+    fn matching(self, re: Regex) -> Matching<Self> {
+        Matching {
+            inner: self,
+            re,
+            invert: false,
+        }
+    }
+
+    // Keeps only the lines selected by filter, grep style.
+    fn grep(self, filter: &LineFilter) -> Result<Matching<Self>, Error> {
+        Ok(Matching {
+            inner: self,
+            re: filter.regex()?,
+            invert: filter.is_inverted(),
+        })
+    }
+
+    // Yields the lines containing any of the keywords, along with which ones,
+    // searching for all of them in a single pass over each line.
+    fn keyword_hits(self, keywords: Keywords) -> KeywordHits<Self> {
+        KeywordHits::new(self, keywords)
+    }
+
+    // Yields the value pointer selects in each JSON line, skipping lines
+    // without one. Strings are unquoted; other values are kept as JSON text.
+    fn extract_pointer(self, pointer: JsonPointer) -> ExtractPointer<Self> {
+        ExtractPointer {
+            inner: self,
+            pointer,
+        }
+    }
+
+    // Keeps only the JSON lines whose value at pointer equals value, compared
+    // the way extract_pointer yields it.
+    fn pointer_equals<T: Into<String>>(
+        self,
+        pointer: JsonPointer,
+        value: T,
+    ) -> PointerEquals<Self> {
+        PointerEquals {
+            inner: self,
+            pointer,
+            value: value.into(),
+            folded: false,
+        }
+    }
+
+    // Stops after at most n lines.
+    fn limit(self, n: usize) -> Take<Self> {
+        self.take(n)
+    }
+
+    // Pairs every line with its 1-based ordinal within the walk.
+    fn records(self) -> Records<Self> {
+        Records {
+            inner: self,
+            number: 0,
+        }
+    }
+
+    // Consumes the lines and returns the k most frequent keys extracted by
+    // key_fn, using bounded memory however long the walk is. Stops at the
+    // first error, returning it instead.
+    fn top_k<F>(self, key_fn: F, k: usize) -> MapItem<Self, Vec<(String, u64)>>
+    where
+        F: FnMut(&str) -> Option<&str>,
+    {
+        let mut error = None;
+        let top = crate::top_k(lines_until_error(self, &mut error), key_fn, k);
+        Self::Item::settle(top, error)
+    }
+
+    // Consumes the lines and counts them per time window, and per key when
+    // key_fn returns one, e.g. for a requests-per-minute series. Stops at
+    // the first error, returning it instead.
+    fn bucket_by_time<F>(self, window: Duration, key_fn: F) -> MapItem<Self, Vec<TimeBucket>>
+    where
+        F: FnMut(&str) -> Option<&str>,
+    {
+        let mut error = None;
+        let buckets = crate::bucket_by_time(lines_until_error(self, &mut error), window, key_fn);
+        Self::Item::settle(buckets, error)
+    }
+
+    // Reads the lines as bytes, each one followed by a '\n', for APIs that
+    // take a Read.
+    fn into_reader(self) -> impl Read {
+        LinesReader::new(self.map(WalkItem::into_line))
+    }
+
+    // Interleaves the lines with markers for the timestamp gaps and bursts
+    // selected by rules.
+    fn markers(self, rules: MarkerRules) -> Markers<Self> {
+        Markers::new(self, rules)
+    }
+
+    // Runs every line through plugin, yielding what it returns and skipping
+    // the lines it drops.
+    #[cfg(feature = "wasm")]
+    fn plugin(self, plugin: Plugin) -> Plugged<Self> {
+        Plugged::new(self, plugin)
+    }
+}
+
+impl<I: Iterator<Item: WalkItem>> WalkExt for I {}
+
+// Matching is the iterator returned by WalkExt::matching
+pub struct Matching<I> {
+    inner: I,
+    re: Regex,
+    // Keeps the lines that don't match instead
+    invert: bool,
+}
+
+impl<I: Iterator<Item: WalkItem>> Iterator for Matching<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let (re, invert) = (&self.re, self.invert);
+        self.inner
+            .find(|item| item.line().is_none_or(|line| re.is_match(line) != invert))
+    }
+}
+
+// ExtractPointer is the iterator returned by WalkExt::extract_pointer
+pub struct ExtractPointer<I> {
+    inner: I,
+    pointer: JsonPointer,
+}
+
+impl<I: Iterator<Item: WalkItem>> Iterator for ExtractPointer<I> {
+    type Item = MapItem<I, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pointer = &self.pointer;
+        self.inner
+            .find_map(|item| item.filter_map_line(|line| pointer.extract(&line)))
+    }
+}
+
+// PointerEquals is the iterator returned by WalkExt::pointer_equals
+pub struct PointerEquals<I> {
+    inner: I,
+    pointer: JsonPointer,
+    value: String,
+    // Whether values are compared case folded, in which case value is too
+    folded: bool,
+}
+
+impl<I> PointerEquals<I> {
+    // Sets how letter case is compared, case sensitively by default.
+    pub fn case(mut self, case: Case) -> Self {
+        self.folded = case.ignores_case([self.value.as_str()]);
+        if self.folded {
+            self.value = fold(&self.value);
+        }
+        self
+    }
+}
+
+impl<I: Iterator<Item: WalkItem>> Iterator for PointerEquals<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let (pointer, value, folded) = (&self.pointer, &self.value, self.folded);
+        self.inner.find(|item| {
+            item.line().is_none_or(|line| {
+                pointer.extract(line).is_some_and(|found| match folded {
+                    true => &fold(&found) == value,
+                    false => &found == value,
+                })
+            })
+        })
+    }
+}
+
+// Record is a line alongside its number, either its ordinal within the walk
+// (WalkExt::records) or its line number in the file (FileWalk::next_record)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub number: usize,
+    pub text: String,
+}
+
+// Records is the iterator returned by WalkExt::records
+pub struct Records<I> {
+    inner: I,
+    number: usize,
+}
+
+impl<I: Iterator<Item: WalkItem>> Iterator for Records<I> {
+    type Item = MapItem<I, Record>;
+
+    // Errors take no number
+    fn next(&mut self) -> Option<Self::Item> {
+        let number = &mut self.number;
+        Some(self.inner.next()?.map_line(|text| {
+            *number += 1;
+            Record {
+                number: *number,
+                text,
+            }
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, OpenerBuilder};
+
+    #[test]
+    fn test_chained_adapters() -> Result<(), Error> {
+        let opener = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .build()
+            .unwrap();
+        let records: Vec<Record> = opener
+            .open()?
+            .matching(Regex::new("^[htw]").unwrap())
+            .skip(1)
+            .limit(2)
+            .records()
+            .collect::<Result<_, _>>()?;
+
+        assert_eq!(
+            records,
+            vec![
+                Record {
+                    number: 1,
+                    text: "there".to_string()
+                },
+                Record {
+                    number: 2,
+                    text: "whats".to_string()
+                },
+            ]
+        );
+
+        // Errors come through the adapters where they happened
+        let fixture = Fixture::lines(["placeholder"]).create()?;
+        std::fs::write(fixture.path(), b"one\n\xff\nthree\n")?;
+        let mut records = fixture
+            .opener()
+            .open()?
+            .matching(Regex::new("e").unwrap())
+            .records();
+        assert_eq!(records.next().unwrap()?.text, "one");
+        assert!(matches!(
+            records.next(),
+            Some(Err(Error::InvalidUtf8 { line: 2 }))
+        ));
+        assert!(records.next().is_none());
+        Ok(())
+    }
+}
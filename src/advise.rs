@@ -0,0 +1,144 @@
+use crate::{blocks::BLOCK_SIZE, Direction, Error};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+// AccessHint describes how a walk is going to read a file, so the OS can
+// tune readahead and caching for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessHint {
+    // Reading straight through the file
+    Sequential,
+    // Jumping around or reading backward, where readahead is wasted
+    Random,
+    // Reading once and never again, so cached pages are dropped afterwards
+    OneShot,
+}
+
+impl AccessHint {
+    // Picks the hint matching the shape of a plain walk.
+    pub(crate) fn for_walk(direction: Direction) -> Self {
+        match direction {
+            Direction::Forward => AccessHint::Sequential,
+            Direction::Backward => AccessHint::Random,
+        }
+    }
+}
+
+// Applies the hint before reading. Hints are best-effort, so failures are ignored.
+pub(crate) fn advise(file: &File, hint: AccessHint) {
+    #[cfg(target_os = "linux")]
+    {
+        let advice = match hint {
+            AccessHint::Sequential | AccessHint::OneShot => libc::POSIX_FADV_SEQUENTIAL,
+            AccessHint::Random => libc::POSIX_FADV_RANDOM,
+        };
+        fadvise(file, 0, 0, advice);
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = (file, hint);
+}
+
+// Drops the file's cached pages after a one-shot walk has finished.
+pub(crate) fn release(file: &File, hint: AccessHint) {
+    #[cfg(target_os = "linux")]
+    if hint == AccessHint::OneShot {
+        fadvise(file, 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = (file, hint);
+}
+
+#[cfg(target_os = "linux")]
+fn fadvise(file: &File, offset: u64, len: u64, advice: libc::c_int) -> bool {
+    use std::os::unix::io::AsRawFd;
+    let ret = unsafe {
+        libc::posix_fadvise(
+            file.as_raw_fd(),
+            offset as libc::off_t,
+            len as libc::off_t,
+            advice,
+        )
+    };
+    ret == 0
+}
+
+// Asks the OS to start reading the byte range into the page cache so that a
+// later walk over it doesn't block on cold IO. Where no readahead hint is
+// available the pages are touched by reading through the range instead.
+pub(crate) fn prefault(file: &mut File, range: Range<u64>) -> Result<(), Error> {
+    let len = file.metadata()?.len();
+    let range = range.start.min(len)..range.end.min(len);
+    if range.is_empty() {
+        return Ok(());
+    }
+
+    #[cfg(target_os = "linux")]
+    if fadvise(
+        file,
+        range.start,
+        range.end - range.start,
+        libc::POSIX_FADV_WILLNEED,
+    ) {
+        return Ok(());
+    }
+
+    touch(file, range)
+}
+
+fn touch(file: &mut File, range: Range<u64>) -> Result<(), Error> {
+    let mut buf = vec![0; BLOCK_SIZE as usize];
+    file.seek(SeekFrom::Start(range.start))?;
+    let mut remaining = range.end - range.start;
+    while remaining > 0 {
+        let want = remaining.min(BLOCK_SIZE) as usize;
+        let read = file.read(&mut buf[..want])?;
+        if read == 0 {
+            break;
+        }
+        remaining -= read as u64;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenerBuilder;
+
+    #[test]
+    fn test_prefault() {
+        let opener = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .build()
+            .unwrap();
+        opener.prefault(0..1024).unwrap();
+        opener.prefault(100..200).unwrap();
+
+        let mut file = File::open("./testfiles/1.txt").unwrap();
+        touch(&mut file, 0..5).unwrap();
+        assert_eq!(file.stream_position().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_access_hints() {
+        assert_eq!(
+            AccessHint::for_walk(Direction::Backward),
+            AccessHint::Random
+        );
+
+        let lines: Vec<String> = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .access_hint(AccessHint::OneShot)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines.len(), 4);
+    }
+}
@@ -0,0 +1,349 @@
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+use crate::Codec;
+use crate::{
+    bom,
+    spool::{spool, spool_file},
+    Error, LineIter,
+};
+use std::{
+    char::REPLACEMENT_CHARACTER,
+    fs::File,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+// Enough for the magic numbers of every layer, including a tar header
+pub(crate) const PEEK_LEN: usize = 512;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// Layer is one step between the bytes of a file and its lines, outermost
+// first, as resolved by OpenerTemplate::auto
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layer {
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+    Compression(Codec),
+    // The regular files of a tar archive, walked one after the other
+    #[cfg(feature = "tar")]
+    Archive {
+        members: Vec<String>,
+    },
+    Encoding(TextEncoding),
+    Format(Format),
+}
+
+// TextEncoding is the encoding lines were decoded from, told by the byte
+// order mark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+// Format is what the lines hold, judged by the file name or else the first
+// line. It is only reported; lines are yielded as text either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    JsonLines,
+    Csv,
+    Tsv,
+    Text,
+}
+
+// AutoLines is the walk returned by OpenerTemplate::auto, along with the
+// layers peeled off to reach its lines
+#[derive(Debug)]
+pub struct AutoLines {
+    lines: LineIter,
+    layers: Vec<Layer>,
+}
+
+impl AutoLines {
+    pub(crate) fn new(lines: LineIter, layers: Vec<Layer>) -> Self {
+        AutoLines { lines, layers }
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn into_lines(self) -> LineIter {
+        self.lines
+    }
+}
+
+impl Iterator for AutoLines {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.lines.size_hint()
+    }
+}
+
+impl Layer {
+    fn members(&self) -> &[String] {
+        match self {
+            #[cfg(feature = "tar")]
+            Layer::Archive { members } => members,
+            _ => &[],
+        }
+    }
+}
+
+type Source = Box<dyn Read + Send>;
+
+// Works out the layers of the file at path from their magic numbers. Returns
+// the decoded contents spooled to a temp file, or None when the file is
+// plain UTF-8 and can be walked as is.
+pub(crate) fn resolve(path: &Path) -> Result<(Option<File>, Vec<Layer>), Error> {
+    let mut layers = vec![];
+    let (head, reader) = peek(Box::new(File::open(path)?))?;
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+    let (head, reader) = decompress(head, reader, &mut layers)?;
+    #[cfg(feature = "tar")]
+    let (head, reader) = unarchive(head, reader, &mut layers)?;
+    // Whatever has been peeled off so far needs spooling
+    let decoded = !layers.is_empty();
+
+    let (encoding, bom) = bom::detect(&head).unwrap_or((TextEncoding::Utf8, 0));
+    layers.push(Layer::Encoding(encoding));
+    let mut reader = reader;
+    io::copy(&mut (&mut reader).take(bom), &mut io::sink())?;
+
+    let decoded = match encoding {
+        TextEncoding::Utf8 if decoded || bom > 0 => Some(spool(reader)?),
+        TextEncoding::Utf8 => None,
+        _ => Some(transcode_utf16(reader, encoding == TextEncoding::Utf16Le)?),
+    };
+    let sample = match &decoded {
+        Some(file) => {
+            let mut file = file;
+            let mut sample = Vec::with_capacity(PEEK_LEN);
+            file.take(PEEK_LEN as u64).read_to_end(&mut sample)?;
+            file.seek(SeekFrom::Start(0))?;
+            sample
+        }
+        None => head,
+    };
+    layers.push(Layer::Format(format_of(path, &layers, &sample)));
+
+    Ok((decoded, layers))
+}
+
+// Reads the first bytes of reader, returning them along with a reader that
+// still yields everything.
+fn peek(mut reader: Source) -> Result<(Vec<u8>, Source), Error> {
+    let mut head = Vec::with_capacity(PEEK_LEN);
+    (&mut reader).take(PEEK_LEN as u64).read_to_end(&mut head)?;
+    let reader = Box::new(io::Cursor::new(head.clone()).chain(reader));
+    Ok((head, reader))
+}
+
+// Peels off as many layers of compression as there are.
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+fn decompress(
+    mut head: Vec<u8>,
+    mut reader: Source,
+    layers: &mut Vec<Layer>,
+) -> Result<(Vec<u8>, Source), Error> {
+    while let Some(codec) = Codec::detect(&head) {
+        layers.push(Layer::Compression(codec));
+        (head, reader) = peek(codec.decoder(reader)?)?;
+    }
+    Ok((head, reader))
+}
+
+// Spools the regular files of a tar archive one after the other, making
+// sure each ends with a newline so lines of adjacent members don't merge.
+#[cfg(feature = "tar")]
+fn unarchive(
+    head: Vec<u8>,
+    reader: Source,
+    layers: &mut Vec<Layer>,
+) -> Result<(Vec<u8>, Source), Error> {
+    if head.get(257..262) != Some(b"ustar") {
+        return Ok((head, reader));
+    }
+
+    let mut archive = tar::Archive::new(reader);
+    let mut out = spool_file()?;
+    let mut members = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        members.push(entry.path()?.to_string_lossy().into_owned());
+        if io::copy(&mut entry, &mut out)? > 0 {
+            let mut last = [0];
+            out.seek(SeekFrom::End(-1))?;
+            out.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                out.write_all(b"\n")?;
+            }
+        }
+    }
+    out.seek(SeekFrom::Start(0))?;
+    layers.push(Layer::Archive { members });
+    peek(Box::new(out))
+}
+
+// Spools UTF-16 text as UTF-8, replacing unpaired surrogates.
+pub(crate) fn transcode_utf16(mut reader: Source, little_endian: bool) -> Result<File, Error> {
+    let mut out = BufWriter::new(spool_file()?);
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut pending = vec![];
+    let mut high_surrogate = None;
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        pending.extend_from_slice(&buf[..read]);
+        let whole = pending.len() / 2 * 2;
+        let mut units: Vec<u16> = high_surrogate.take().into_iter().collect();
+        units.extend(pending[..whole].chunks_exact(2).map(|pair| {
+            let pair = [pair[0], pair[1]];
+            match little_endian {
+                true => u16::from_le_bytes(pair),
+                false => u16::from_be_bytes(pair),
+            }
+        }));
+        pending.drain(..whole);
+        // The low half of a pair may be in the next chunk
+        if units
+            .last()
+            .is_some_and(|unit| (0xd800..0xdc00).contains(unit))
+        {
+            high_surrogate = units.pop();
+        }
+        let text: String = char::decode_utf16(units)
+            .map(|c| c.unwrap_or(REPLACEMENT_CHARACTER))
+            .collect();
+        out.write_all(text.as_bytes())?;
+    }
+    if high_surrogate.is_some() || !pending.is_empty() {
+        write!(out, "{}", REPLACEMENT_CHARACTER)?;
+    }
+
+    let mut file = out.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+// Picks the format from the innermost file name with a telling extension,
+// skipping compression and archive extensions, or else from the first line.
+pub(crate) fn format_of(path: &Path, layers: &[Layer], sample: &[u8]) -> Format {
+    let members = layers.iter().flat_map(|layer| layer.members());
+    let names: Vec<&Path> = std::iter::once(path)
+        .chain(members.map(Path::new))
+        .collect();
+    for name in names.into_iter().rev() {
+        let file_name = Path::new(name)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        for extension in file_name.rsplit('.') {
+            match extension {
+                "gz" | "tgz" | "zst" | "bz2" | "xz" | "tar" => continue,
+                "jsonl" | "ndjson" => return Format::JsonLines,
+                "csv" => return Format::Csv,
+                "tsv" => return Format::Tsv,
+                _ => break,
+            }
+        }
+    }
+
+    let sample = String::from_utf8_lossy(sample);
+    let first = sample.lines().map(str::trim).find(|line| !line.is_empty());
+    match first {
+        Some(line) if line.starts_with('{') => Format::JsonLines,
+        _ => Format::Text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{Fixture, FixtureEncoding},
+        Opener, OpenerTemplateBuilder,
+    };
+
+    #[test]
+    fn test_auto_plain() {
+        let lines = Opener::auto("./testfiles/1.txt").unwrap();
+        assert_eq!(
+            lines.layers(),
+            &[
+                Layer::Encoding(TextEncoding::Utf8),
+                Layer::Format(Format::Text)
+            ]
+        );
+        assert_eq!(lines.count(), 4);
+
+        let fixture = Fixture::lines([r#"{"a": 1}"#, "dé😀"])
+            .encoding(FixtureEncoding::Utf16Be)
+            .bom(true)
+            .create()
+            .unwrap();
+        let template = OpenerTemplateBuilder::default()
+            .position("end")
+            .direction("backward")
+            .build()
+            .unwrap();
+        let lines = template.auto(fixture.path()).unwrap();
+        assert_eq!(
+            lines.layers(),
+            &[
+                Layer::Encoding(TextEncoding::Utf16Be),
+                Layer::Format(Format::JsonLines)
+            ]
+        );
+        assert_eq!(
+            lines.collect::<Result<Vec<_>, _>>().unwrap(),
+            vec!["dé😀", r#"{"a": 1}"#]
+        );
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn test_auto_tar_gz() {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            vec![],
+            flate2::Compression::default(),
+        ));
+        for (name, contents) in [("a.jsonl", "{}\n{}"), ("b.jsonl", "{}\n")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+        let fixture = Fixture::lines(["unused"]).create().unwrap();
+        std::fs::write(fixture.path(), archive).unwrap();
+
+        let lines = Opener::auto(fixture.path()).unwrap();
+        assert_eq!(
+            lines.layers(),
+            &[
+                Layer::Compression(Codec::Gzip),
+                Layer::Archive {
+                    members: vec!["a.jsonl".to_string(), "b.jsonl".to_string()]
+                },
+                Layer::Encoding(TextEncoding::Utf8),
+                Layer::Format(Format::JsonLines),
+            ]
+        );
+        assert_eq!(lines.count(), 3);
+    }
+}
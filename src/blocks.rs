@@ -0,0 +1,133 @@
+use crate::{Direction, Error};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+};
+
+pub(crate) const BLOCK_SIZE: u64 = 8192;
+
+// Blocks reads raw, block-aligned chunks of a file in either direction,
+// for parsers that want the crate's IO without the line layer.
+pub struct Blocks {
+    file: File,
+    direction: Direction,
+    offset: u64,
+    len: u64,
+    buf: Vec<u8>,
+}
+
+impl Blocks {
+    pub(crate) fn new(file: File, offset: u64, direction: Direction) -> Result<Self, Error> {
+        let len = file.metadata()?.len();
+        Ok(Blocks {
+            file,
+            direction,
+            offset: offset.min(len),
+            len,
+            buf: Vec::with_capacity(BLOCK_SIZE as usize),
+        })
+    }
+
+    // Returns the next block along with the byte offset it starts at. Blocks
+    // end (forward) or start (backward) on multiples of the block size, so
+    // only the first block read may be shorter than the others.
+    pub fn next_block(&mut self) -> Result<Option<(u64, &[u8])>, Error> {
+        let (start, end) = match self.direction {
+            Direction::Forward => {
+                if self.offset >= self.len {
+                    return Ok(None);
+                }
+                let end = ((self.offset / BLOCK_SIZE + 1) * BLOCK_SIZE).min(self.len);
+                (self.offset, end)
+            }
+            Direction::Backward => {
+                if self.offset == 0 {
+                    return Ok(None);
+                }
+                let start = (self.offset - 1) / BLOCK_SIZE * BLOCK_SIZE;
+                (start, self.offset)
+            }
+        };
+
+        self.buf.resize((end - start) as usize, 0);
+        self.file.seek(SeekFrom::Start(start))?;
+        self.file.read_exact(&mut self.buf)?;
+        self.offset = match self.direction {
+            Direction::Forward => end,
+            Direction::Backward => start,
+        };
+
+        Ok(Some((start, &self.buf)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, OpenerBuilder, Position};
+
+    #[test]
+    fn test_blocks() {
+        let mut blocks = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .position(Position::Middle(2))
+            .build()
+            .unwrap()
+            .blocks()
+            .unwrap();
+        assert_eq!(
+            blocks.next_block().unwrap(),
+            Some((6, "there\nwhats\nup".as_bytes()))
+        );
+        assert_eq!(blocks.next_block().unwrap(), None);
+
+        let mut blocks = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .position("end")
+            .direction("backward")
+            .build()
+            .unwrap()
+            .blocks()
+            .unwrap();
+        assert_eq!(
+            blocks.next_block().unwrap(),
+            Some((0, "hello\nthere\nwhats\nup".as_bytes()))
+        );
+        assert_eq!(blocks.next_block().unwrap(), None);
+    }
+
+    #[test]
+    fn test_block_alignment() {
+        let fixture = Fixture::lines(["a".repeat(BLOCK_SIZE as usize * 2 + 10)])
+            .trailing_newline(false)
+            .create()
+            .unwrap();
+        let path = fixture.path();
+
+        let mut forward = Blocks::new(File::open(path).unwrap(), 10, Direction::Forward).unwrap();
+        let mut spans = vec![];
+        while let Some((start, block)) = forward.next_block().unwrap() {
+            spans.push((start, block.len() as u64));
+        }
+        assert_eq!(
+            spans,
+            vec![
+                (10, BLOCK_SIZE - 10),
+                (BLOCK_SIZE, BLOCK_SIZE),
+                (BLOCK_SIZE * 2, 10)
+            ]
+        );
+
+        let mut backward = Blocks::new(
+            File::open(path).unwrap(),
+            BLOCK_SIZE + 5,
+            Direction::Backward,
+        )
+        .unwrap();
+        let mut spans = vec![];
+        while let Some((start, block)) = backward.next_block().unwrap() {
+            spans.push((start, block.len() as u64));
+        }
+        assert_eq!(spans, vec![(BLOCK_SIZE, 5), (0, BLOCK_SIZE)]);
+    }
+}
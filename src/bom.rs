@@ -0,0 +1,101 @@
+use crate::TextEncoding;
+use std::io::{self, Read, Seek, SeekFrom};
+
+// Tells the encoding from the byte order mark head starts with, if any,
+// along with the length of the mark.
+pub(crate) fn detect(head: &[u8]) -> Option<(TextEncoding, u64)> {
+    match head {
+        [0xef, 0xbb, 0xbf, ..] => Some((TextEncoding::Utf8, 3)),
+        [0xff, 0xfe, ..] => Some((TextEncoding::Utf16Le, 2)),
+        [0xfe, 0xff, ..] => Some((TextEncoding::Utf16Be, 2)),
+        _ => None,
+    }
+}
+
+// Reads the byte order mark at the start of input, if any, leaving input
+// back at the start.
+pub(crate) fn read<R: Read + Seek>(input: &mut R) -> io::Result<Option<(TextEncoding, u64)>> {
+    input.seek(SeekFrom::Start(0))?;
+    let mut head = Vec::with_capacity(3);
+    input.take(3).read_to_end(&mut head)?;
+    input.seek(SeekFrom::Start(0))?;
+    Ok(detect(&head))
+}
+
+// Unmarked hides the byte order mark at the start of a reader, so offset 0
+// is the first byte of text. Unlike a Bounded window it has no end, so a
+// file that grows can still be followed.
+pub(crate) struct Unmarked<R> {
+    inner: R,
+    mark: u64,
+}
+
+impl<R: Seek> Unmarked<R> {
+    pub(crate) fn new(mut inner: R, mark: u64) -> io::Result<Self> {
+        inner.seek(SeekFrom::Start(mark))?;
+        Ok(Unmarked { inner, mark })
+    }
+}
+
+impl<R: Read> Read for Unmarked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for Unmarked<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => {
+                let end = self.inner.seek(SeekFrom::End(0))? - self.mark;
+                end.checked_add_signed(offset)
+            }
+            SeekFrom::Current(offset) => {
+                let pos = self.inner.stream_position()? - self.mark;
+                pos.checked_add_signed(offset)
+            }
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek position."))?;
+
+        Ok(self.inner.seek(SeekFrom::Start(self.mark + target))? - self.mark)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{Fixture, FixtureEncoding};
+
+    #[test]
+    fn test_bom() {
+        let walk = |encoding: FixtureEncoding, direction: &str| -> Vec<String> {
+            Fixture::lines(["één", "twee"])
+                .encoding(encoding)
+                .bom(true)
+                .create()
+                .unwrap()
+                .builder()
+                .position(if direction == "forward" {
+                    "start"
+                } else {
+                    "end"
+                })
+                .direction(direction)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+
+        for encoding in [
+            FixtureEncoding::Utf8,
+            FixtureEncoding::Utf16Le,
+            FixtureEncoding::Utf16Be,
+        ] {
+            assert_eq!(walk(encoding, "forward"), vec!["één", "twee"]);
+            assert_eq!(walk(encoding, "backward"), vec!["twee", "één"]);
+        }
+    }
+}
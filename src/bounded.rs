@@ -0,0 +1,75 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+// Bounded presents only the first len bytes of a reader, so that a reverse
+// reader seeking to the "end" starts at a chosen offset instead. A window
+// also hides the bytes before its start, which becomes offset 0.
+pub(crate) struct Bounded<R> {
+    inner: R,
+    start: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<R: Read + Seek> Bounded<R> {
+    pub(crate) fn new(inner: R, len: u64) -> io::Result<Self> {
+        Bounded::window(inner, 0, len)
+    }
+
+    pub(crate) fn window(mut inner: R, start: u64, end: u64) -> io::Result<Self> {
+        inner.seek(SeekFrom::Start(start))?;
+        Ok(Bounded {
+            inner,
+            start,
+            len: end.saturating_sub(start),
+            pos: 0,
+        })
+    }
+}
+
+impl<R: Read> Read for Bounded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos) as usize;
+        let want = buf.len().min(remaining);
+        let read = self.inner.read(&mut buf[..want])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for Bounded<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek position."))?;
+
+        self.pos = self.inner.seek(SeekFrom::Start(self.start + target))? - self.start;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_bounded() {
+        let mut bounded = Bounded::new(Cursor::new(b"hello world"), 5).unwrap();
+        assert_eq!(bounded.seek(SeekFrom::End(-2)).unwrap(), 3);
+
+        let mut contents = String::new();
+        bounded.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "lo");
+
+        let mut window = Bounded::window(Cursor::new(b"hello world"), 6, 9).unwrap();
+        assert_eq!(window.seek(SeekFrom::End(-1)).unwrap(), 2);
+        assert!(window.seek(SeekFrom::Current(-3)).is_err());
+
+        let mut contents = String::new();
+        window.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "r");
+    }
+}
@@ -0,0 +1,155 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::{collections::BTreeMap, time::Duration};
+
+static ISO_8601: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(\d{4})-(\d{2})-(\d{2})[T ](\d{2}):(\d{2}):(\d{2})(?:[.,]\d+)?(Z|[+-]\d{2}:?\d{2})?",
+    )
+    .unwrap()
+});
+static COMMON_LOG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(\d{2})/([A-Z][a-z]{2})/(\d{4}):(\d{2}):(\d{2}):(\d{2}) ([+-]\d{4})").unwrap()
+});
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+// TimeBucket is the number of lines whose timestamp falls in the window
+// starting at start (Unix seconds), for one key when bucketing by key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeBucket {
+    pub start: i64,
+    pub key: Option<String>,
+    pub count: u64,
+}
+
+// Finds the first ISO 8601 (2023-01-02T03:04:05Z) or Common Log Format
+// (02/Jan/2023:03:04:05 +0000) timestamp in line and returns it as Unix
+// seconds. Timestamps without an offset are taken as UTC.
+pub fn parse_timestamp(line: &str) -> Option<i64> {
+    if let Some(caps) = ISO_8601.captures(line) {
+        let num = |idx: usize| caps[idx].parse::<i64>().ok();
+        let offset = match caps.get(7).map(|m| m.as_str()) {
+            None | Some("Z") => 0,
+            Some(offset) => parse_offset(offset)?,
+        };
+        return Some(to_unix(num(1)?, num(2)?, num(3)?, num(4)?, num(5)?, num(6)?)? - offset);
+    }
+
+    let caps = COMMON_LOG.captures(line)?;
+    let num = |idx: usize| caps[idx].parse::<i64>().ok();
+    let month = MONTHS.iter().position(|m| *m == &caps[2])? as i64 + 1;
+    let offset = parse_offset(&caps[7])?;
+    Some(to_unix(num(3)?, month, num(1)?, num(4)?, num(5)?, num(6)?)? - offset)
+}
+
+// Parses +HH:MM, +HHMM or -HHMM into seconds east of UTC.
+fn parse_offset(offset: &str) -> Option<i64> {
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let digits: String = offset[1..].chars().filter(|c| *c != ':').collect();
+    let hours: i64 = digits.get(..2)?.parse().ok()?;
+    let minutes: i64 = digits.get(2..4)?.parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn to_unix(year: i64, month: i64, day: i64, hour: i64, min: i64, sec: i64) -> Option<i64> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || min > 59 || sec > 60 {
+        return None;
+    }
+    // Days from the civil calendar date, after Howard Hinnant's algorithm
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    Some(days * 86400 + hour * 3600 + min * 60 + sec)
+}
+
+// Counts lines per time window, and per key when key_fn returns one, in
+// order of window start then key. Lines without a recognizable timestamp
+// are skipped. Memory grows with the number of windows and keys, not lines.
+pub fn bucket_by_time<I, F>(lines: I, window: Duration, mut key_fn: F) -> Vec<TimeBucket>
+where
+    I: IntoIterator<Item = String>,
+    F: FnMut(&str) -> Option<&str>,
+{
+    let width = window.as_secs().max(1) as i64;
+    let mut counts: BTreeMap<(i64, Option<String>), u64> = BTreeMap::new();
+    for line in lines {
+        let Some(timestamp) = parse_timestamp(&line) else {
+            continue;
+        };
+        let start = timestamp.div_euclid(width) * width;
+        let key = key_fn(&line).map(str::to_string);
+        *counts.entry((start, key)).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|((start, key), count)| TimeBucket { start, key, count })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WalkExt;
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("at 1970-01-01T00:01:00Z ok"), Some(60));
+        assert_eq!(parse_timestamp("2023-01-02 03:04:05.123"), Some(1672628645));
+        assert_eq!(
+            parse_timestamp("2023-01-02T05:04:05+02:00"),
+            Some(1672628645)
+        );
+        assert_eq!(
+            parse_timestamp(r#"1.2.3.4 - - [02/Jan/2023:03:04:05 +0000] "GET /" 200"#),
+            Some(1672628645)
+        );
+        assert_eq!(parse_timestamp("no time here"), None);
+    }
+
+    #[test]
+    fn test_bucket_by_time() {
+        let lines = [
+            "2023-01-02T03:04:05Z GET /a",
+            "2023-01-02T03:04:59Z POST /b",
+            "garbage",
+            "2023-01-02T03:05:00Z GET /c",
+        ]
+        .map(String::from);
+
+        let per_minute = lines
+            .clone()
+            .into_iter()
+            .bucket_by_time(Duration::from_secs(60), |_| None);
+        assert_eq!(
+            per_minute,
+            vec![
+                TimeBucket {
+                    start: 1672628640,
+                    key: None,
+                    count: 2
+                },
+                TimeBucket {
+                    start: 1672628700,
+                    key: None,
+                    count: 1
+                },
+            ]
+        );
+
+        let per_method = bucket_by_time(lines, Duration::from_secs(3600), |line| {
+            line.split(' ').nth(1)
+        });
+        let counts: Vec<_> = per_method
+            .iter()
+            .map(|b| (b.key.as_deref(), b.count))
+            .collect();
+        assert_eq!(counts, vec![(Some("GET"), 2), (Some("POST"), 1)]);
+    }
+}
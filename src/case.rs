@@ -0,0 +1,131 @@
+use crate::Error;
+use regex::{Regex, RegexBuilder};
+use regex_syntax::hir::{ClassUnicode, ClassUnicodeRange};
+
+// Case sets how the matching APIs compare letter case. Ignoring it goes by
+// Unicode simple case folding, the way the regex crate does, so "ÉTÉ"
+// matches "été" but "STRASSE" doesn't match "straße".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Case {
+    #[default]
+    Sensitive,
+    Insensitive,
+    // Ignores case unless a pattern has an uppercase letter, like ripgrep's
+    // and less's smart case
+    Smart,
+}
+
+impl Case {
+    // Whether case is ignored when matching patterns.
+    pub fn ignores_case<'a, I: IntoIterator<Item = &'a str>>(self, patterns: I) -> bool {
+        match self {
+            Case::Sensitive => false,
+            Case::Insensitive => true,
+            Case::Smart => !patterns.into_iter().any(has_uppercase),
+        }
+    }
+
+    // Compiles pattern into a regex comparing case this way, e.g. for
+    // WalkExt::matching or Rewrite::new.
+    pub fn regex(self, pattern: &str) -> Result<Regex, Error> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(self.ignores_case([pattern]))
+            .build()?;
+        Ok(regex)
+    }
+}
+
+// Folds text so strings equal ignoring case fold to the same string. Every
+// char maps to the least char of its simple case folding class, taken from
+// the regex crate's own tables so the two always agree.
+pub(crate) fn fold(text: &str) -> String {
+    text.chars().map(fold_char).collect()
+}
+
+fn fold_char(c: char) -> char {
+    // ASCII letters fold only with their other case and chars past ASCII,
+    // such as the Kelvin sign, so the uppercase letter is the least
+    if c.is_ascii() {
+        return c.to_ascii_uppercase();
+    }
+    let mut class = ClassUnicode::new([ClassUnicodeRange::new(c, c)]);
+    class.case_fold_simple();
+    class.ranges()[0].start()
+}
+
+// Escapes such as \W or \S name classes rather than letters, and those
+// such as \p{Lu} or \x{1F} spell out a class name or code point, so none of
+// them count as uppercase.
+fn has_uppercase(pattern: &str) -> bool {
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('p' | 'P' | 'x' | 'u' | 'U') if chars.peek() == Some(&'{') => {
+                    chars.by_ref().find(|&c| c == '}');
+                }
+                Some('p' | 'P') => {
+                    chars.next();
+                }
+                Some('x' | 'u' | 'U') => while chars.next_if(char::is_ascii_hexdigit).is_some() {},
+                _ => {}
+            },
+            c if c.is_uppercase() => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Keywords, OpenerBuilder, WalkExt};
+
+    #[test]
+    fn test_case_modes() -> Result<(), Error> {
+        assert!(Case::Smart.ignores_case(["error"]));
+        assert!(!Case::Smart.ignores_case(["error", "OOM"]));
+        assert!(Case::Smart.ignores_case([r"\Werror\S"]));
+        assert!(Case::Smart.ignores_case([r"\p{Lu}+ \PL \x{1F} \u00E9"]));
+        assert!(!Case::Smart.ignores_case([r"\p{Lu} Error"]));
+        assert_eq!(fold("ÉTÉ ΣΟΦΌΣ"), fold("été σοφός"));
+
+        // Folding agrees with the regex crate's (?i)
+        for (a, b) in [
+            ("k", "\u{212a}"),
+            ("s", "ſ"),
+            ("İ", "i"),
+            ("ß", "ẞ"),
+            ("straße", "STRASSE"),
+            ("ǅ", "ǆ"),
+        ] {
+            let regex = Case::Insensitive.regex(&format!("^{}$", regex::escape(a)))?;
+            assert_eq!(fold(a) == fold(b), regex.is_match(b), "{} {}", a, b);
+        }
+
+        let opener = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .build()
+            .unwrap();
+        let matched: Vec<String> = opener
+            .open()?
+            .matching(Case::Smart.regex("H|W")?)
+            .collect::<Result<_, _>>()?;
+        assert!(matched.is_empty());
+        let matched: Vec<String> = opener
+            .open()?
+            .matching(Case::Smart.regex("h|w")?)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(matched, vec!["hello", "there", "whats"]);
+
+        let keywords = Keywords::with_case(["HELLO", "Up"], Case::Insensitive)?;
+        let hits: Vec<String> = opener
+            .open()?
+            .keyword_hits(keywords)
+            .map(|hit| hit.map(|hit| hit.line))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(hits, vec!["hello", "up"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{AtomicFile, Error};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Seek, SeekFrom},
+    path::Path,
+};
+
+// Reclaim selects how space taken by already-consumed bytes is given back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reclaim {
+    // Deallocates the consumed range in place. Offsets stay valid and the
+    // range reads back as zeros. Only supported on Linux.
+    PunchHole,
+    // Rewrites the file without the consumed prefix, shifting all offsets
+    // down. Writers must be paused while this runs.
+    TruncatePrefix,
+}
+
+// Frees the first consumed bytes of the file at path once they have been
+// processed, turning an append-only file into a queue that doesn't grow forever.
+pub fn reclaim<P: AsRef<Path>>(path: P, consumed: u64, mode: Reclaim) -> Result<(), Error> {
+    match mode {
+        Reclaim::PunchHole => punch_hole(path.as_ref(), consumed),
+        Reclaim::TruncatePrefix => truncate_prefix(path.as_ref(), consumed),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn punch_hole(path: &Path, len: u64) -> Result<(), Error> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = OpenOptions::new().write(true).open(path)?;
+    let ret = unsafe {
+        libc::fallocate(
+            file.as_raw_fd(),
+            libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+            0,
+            len as libc::off_t,
+        )
+    };
+    if ret != 0 {
+        return Err(Error::File(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn punch_hole(_path: &Path, _len: u64) -> Result<(), Error> {
+    Err(Error::File(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Punching holes is only supported on Linux.",
+    )))
+}
+
+fn truncate_prefix(path: &Path, len: u64) -> Result<(), Error> {
+    let mut source = File::open(path)?;
+    source.seek(SeekFrom::Start(len))?;
+
+    let mut target = AtomicFile::create(path)?;
+    io::copy(&mut source, &mut target)?;
+    target.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+    use std::fs;
+
+    #[test]
+    fn test_truncate_prefix() {
+        let fixture = Fixture::numbered(3).create().unwrap();
+        reclaim(fixture.path(), 7, Reclaim::TruncatePrefix).unwrap();
+        assert_eq!(
+            fs::read_to_string(fixture.path()).unwrap(),
+            "line 2\nline 3\n"
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_punch_hole() {
+        let fixture = Fixture::numbered(3).create().unwrap();
+        // Some filesystems (e.g. overlay or tmpfs variants) don't support
+        // it, which is the only error allowed
+        match reclaim(fixture.path(), 7, Reclaim::PunchHole) {
+            Ok(()) => {}
+            Err(Error::File(err)) if err.raw_os_error() == Some(libc::EOPNOTSUPP) => {
+                eprintln!("skipping test_punch_hole: the temp filesystem can't punch holes");
+                return;
+            }
+            Err(err) => panic!("punching a hole failed: {}", err),
+        }
+        let contents = fs::read(fixture.path()).unwrap();
+        assert_eq!(contents.len(), 21);
+        assert_eq!(&contents[..7], &[0; 7]);
+        assert_eq!(&contents[7..], b"line 2\nline 3\n");
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    #[test]
+    fn test_punch_hole() {
+        let fixture = Fixture::numbered(3).create().unwrap();
+        assert!(matches!(
+            reclaim(fixture.path(), 7, Reclaim::PunchHole),
+            Err(Error::File(err)) if err.kind() == io::ErrorKind::Unsupported
+        ));
+    }
+}
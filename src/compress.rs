@@ -0,0 +1,229 @@
+use crate::{AtomicFile, Error};
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+
+// Codec selects the compression format used when writing lines out, and is
+// detected when reading compressed files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+    #[cfg(feature = "xz")]
+    Xz,
+}
+
+// The longest magic number of any codec: bzip2's, counting the block magic
+// after the stream header
+pub(crate) const MAGIC_LEN: usize = 10;
+
+// What follows a bzip2 stream header: the magic of its first block, or of
+// the end of the stream when it is empty
+#[cfg(feature = "bzip2")]
+const BZIP2_BLOCK_MAGIC: [&[u8]; 2] = [b"1AY&SY", &[0x17, 0x72, 0x45, 0x38, 0x50, 0x90]];
+
+impl Codec {
+    // Recognizes a codec by the magic number at the start of a file.
+    pub fn detect(magic: &[u8]) -> Option<Codec> {
+        #[cfg(feature = "gzip")]
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            return Some(Codec::Gzip);
+        }
+        #[cfg(feature = "zstd")]
+        if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Some(Codec::Zstd);
+        }
+        #[cfg(feature = "bzip2")]
+        // "BZh" alone is too likely to start a line of text, so the block
+        // size digit and block magic after it have to match too
+        if let [b'B', b'Z', b'h', b'1'..=b'9', block @ ..] = magic {
+            if BZIP2_BLOCK_MAGIC
+                .iter()
+                .any(|magic| block.starts_with(magic))
+            {
+                return Some(Codec::Bzip2);
+            }
+        }
+        #[cfg(feature = "xz")]
+        if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            return Some(Codec::Xz);
+        }
+        None
+    }
+
+    // Recognizes a codec by the extension of a file name, such as ".zst".
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<Codec> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "gzip")]
+            "gz" | "gzip" => Some(Codec::Gzip),
+            #[cfg(feature = "zstd")]
+            "zst" | "zstd" => Some(Codec::Zstd),
+            #[cfg(feature = "bzip2")]
+            "bz2" => Some(Codec::Bzip2),
+            #[cfg(feature = "xz")]
+            "xz" => Some(Codec::Xz),
+            _ => None,
+        }
+    }
+
+    // Wraps input in a reader of its decompressed contents. Concatenated
+    // streams, as left by appending to a compressed file, are read as one.
+    pub(crate) fn decoder<R: Read + Send + 'static>(
+        self,
+        input: R,
+    ) -> Result<Box<dyn Read + Send>, Error> {
+        Ok(match self {
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => Box::new(flate2::read::MultiGzDecoder::new(input)),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => Box::new(zstd::Decoder::new(input)?),
+            #[cfg(feature = "bzip2")]
+            Codec::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(input)),
+            #[cfg(feature = "xz")]
+            Codec::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(input)),
+        })
+    }
+}
+
+// Streams lines into a compressed file one at a time, returning how many were written.
+pub(crate) fn write_compressed<I, P>(lines: I, path: P, codec: Codec) -> Result<u64, Error>
+where
+    I: Iterator<Item = Result<String, Error>>,
+    P: AsRef<Path>,
+{
+    let file = AtomicFile::create(path)?;
+    match codec {
+        #[cfg(feature = "gzip")]
+        Codec::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            let written = write_lines(&mut encoder, lines)?;
+            encoder.finish()?.commit()?;
+            Ok(written)
+        }
+        #[cfg(feature = "zstd")]
+        Codec::Zstd => {
+            let mut encoder = zstd::Encoder::new(file, 0)?;
+            let written = write_lines(&mut encoder, lines)?;
+            encoder.finish()?.commit()?;
+            Ok(written)
+        }
+        #[cfg(feature = "bzip2")]
+        Codec::Bzip2 => {
+            let mut encoder = bzip2::write::BzEncoder::new(file, bzip2::Compression::default());
+            let written = write_lines(&mut encoder, lines)?;
+            encoder.finish()?.commit()?;
+            Ok(written)
+        }
+        #[cfg(feature = "xz")]
+        Codec::Xz => {
+            let mut encoder = xz2::write::XzEncoder::new(file, 6);
+            let written = write_lines(&mut encoder, lines)?;
+            encoder.finish()?.commit()?;
+            Ok(written)
+        }
+    }
+}
+
+fn write_lines<W: Write, I: Iterator<Item = Result<String, Error>>>(
+    out: &mut W,
+    lines: I,
+) -> Result<u64, Error> {
+    let mut written = 0;
+    for line in lines {
+        out.write_all(line?.as_bytes())?;
+        out.write_all(b"\n")?;
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fixtures::Fixture, OpenerBuilder};
+    use std::io::Read;
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_write_gzip() {
+        let output = Fixture::lines(["unused"]).create().unwrap();
+        let written = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .build()
+            .unwrap()
+            .write_compressed(output.path(), super::Codec::Gzip)
+            .unwrap();
+
+        let mut contents = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(output.path()).unwrap())
+            .read_to_string(&mut contents)
+            .unwrap();
+
+        assert_eq!(written, 4);
+        assert_eq!(contents, "hello\nthere\nwhats\nup\n");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_write_zstd() {
+        let output = Fixture::lines(["unused"]).create().unwrap();
+        OpenerBuilder::default()
+            .path("./testfiles/2.txt")
+            .build()
+            .unwrap()
+            .write_compressed(output.path(), super::Codec::Zstd)
+            .unwrap();
+
+        let mut contents = String::new();
+        zstd::Decoder::new(std::fs::File::open(output.path()).unwrap())
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+
+        assert_eq!(contents, "am i clear now\n");
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn test_write_bzip2() {
+        let output = Fixture::lines(["unused"]).create().unwrap();
+        OpenerBuilder::default()
+            .path("./testfiles/2.txt")
+            .build()
+            .unwrap()
+            .write_compressed(output.path(), super::Codec::Bzip2)
+            .unwrap();
+
+        let mut contents = String::new();
+        bzip2::read::BzDecoder::new(std::fs::File::open(output.path()).unwrap())
+            .read_to_string(&mut contents)
+            .unwrap();
+
+        assert_eq!(contents, "am i clear now\n");
+    }
+
+    #[cfg(feature = "xz")]
+    #[test]
+    fn test_write_xz() {
+        let output = Fixture::lines(["unused"]).create().unwrap();
+        OpenerBuilder::default()
+            .path("./testfiles/2.txt")
+            .build()
+            .unwrap()
+            .write_compressed(output.path(), super::Codec::Xz)
+            .unwrap();
+
+        let mut contents = String::new();
+        xz2::read::XzDecoder::new(std::fs::File::open(output.path()).unwrap())
+            .read_to_string(&mut contents)
+            .unwrap();
+
+        assert_eq!(contents, "am i clear now\n");
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{Direction, Error, LineEnding, Position};
+
+// ConformanceCase is one walk of the conformance suite: a file, how to walk
+// it and what the walk has to yield
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformanceCase {
+    pub contents: Vec<u8>,
+    pub line_ending: LineEnding,
+    pub position: Position,
+    pub direction: Direction,
+    pub max_position: Option<Position>,
+    // The lines in the order they're yielded, or None if opening the walk
+    // has to fail
+    pub expected: Option<Vec<String>>,
+}
+
+// ConformanceFailure is a case whose walk didn't yield what was expected,
+// along with what it yielded or the error it failed with
+#[derive(Debug)]
+pub struct ConformanceFailure {
+    pub case: ConformanceCase,
+    pub got: Result<Vec<String>, Error>,
+}
+
+const LINES: [&str; 4] = ["one", "", "three", "four"];
+
+// Every combination of position, direction, max position and line ending
+// the suite covers, over a four line file with and without a trailing line
+// break, and an empty one.
+pub fn cases() -> Vec<ConformanceCase> {
+    let mut files = vec![(vec![], LineEnding::Lf, 0)];
+    let line_endings = [
+        (LineEnding::Lf, "\n"),
+        (LineEnding::CrLf, "\r\n"),
+        (LineEnding::Cr, "\r"),
+    ];
+    for (line_ending, terminator) in line_endings {
+        let joined = LINES.join(terminator);
+        files.push((joined.clone().into_bytes(), line_ending, LINES.len()));
+        files.push(((joined + terminator).into_bytes(), line_ending, LINES.len()));
+    }
+    let positions = [
+        Position::Start,
+        Position::Middle(2),
+        Position::Middle(4),
+        Position::End,
+        Position::FromEnd(2),
+    ];
+    let bounds = [
+        None,
+        Some(Position::Start),
+        Some(Position::Middle(3)),
+        Some(Position::End),
+        Some(Position::FromEnd(1)),
+    ];
+
+    let mut cases = vec![];
+    for (contents, line_ending, len) in files {
+        for position in positions {
+            for direction in [Direction::Forward, Direction::Backward] {
+                for max_position in bounds {
+                    cases.push(ConformanceCase {
+                        contents: contents.clone(),
+                        line_ending,
+                        position,
+                        direction,
+                        max_position,
+                        expected: expected(&LINES[..len], position, direction, max_position),
+                    });
+                }
+            }
+        }
+    }
+    cases
+}
+
+// Walks every case with walk, which opens the case's contents with the
+// backend under test, and returns the cases it got wrong.
+pub fn run<F>(mut walk: F) -> Vec<ConformanceFailure>
+where
+    F: FnMut(&ConformanceCase) -> Result<Vec<String>, Error>,
+{
+    cases()
+        .into_iter()
+        .filter_map(|case| {
+            let got = walk(&case);
+            let passed = match (&got, &case.expected) {
+                (Ok(lines), Some(expected)) => lines == expected,
+                (Err(_), None) => true,
+                _ => false,
+            };
+            (!passed).then_some(ConformanceFailure { case, got })
+        })
+        .collect()
+}
+
+// What walking lines should yield, worked out from the line numbers rather
+// than by walking.
+fn expected(
+    lines: &[&str],
+    position: Position,
+    direction: Direction,
+    max_position: Option<Position>,
+) -> Option<Vec<String>> {
+    let total = lines.len();
+    let line_number = |position: Position| match position.resolve(total) {
+        Position::Start => 1,
+        Position::Middle(n) => n,
+        _ => total,
+    };
+    let start = line_number(position);
+    let bound = max_position.map(|max| match max {
+        Position::Start => 0,
+        max => line_number(max),
+    });
+
+    match (direction, position) {
+        (Direction::Backward, Position::Start) | (Direction::Forward, Position::End) => {
+            return None
+        }
+        _ => {}
+    }
+    let numbers: Vec<usize> = match (direction, bound) {
+        (Direction::Forward, Some(bound)) if bound < start => return None,
+        (Direction::Backward, Some(bound)) if bound > start => return None,
+        (Direction::Forward, bound) => (start..=bound.unwrap_or(total).min(total)).collect(),
+        (Direction::Backward, bound) => (bound.unwrap_or(1).max(1)..=start.min(total))
+            .rev()
+            .collect(),
+    };
+    Some(
+        numbers
+            .into_iter()
+            .map(|n| lines[n - 1].to_string())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, WalkConfig};
+    use std::{fs, io::Cursor};
+
+    fn config(case: &ConformanceCase) -> WalkConfig {
+        WalkConfig {
+            position: case.position,
+            direction: case.direction,
+            max_position: case.max_position,
+            ..WalkConfig::default()
+        }
+    }
+
+    fn walk_file(case: &ConformanceCase, config: WalkConfig) -> Result<Vec<String>, Error> {
+        let fixture = Fixture::lines(["unused"]).create()?;
+        fs::write(fixture.path(), &case.contents)?;
+        config.open(fixture.path())?.collect()
+    }
+
+    #[test]
+    fn test_conformance() {
+        let failures = run(|case| {
+            config(case)
+                .open_reader(Cursor::new(case.contents.clone()))?
+                .collect()
+        });
+        assert!(failures.is_empty(), "{:#?}", failures);
+
+        let failures = run(|case| walk_file(case, config(case)));
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_conformance_mmap() {
+        let failures = run(|case| {
+            let config = WalkConfig {
+                backend: crate::Backend::Mmap,
+                ..config(case)
+            };
+            walk_file(case, config)
+        });
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_conformance_gzip() {
+        use std::io::Write;
+
+        let failures = run(|case| {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(&case.contents)?;
+            let compressed = Fixture::lines(["unused"]).create()?;
+            fs::write(compressed.path(), encoder.finish()?)?;
+            config(case).open(compressed.path())?.collect()
+        });
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+}
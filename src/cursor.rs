@@ -0,0 +1,262 @@
+use crate::{
+    bom, compute_offset, offset_from_end, snap_backward, Error, LineEnding, LineIndex, Position,
+    Utf8Policy,
+};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    sync::Arc,
+};
+
+// Cursor sits between two lines of a file it keeps open, as returned by
+// Opener::cursor, and moves over them one at a time in either direction or
+// jumps with seek. Only the lines moved over are read, so a viewer can page
+// through a file of any size without walking it.
+pub struct Cursor {
+    input: BufReader<bom::Unmarked<File>>,
+    line_ending: LineEnding,
+    invalid_utf8: Utf8Policy,
+    // Looked up by seek in place of scanning, while it fits the file
+    index: Option<Arc<LineIndex>>,
+    // Where the line after the cursor starts, counting from after a byte
+    // order mark like Byte positions do
+    offset: u64,
+    // Whether input is already at offset, as it is after next_line
+    in_place: bool,
+}
+
+impl Cursor {
+    pub(crate) fn new(
+        mut input: File,
+        line_ending: LineEnding,
+        invalid_utf8: Utf8Policy,
+        index: Option<Arc<LineIndex>>,
+        position: Position,
+    ) -> Result<Self, Error> {
+        let mark = bom::read(&mut input)?.map_or(0, |(_, mark)| mark);
+        let mut cursor = Cursor {
+            input: BufReader::new(bom::Unmarked::new(input, mark)?),
+            line_ending,
+            invalid_utf8,
+            index,
+            offset: 0,
+            in_place: false,
+        };
+        cursor.seek(position)?;
+        Ok(cursor)
+    }
+
+    // The byte offset of the line after the cursor, or the length of the
+    // file at its end.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // Returns the line after the cursor and moves past it, or None at the
+    // end of the file. Lines appended since the end was reached are picked
+    // up by the next call.
+    pub fn next_line(&mut self) -> Result<Option<String>, Error> {
+        if !self.in_place {
+            self.input.seek(SeekFrom::Start(self.offset))?;
+            self.in_place = true;
+        }
+        let mut line = vec![];
+        let read = self
+            .input
+            .read_until(self.line_ending.delimiter(), &mut line)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        // A line that fails to decode is left after the cursor
+        let line = self.decode(line, self.offset);
+        match line {
+            Ok(_) => self.offset += read as u64,
+            Err(_) => self.in_place = false,
+        }
+        line.map(Some)
+    }
+
+    // Returns the line before the cursor and moves back over it, or None at
+    // the start of the file.
+    pub fn prev_line(&mut self) -> Result<Option<String>, Error> {
+        if self.offset == 0 {
+            return Ok(None);
+        }
+        // The byte before the cursor ends the line before it, unless the
+        // cursor is at the end of a file without a final delimiter
+        let start = snap_backward(
+            &mut self.input,
+            self.offset - 1,
+            self.line_ending.delimiter(),
+        )?;
+        self.input.seek(SeekFrom::Start(start))?;
+        let mut line = vec![];
+        (&mut self.input)
+            .take(self.offset - start)
+            .read_to_end(&mut line)?;
+        // Reading left input at the old offset, where the cursor stays if
+        // the line fails to decode
+        let line = self.decode(line, start);
+        self.in_place = line.is_err();
+        if line.is_ok() {
+            self.offset = start;
+        }
+        line.map(Some)
+    }
+
+    // Moves the cursor to the start of the line at position. Byte and
+    // Percent positions move it to the start of the line they fall in, and
+    // End to the start of the last line; lines past the end move it to the
+    // end of the file. Positions by line are looked up in the index set on
+    // the opener, if it still fits the file.
+    pub fn seek(&mut self, position: Position) -> Result<(), Error> {
+        let delimiter = self.line_ending.delimiter();
+        let len = self.input.seek(SeekFrom::End(0))?;
+        let index = self
+            .index
+            .as_deref()
+            .filter(|index| index.fits(len, delimiter));
+        self.offset = match (position, index) {
+            (Position::Start, _) => 0,
+            (Position::Middle(line), Some(index)) => index.offset(line),
+            (Position::End, Some(index)) => index.offset(index.line_count()),
+            (Position::FromEnd(lines), Some(index)) => {
+                index.offset((index.line_count() + 1).saturating_sub(lines).max(1))
+            }
+            (position, _) => self.scan_for(position, len)?,
+        };
+        self.in_place = false;
+        Ok(())
+    }
+
+    // Finds where the line at position starts by scanning the file.
+    fn scan_for(&mut self, position: Position, len: u64) -> Result<u64, Error> {
+        let delimiter = self.line_ending.delimiter();
+        Ok(match position {
+            Position::Start => 0,
+            Position::Middle(_) => compute_offset(&mut self.input, position, delimiter)? as u64,
+            Position::End => offset_from_end(&mut self.input, 1, delimiter)?,
+            Position::FromEnd(lines) => offset_from_end(&mut self.input, lines, delimiter)?,
+            Position::Byte(_) | Position::Percent(_) => {
+                snap_backward(&mut self.input, position.byte_offset(len), delimiter)?
+            }
+        })
+    }
+
+    fn decode(&self, mut line: Vec<u8>, offset: u64) -> Result<String, Error> {
+        self.line_ending.strip(&mut line);
+        self.invalid_utf8
+            .decode(line)
+            .ok_or(Error::InvalidUtf8At { offset })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fixtures::Fixture, Error, LineIndex, OpenerBuilder, Position};
+    use std::{fs, io::Write};
+
+    #[test]
+    fn test_cursor() {
+        // "hello\nthere\nwhats\nup"
+        let mut cursor = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .build()
+            .unwrap()
+            .cursor()
+            .unwrap();
+        assert_eq!(cursor.prev_line().unwrap(), None);
+        assert_eq!(cursor.next_line().unwrap().unwrap(), "hello");
+        assert_eq!(cursor.next_line().unwrap().unwrap(), "there");
+        assert_eq!(cursor.offset(), 12);
+        assert_eq!(cursor.prev_line().unwrap().unwrap(), "there");
+        assert_eq!(cursor.prev_line().unwrap().unwrap(), "hello");
+        assert_eq!(cursor.offset(), 0);
+
+        cursor.seek(Position::End).unwrap();
+        assert_eq!(cursor.next_line().unwrap().unwrap(), "up");
+        assert_eq!(cursor.next_line().unwrap(), None);
+        assert_eq!(cursor.offset(), 20);
+        assert_eq!(cursor.prev_line().unwrap().unwrap(), "up");
+        assert_eq!(cursor.prev_line().unwrap().unwrap(), "whats");
+        cursor.seek(Position::Byte(8)).unwrap();
+        assert_eq!(cursor.offset(), 6);
+        cursor.seek(Position::Middle(3)).unwrap();
+        assert_eq!(cursor.next_line().unwrap().unwrap(), "whats");
+        cursor.seek(Position::FromEnd(3)).unwrap();
+        assert_eq!(cursor.prev_line().unwrap().unwrap(), "hello");
+
+        // Opened at the walk's position, stripping CRLF and following the
+        // file as it grows
+        let fixture = Fixture::lines(["one", "two"]).create().unwrap();
+        fs::write(fixture.path(), b"one\r\ntwo\r\n").unwrap();
+        let mut cursor = fixture
+            .builder()
+            .position(Position::End)
+            .build()
+            .unwrap()
+            .cursor()
+            .unwrap();
+        assert_eq!(cursor.next_line().unwrap().unwrap(), "two");
+        assert_eq!(cursor.next_line().unwrap(), None);
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(fixture.path())
+            .unwrap();
+        file.write_all(b"three\r\n\xff\r\n").unwrap();
+        assert_eq!(cursor.next_line().unwrap().unwrap(), "three");
+        assert!(matches!(
+            cursor.next_line(),
+            Err(Error::InvalidUtf8At { offset: 17 })
+        ));
+        assert_eq!(cursor.prev_line().unwrap().unwrap(), "three");
+    }
+
+    #[test]
+    fn test_get_line() {
+        let fixture = Fixture::numbered(100).create().unwrap();
+        let opener = fixture.opener();
+        assert_eq!(opener.get_line(1).unwrap().unwrap(), "line 1");
+        assert_eq!(opener.get_line(57).unwrap().unwrap(), "line 57");
+        assert_eq!(opener.get_line(100).unwrap().unwrap(), "line 100");
+        assert_eq!(opener.get_line(101).unwrap(), None);
+        assert_eq!(opener.get_line(0).unwrap(), None);
+
+        // Looked up in the index rather than the file, so one of the same
+        // size with different lines is read where it says
+        let other = Fixture::lines(["abc", "d"]).create().unwrap();
+        let same_size = Fixture::lines(["ab", "cd"]).create().unwrap();
+        let opener = same_size
+            .builder()
+            .index(LineIndex::build(other.path()).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(opener.get_line(2).unwrap().unwrap(), "d");
+        let mut cursor = opener.cursor().unwrap();
+        cursor.seek(Position::End).unwrap();
+        assert_eq!(cursor.offset(), 4);
+    }
+
+    #[test]
+    fn test_around() {
+        let fixture = Fixture::numbered(10).create().unwrap();
+        let opener = fixture.opener();
+        let around = |line_no: usize, before: usize, after: usize| -> Vec<usize> {
+            opener
+                .around(line_no, before, after)
+                .unwrap()
+                .into_iter()
+                .map(|(number, line)| {
+                    assert_eq!(line, format!("line {}", number));
+                    number
+                })
+                .collect()
+        };
+        assert_eq!(around(5, 2, 1), vec![3, 4, 5, 6]);
+        assert_eq!(around(2, 3, 0), vec![1, 2]);
+        assert_eq!(around(9, 0, 3), vec![9, 10]);
+        assert_eq!(around(1, 0, 0), vec![1]);
+        assert!(around(11, 3, 3).is_empty());
+        assert!(around(0, 3, 3).is_empty());
+    }
+}
@@ -0,0 +1,285 @@
+use crate::{
+    bom, buffer_capacity, compress::MAGIC_LEN, throttle::Throttled, Codec, Direction, Error,
+    LineEnding, LineIter, Position, TextEncoding, WalkOptions, WalkPlan,
+};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// Returns a reader of the decompressed contents of input when it is
+// compressed, judged by its magic number. The extension is only trusted
+// for files too short to hold a magic number, so a plain text file named
+// like a compressed one is still read as text.
+pub(crate) fn decoder(
+    mut input: &File,
+    path: &Path,
+) -> Result<Option<Box<dyn Read + Send>>, Error> {
+    let mut magic = Vec::with_capacity(MAGIC_LEN);
+    input.take(MAGIC_LEN as u64).read_to_end(&mut magic)?;
+    input.seek(SeekFrom::Start(0))?;
+    let codec = match magic.len() < MAGIC_LEN {
+        true => Codec::detect(&magic).or_else(|| Codec::from_extension(path)),
+        false => Codec::detect(&magic),
+    };
+    match codec {
+        Some(codec) => codec.decoder(input.try_clone()?).map(Some),
+        None => Ok(None),
+    }
+}
+
+// Walks forward through the decompressed contents while a background
+// thread keeps up to capacity chunks decompressed ahead, so decompressing
+// and reading lines overlap. The line count isn't known up front, so the
+// walk simply ends with the contents.
+pub(crate) fn walk_ahead(
+    decoder: Box<dyn Read + Send>,
+    capacity: usize,
+    position: Position,
+    max_position: Option<Position>,
+    options: WalkOptions,
+) -> Result<LineIter, Error> {
+    let first_line = match position {
+        Position::Middle(line) => line,
+        _ => 1,
+    };
+    let last_line = match max_position {
+        Some(Position::Start) => 0,
+        Some(Position::Middle(line)) => line,
+        _ => usize::MAX,
+    };
+    if last_line < first_line {
+        return Err(Error::MaxLinePosition {
+            pos: first_line,
+            max: last_line,
+            dir: Direction::Forward,
+        });
+    }
+
+    let decoder = Box::new(Throttled::new(decoder, options.nice_io));
+    let ahead = Ahead::spawn(decoder, capacity.max(1));
+    let mut reader = BufReader::with_capacity(buffer_capacity(options.buffer_size), ahead);
+    // A UTF-8 byte order mark is dropped, as it is from plain files
+    if let Some((TextEncoding::Utf8, mark)) = bom::detect(reader.fill_buf()?) {
+        reader.consume(mark as usize);
+    }
+    let line_ending = match options.delimiter {
+        Some(delimiter) => LineEnding::Delimiter(delimiter),
+        None => options
+            .newlines
+            .apply(LineEnding::detect(reader.fill_buf()?)),
+    };
+    let mut offset = 0;
+    for _ in 1..first_line {
+        let read = reader.skip_until(line_ending.delimiter())?;
+        if read == 0 {
+            break;
+        }
+        offset += read as u64;
+    }
+
+    let plan = WalkPlan::streaming(line_ending, first_line, last_line, offset);
+    Ok(plan.into_lines(Box::new(reader), None, options))
+}
+
+// Ahead reads the chunks decompressed by its background thread. The thread
+// stops once the contents are exhausted or the reader is dropped.
+struct Ahead {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Ahead {
+    fn spawn(mut decoder: Box<dyn Read + Send>, capacity: usize) -> Self {
+        let (sender, chunks) = mpsc::sync_channel(capacity);
+        thread::spawn(move || loop {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let chunk = match decoder.read(&mut chunk) {
+                Ok(0) => return,
+                Ok(read) => {
+                    chunk.truncate(read);
+                    Ok(chunk)
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e),
+            };
+            let failed = chunk.is_err();
+            if sender.send(chunk).is_err() || failed {
+                return;
+            }
+        });
+        Ahead {
+            chunks,
+            chunk: vec![],
+            pos: 0,
+        }
+    }
+}
+
+impl Read for Ahead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.chunk.len() {
+            match self.chunks.recv() {
+                Ok(chunk) => self.chunk = chunk?,
+                // The thread is done, so the contents are too
+                Err(_) => return Ok(0),
+            }
+            self.pos = 0;
+        }
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod tests {
+    #[cfg(feature = "gzip")]
+    use crate::OpenerBuilder;
+    use crate::{
+        fixtures::{Fixture, FixtureDir},
+        open_file, open_lines, Codec,
+    };
+    #[cfg(feature = "gzip")]
+    use std::{fs, io::Write};
+
+    #[cfg(feature = "gzip")]
+    fn gzip(text: &str) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_open_gzip() {
+        let fixture = Fixture::lines(["unused"]).create().unwrap();
+        // Two members, as produced by appending to a compressed log
+        let mut contents = gzip("one\ntwo\n");
+        contents.extend(gzip("three\n"));
+        fs::write(fixture.path(), contents).unwrap();
+        let path = fixture.path();
+
+        let lines: Vec<String> = open_file(path, None, None, None).unwrap().collect();
+        assert_eq!(lines, vec!["one", "two", "three"]);
+
+        let lines: Vec<String> = open_file(path, "end", "backward", None).unwrap().collect();
+        assert_eq!(lines, vec!["three", "two", "one"]);
+
+        let lines: Vec<String> = open_file(path, 2, None, None).unwrap().collect();
+        assert_eq!(lines, vec!["two", "three"]);
+    }
+
+    #[test]
+    fn test_open_codecs() {
+        let codecs = [
+            #[cfg(feature = "gzip")]
+            Codec::Gzip,
+            #[cfg(feature = "zstd")]
+            Codec::Zstd,
+            #[cfg(feature = "bzip2")]
+            Codec::Bzip2,
+            #[cfg(feature = "xz")]
+            Codec::Xz,
+        ];
+        let source = Fixture::numbered(3).create().unwrap();
+        for codec in codecs {
+            let compressed = Fixture::lines(["unused"]).create().unwrap();
+            source
+                .opener()
+                .write_compressed(compressed.path(), codec)
+                .unwrap();
+            let path = compressed.path();
+
+            let lines: Vec<String> = open_file(path, "end", "backward", None).unwrap().collect();
+            assert_eq!(lines, vec!["line 3", "line 2", "line 1"], "{:?}", codec);
+        }
+    }
+
+    #[test]
+    fn test_open_plain_lookalikes() {
+        // Text that starts like a bzip2 header, and text with the extension
+        // of a compressed file, is still read as text
+        let dir = FixtureDir::create().unwrap();
+        let lines = ["BZh91AY is not a block", "second line"];
+        for name in [
+            "plain.txt",
+            "plain.gz",
+            "plain.zst",
+            "plain.bz2",
+            "plain.xz",
+        ] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, lines.join("\n")).unwrap();
+            let read = open_lines(&path, None, None, None)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(read, lines, "{}", name);
+        }
+        #[cfg(feature = "bzip2")]
+        assert_eq!(Codec::detect(b"BZh91AY&SY"), Some(Codec::Bzip2));
+        assert_eq!(Codec::detect(b"BZh91AY is"), None);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_decompress_ahead() {
+        let fixture = Fixture::lines(["unused"]).create().unwrap();
+        let text: String = (1..=20_000).map(|n| format!("line {}\n", n)).collect();
+        fs::write(fixture.path(), gzip(&text)).unwrap();
+
+        let walk = |position: usize, max_position: Option<usize>| {
+            let mut builder = fixture.builder();
+            builder.position(position).decompress_ahead(2usize);
+            if let Some(max_position) = max_position {
+                builder.max_position(max_position);
+            }
+            builder
+                .build()
+                .unwrap()
+                .open()
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        let lines = walk(1, None);
+        assert_eq!(lines.len(), 20_000);
+        assert_eq!(lines[19_999], "line 20000");
+        assert_eq!(walk(19_999, None), vec!["line 19999", "line 20000"]);
+        assert_eq!(walk(5, Some(6)), vec!["line 5", "line 6"]);
+        assert!(walk(30_000, None).is_empty());
+
+        // As from plain files, a byte order mark isn't part of the first line
+        let marked = Fixture::lines(["unused"]).create().unwrap();
+        fs::write(marked.path(), gzip("\u{feff}one\ntwo\n")).unwrap();
+        let lines = marked
+            .builder()
+            .decompress_ahead(2usize)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["one", "two"]);
+
+        let opener = OpenerBuilder::default()
+            .path(fixture.path())
+            .position(2)
+            .max_position(1)
+            .decompress_ahead(2usize)
+            .build()
+            .unwrap();
+        assert!(opener.open().is_err());
+    }
+}
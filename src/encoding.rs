@@ -0,0 +1,104 @@
+use crate::{spool::spool_file, Error};
+use encoding_rs::{CoderResult, Encoding, UTF_8};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// Looks up an encoding by its WHATWG label, such as "utf-16le", "latin1" or
+// "shift_jis". Returns None for UTF-8, which needs no transcoding.
+pub(crate) fn lookup(label: Option<&str>) -> Result<Option<&'static Encoding>, Error> {
+    let Some(label) = label else {
+        return Ok(None);
+    };
+    match Encoding::for_label(label.as_bytes()) {
+        Some(encoding) if encoding == UTF_8 => Ok(None),
+        Some(encoding) => Ok(Some(encoding)),
+        None => Err(Error::UnknownEncoding {
+            label: label.to_string(),
+        }),
+    }
+}
+
+// Spools reader transcoded from encoding to UTF-8, replacing malformed
+// sequences with U+FFFD. A byte order mark takes precedence over encoding.
+pub(crate) fn transcode<R: Read>(
+    mut reader: R,
+    encoding: &'static Encoding,
+) -> Result<File, Error> {
+    let mut decoder = encoding.new_decoder();
+    let mut out = BufWriter::new(spool_file()?);
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut text = String::with_capacity(CHUNK_SIZE * 3);
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let last = read == 0;
+        let mut src = &buf[..read];
+        loop {
+            text.clear();
+            let (result, consumed, _) = decoder.decode_to_string(src, &mut text, last);
+            out.write_all(text.as_bytes())?;
+            src = &src[consumed..];
+            if result == CoderResult::InputEmpty {
+                break;
+            }
+        }
+        if last {
+            break;
+        }
+    }
+
+    let mut file = out.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fixtures::Fixture, Error};
+    use std::fs;
+
+    #[test]
+    fn test_encoding() {
+        let fixture = Fixture::lines(["placeholder"]).create().unwrap();
+        let utf16: Vec<u8> = "été\r\nnaïve\r\n€"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        fs::write(fixture.path(), utf16).unwrap();
+        let lines: Vec<String> = fixture
+            .builder()
+            .encoding("UTF-16LE")
+            .position("end")
+            .direction("backward")
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["€", "naïve", "été"]);
+
+        fs::write(fixture.path(), b"caf\xe9\n").unwrap();
+        let walk = |label: &str| {
+            fixture
+                .builder()
+                .encoding(label)
+                .build()
+                .unwrap()
+                .open()
+                .map(|lines| lines.collect::<Result<Vec<_>, _>>().unwrap())
+        };
+        assert_eq!(walk("latin1").unwrap(), vec!["café"]);
+        assert!(matches!(
+            walk("klingon"),
+            Err(Error::UnknownEncoding { .. })
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{count_lines, read_line_ending, Error};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+const SAMPLE_COUNT: u64 = 4;
+
+// Estimates the number of lines in the file at path by counting line breaks
+// in a few evenly spaced windows totalling roughly sample_bytes. Files no
+// larger than sample_bytes are counted exactly.
+pub fn estimate_lines<T: AsRef<Path>>(path: T, sample_bytes: u64) -> Result<usize, Error> {
+    let mut input = File::open(path)?;
+    let len = input.metadata()?.len();
+    let delimiter = read_line_ending(&input)?.delimiter();
+
+    if len <= sample_bytes || sample_bytes < SAMPLE_COUNT {
+        input.seek(SeekFrom::Start(0))?;
+        return count_lines(input, delimiter);
+    }
+
+    let window = sample_bytes / SAMPLE_COUNT;
+    let stride = (len - window) / (SAMPLE_COUNT - 1);
+    let mut buf = vec![0; window as usize];
+    let mut breaks = 0;
+    for idx in 0..SAMPLE_COUNT {
+        input.seek(SeekFrom::Start(idx * stride))?;
+        input.read_exact(&mut buf)?;
+        breaks += memchr::memchr_iter(delimiter, &buf).count() as u64;
+    }
+
+    let sampled = window * SAMPLE_COUNT;
+    Ok(((breaks as f64 / sampled as f64) * len as f64).round() as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+
+    #[test]
+    fn test_exact_for_small_files() {
+        assert_eq!(estimate_lines("./testfiles/1.txt", 4096).unwrap(), 4);
+        assert_eq!(estimate_lines("./testfiles/3.txt", 4096).unwrap(), 0);
+        assert_eq!(estimate_lines("./testfiles/5.txt", 4096).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_sampled_estimate() {
+        let fixture = Fixture::sized(160_000).create().unwrap();
+        let estimate = estimate_lines(fixture.path(), 4096).unwrap();
+
+        assert_eq!(estimate, 10_000);
+    }
+}
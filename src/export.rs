@@ -0,0 +1,136 @@
+use crate::{walk_file, AccessHint, AtomicFile, Direction, Error, HashAlgo, Position, WalkOptions};
+use flate2::{write::GzEncoder, Compression};
+use std::path::{Path, PathBuf};
+
+// ExportSlice selects the range of lines of a single file to include in a bundle
+#[derive(Debug, Clone)]
+pub struct ExportSlice {
+    pub path: PathBuf,
+    pub position: Position,
+    pub max_position: Option<Position>,
+}
+
+impl ExportSlice {
+    pub fn new<T: Into<PathBuf>, P: Into<Position>>(
+        path: T,
+        position: P,
+        max_position: Option<Position>,
+    ) -> Self {
+        ExportSlice {
+            path: path.into(),
+            position: position.into(),
+            max_position,
+        }
+    }
+}
+
+// Writes every slice into a tar.gz bundle at output, alongside a MANIFEST
+// listing the source, line range and content hash of each entry. Hashes are
+// XXH3, so they stay comparable across builds and Rust releases.
+pub fn export_tarball<P: AsRef<Path>>(output: P, slices: &[ExportSlice]) -> Result<(), Error> {
+    let file = AtomicFile::create(output)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let mut manifest = String::from("entry\tsource\trange\thash\n");
+
+    for (idx, slice) in slices.iter().enumerate() {
+        let lines: Vec<String> = walk_file(
+            &slice.path,
+            slice.position,
+            Direction::Forward,
+            slice.max_position,
+            WalkOptions {
+                access_hint: Some(AccessHint::OneShot),
+                ..Default::default()
+            },
+        )?
+        .collect::<Result<_, _>>()?;
+        let mut contents = lines.join("\n");
+        if !contents.is_empty() {
+            contents.push('\n');
+        }
+
+        let file_name = slice
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "slice".to_string());
+        let entry = format!("slices/{:03}-{}", idx, file_name);
+        append_entry(&mut builder, &entry, contents.as_bytes())?;
+
+        manifest.push_str(&format!(
+            "{}\t{}\t{}-{}\t{:016x}\n",
+            entry,
+            slice.path.display(),
+            slice.position,
+            slice.max_position.unwrap_or(Position::End),
+            HashAlgo::Xxh3.hash(&contents),
+        ));
+    }
+
+    append_entry(&mut builder, "MANIFEST", manifest.as_bytes())?;
+    builder.into_inner()?.finish()?.commit()?;
+    Ok(())
+}
+
+fn append_entry<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    name: &str,
+    contents: &[u8],
+) -> Result<(), Error> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, name, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+    use flate2::read::GzDecoder;
+    use std::{fs::File, io::Read};
+
+    #[test]
+    fn test_export_tarball() {
+        let output = Fixture::lines(["unused"]).create().unwrap();
+        export_tarball(
+            output.path(),
+            &[
+                ExportSlice::new("./testfiles/1.txt", 2, Some(Position::Middle(3))),
+                ExportSlice::new("./testfiles/2.txt", Position::Start, None),
+            ],
+        )
+        .unwrap();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(output.path()).unwrap()));
+        let mut entries = vec![];
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            entries.push((
+                entry.path().unwrap().to_string_lossy().to_string(),
+                contents,
+            ));
+        }
+
+        assert_eq!(
+            entries[0],
+            ("slices/000-1.txt".to_string(), "there\nwhats\n".to_string())
+        );
+        assert_eq!(
+            entries[1],
+            (
+                "slices/001-2.txt".to_string(),
+                "am i clear now\n".to_string()
+            )
+        );
+        assert_eq!(entries[2].0, "MANIFEST");
+        assert!(entries[2].1.contains(&format!(
+            "slices/000-1.txt\t./testfiles/1.txt\t2-3\t{:016x}\n",
+            HashAlgo::Xxh3.hash("there\nwhats\n")
+        )));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{Case, Error};
+use regex::{Regex, RegexBuilder};
+use std::{fmt, sync::Arc};
+
+// Anchor sets where a LineFilter pattern has to match within a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Anchor {
+    #[default]
+    Anywhere,
+    // Between word boundaries, like grep -w
+    Word,
+    // The whole line, like grep -x
+    Line,
+}
+
+// LineFilter is a grep-style filter for WalkExt::grep: a regular expression
+// along with how to match it, so patterns don't have to be anchored by hand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineFilter {
+    pattern: String,
+    case: Case,
+    anchor: Anchor,
+    invert: bool,
+}
+
+impl LineFilter {
+    pub fn new<T: Into<String>>(pattern: T) -> Self {
+        LineFilter {
+            pattern: pattern.into(),
+            case: Case::default(),
+            anchor: Anchor::default(),
+            invert: false,
+        }
+    }
+
+    pub fn case(mut self, case: Case) -> Self {
+        self.case = case;
+        self
+    }
+
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    // Keeps the lines that don't match instead, like grep -v.
+    pub fn invert(mut self) -> Self {
+        self.invert = true;
+        self
+    }
+
+    pub fn is_inverted(&self) -> bool {
+        self.invert
+    }
+
+    // Compiles the pattern with its anchor and case applied. Inversion is
+    // left to the caller.
+    pub fn regex(&self) -> Result<Regex, Error> {
+        let pattern = match self.anchor {
+            Anchor::Anywhere => self.pattern.clone(),
+            Anchor::Word => format!(r"\b(?:{})\b", self.pattern),
+            Anchor::Line => format!("^(?:{})$", self.pattern),
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(self.case.ignores_case([self.pattern.as_str()]))
+            .build()?;
+        Ok(regex)
+    }
+}
+
+// LinePredicate decides which lines a walk yields, as set with the filter
+// setting. It is cheap to clone, and clones share the same closure.
+#[derive(Clone)]
+pub struct LinePredicate(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl LinePredicate {
+    pub fn new<F: Fn(&str) -> bool + Send + Sync + 'static>(predicate: F) -> Self {
+        LinePredicate(Arc::new(predicate))
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        (self.0)(line)
+    }
+}
+
+impl<F: Fn(&str) -> bool + Send + Sync + 'static> From<F> for LinePredicate {
+    fn from(predicate: F) -> Self {
+        LinePredicate::new(predicate)
+    }
+}
+
+impl fmt::Debug for LinePredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LinePredicate")
+    }
+}
+
+// LineMap transforms the lines a walk yields, as set with the map setting,
+// such as to trim or redact them. Like LinePredicate, clones share it.
+#[derive(Clone)]
+pub struct LineMap(Arc<dyn Fn(String) -> String + Send + Sync>);
+
+impl LineMap {
+    pub fn new<F: Fn(String) -> String + Send + Sync + 'static>(map: F) -> Self {
+        LineMap(Arc::new(map))
+    }
+
+    pub fn apply(&self, line: String) -> String {
+        (self.0)(line)
+    }
+}
+
+impl<F: Fn(String) -> String + Send + Sync + 'static> From<F> for LineMap {
+    fn from(map: F) -> Self {
+        LineMap::new(map)
+    }
+}
+
+impl fmt::Debug for LineMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LineMap")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, WalkExt};
+
+    #[test]
+    fn test_grep() {
+        let fixture = Fixture::lines(["error", "errors found", "an Error here", "ok"])
+            .create()
+            .unwrap();
+        let grep = |filter: LineFilter| -> Vec<String> {
+            fixture
+                .opener()
+                .open()
+                .unwrap()
+                .grep(&filter)
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+
+        assert_eq!(grep(LineFilter::new("error")).len(), 2);
+        assert_eq!(
+            grep(LineFilter::new("error").anchor(Anchor::Word)),
+            vec!["error"]
+        );
+        assert_eq!(
+            grep(LineFilter::new("error|ok").anchor(Anchor::Line)),
+            vec!["error", "ok"]
+        );
+        assert_eq!(
+            grep(
+                LineFilter::new("error")
+                    .case(Case::Insensitive)
+                    .anchor(Anchor::Word)
+            ),
+            vec!["error", "an Error here"]
+        );
+        assert_eq!(
+            grep(LineFilter::new("error").invert()),
+            vec!["an Error here", "ok"]
+        );
+        assert!(LineFilter::new("(").regex().is_err());
+    }
+
+    #[test]
+    fn test_filter() {
+        let fixture = Fixture::lines(["error", "", "ok", "an error"])
+            .create()
+            .unwrap();
+        let lines = fixture
+            .builder()
+            .filter(|line: &str| line.contains("error"))
+            .build()
+            .unwrap()
+            .open()
+            .unwrap();
+        assert_eq!(
+            lines.collect::<Result<Vec<_>, _>>().unwrap(),
+            vec!["error", "an error"]
+        );
+
+        let lines: Vec<String> = fixture
+            .builder()
+            .position("end")
+            .direction("backward")
+            .filter(|line: &str| !line.is_empty())
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["an error", "ok", "error"]);
+    }
+
+    #[test]
+    fn test_map() {
+        let fixture = Fixture::lines(["  token=abc ", "", "ok"]).create().unwrap();
+        let lines: Vec<String> = fixture
+            .builder()
+            .filter(|line: &str| !line.is_empty())
+            .map(|line: String| match line.trim().split_once('=') {
+                Some((key, _)) => format!("{}=***", key),
+                None => line.trim().to_string(),
+            })
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["token=***", "ok"]);
+    }
+}
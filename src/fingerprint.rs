@@ -0,0 +1,99 @@
+use crate::{Error, LineIter};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    time::SystemTime,
+};
+use xxhash_rust::xxh3::Xxh3;
+
+// Bytes hashed at the start, middle and end of a file
+const SAMPLE_LEN: u64 = 4096;
+
+// Fingerprint tells cheaply whether a file changed since it was last walked,
+// from its size, modification time and a hash of a few sampled blocks.
+// Changes that keep all three, such as rewriting bytes between the samples
+// within the same mtime tick, go unnoticed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub sample_hash: u64,
+}
+
+impl Fingerprint {
+    pub fn of<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let size = metadata.len();
+
+        let mut hasher = Xxh3::new();
+        let mut sample = Vec::with_capacity(SAMPLE_LEN as usize);
+        let middle = size.saturating_sub(SAMPLE_LEN) / 2;
+        let end = size.saturating_sub(SAMPLE_LEN);
+        for offset in [0, middle, end] {
+            sample.clear();
+            file.seek(SeekFrom::Start(offset))?;
+            (&mut file).take(SAMPLE_LEN).read_to_end(&mut sample)?;
+            hasher.update(&sample);
+        }
+
+        Ok(Fingerprint {
+            size,
+            modified: metadata.modified().ok(),
+            sample_hash: hasher.digest(),
+        })
+    }
+}
+
+// IfChanged is returned by Opener::if_changed
+#[derive(Debug)]
+pub enum IfChanged {
+    NotModified,
+    // The walk of the changed file, along with the fingerprint to pass next
+    // time. The walk is boxed to keep NotModified small.
+    Modified {
+        lines: Box<LineIter>,
+        fingerprint: Fingerprint,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+    use std::fs;
+
+    #[test]
+    fn test_if_changed() {
+        let fixture = Fixture::numbered(2000).create().unwrap();
+        let opener = fixture.opener();
+
+        let fingerprint = match opener.if_changed(None).unwrap() {
+            IfChanged::Modified { lines, fingerprint } => {
+                assert_eq!(lines.count(), 2000);
+                fingerprint
+            }
+            IfChanged::NotModified => panic!("a first walk is always modified"),
+        };
+        assert!(matches!(
+            opener.if_changed(Some(&fingerprint)).unwrap(),
+            IfChanged::NotModified
+        ));
+
+        // Same size, different bytes in the last block
+        let mut contents = fs::read(fixture.path()).unwrap();
+        let last = contents.len() - 2;
+        contents[last] = b'X';
+        fs::write(fixture.path(), contents).unwrap();
+        match opener.if_changed(Some(&fingerprint)).unwrap() {
+            IfChanged::Modified {
+                fingerprint: new, ..
+            } => {
+                assert_eq!(new.size, fingerprint.size);
+                assert_ne!(new.sample_hash, fingerprint.sample_hash);
+            }
+            IfChanged::NotModified => panic!("the change was missed"),
+        }
+    }
+}
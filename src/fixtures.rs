@@ -0,0 +1,240 @@
+use crate::{Error, LineEnding, Opener, OpenerBuilder};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static NEXT_FIXTURE: AtomicUsize = AtomicUsize::new(0);
+
+// FixtureEncoding is the byte encoding a fixture's text is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FixtureEncoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+// Fixture is a temporary file for tests, removed again when dropped
+#[derive(Debug)]
+pub struct Fixture {
+    path: PathBuf,
+}
+
+impl Fixture {
+    // Starts a fixture containing the given lines.
+    pub fn lines<I, T>(lines: I) -> FixtureBuilder
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        FixtureBuilder {
+            lines: lines.into_iter().map(Into::into).collect(),
+            line_ending: LineEnding::Lf,
+            trailing_newline: true,
+            encoding: FixtureEncoding::Utf8,
+            bom: false,
+        }
+    }
+
+    // Starts a fixture of count lines reading "line 1", "line 2", ...
+    pub fn numbered(count: usize) -> FixtureBuilder {
+        Self::lines((1..=count).map(|n| format!("line {}", n)))
+    }
+
+    // Starts a fixture of roughly size bytes made of fixed-width lines.
+    pub fn sized(size: usize) -> FixtureBuilder {
+        Self::lines((0..size / 16).map(|n| format!("{:015}", n)))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // An Opener for the fixture with default settings.
+    pub fn opener(&self) -> Opener {
+        self.builder().build().unwrap()
+    }
+
+    // A builder for an Opener with the path already set.
+    pub fn builder(&self) -> OpenerBuilder {
+        let mut builder = OpenerBuilder::default();
+        builder.path(&self.path);
+        builder
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// FixtureDir is an empty temporary directory for tests, removed along with
+// everything in it when dropped
+#[derive(Debug)]
+pub struct FixtureDir {
+    path: PathBuf,
+}
+
+impl FixtureDir {
+    // Creates a fresh directory in the temp directory.
+    pub fn create() -> Result<Self, Error> {
+        let path = fixture_path();
+        fs::create_dir(&path)?;
+        Ok(FixtureDir { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FixtureDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+// A path in the temp directory no other fixture of this process uses.
+fn fixture_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "filewalker-fixture-{}-{}",
+        std::process::id(),
+        NEXT_FIXTURE.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+// FixtureBuilder describes the contents of a Fixture before it is written
+#[derive(Debug, Clone)]
+pub struct FixtureBuilder {
+    lines: Vec<String>,
+    line_ending: LineEnding,
+    trailing_newline: bool,
+    encoding: FixtureEncoding,
+    bom: bool,
+}
+
+impl FixtureBuilder {
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    pub fn trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+
+    pub fn encoding(mut self, encoding: FixtureEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    // Starts the file with a byte order mark for the chosen encoding.
+    pub fn bom(mut self, bom: bool) -> Self {
+        self.bom = bom;
+        self
+    }
+
+    // Writes the fixture to a fresh file in the temp directory.
+    pub fn create(&self) -> Result<Fixture, Error> {
+        let terminator = match self.line_ending {
+            LineEnding::Lf => "\n".to_string(),
+            LineEnding::CrLf => "\r\n".to_string(),
+            LineEnding::Cr => "\r".to_string(),
+            LineEnding::Delimiter(delimiter) => char::from(delimiter).to_string(),
+        };
+        let mut text = self.lines.join(&terminator);
+        if self.trailing_newline && !self.lines.is_empty() {
+            text.push_str(&terminator);
+        }
+
+        let mut bytes = vec![];
+        if self.bom {
+            text.insert(0, '\u{feff}');
+        }
+        match self.encoding {
+            FixtureEncoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+            FixtureEncoding::Utf16Le => text
+                .encode_utf16()
+                .for_each(|unit| bytes.extend_from_slice(&unit.to_le_bytes())),
+            FixtureEncoding::Utf16Be => text
+                .encode_utf16()
+                .for_each(|unit| bytes.extend_from_slice(&unit.to_be_bytes())),
+        }
+
+        let path = fixture_path();
+        fs::write(&path, bytes)?;
+        Ok(Fixture { path })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_contents() {
+        let fixture = Fixture::lines(["a", "b"])
+            .line_ending(LineEnding::CrLf)
+            .trailing_newline(false)
+            .create()
+            .unwrap();
+        assert_eq!(fs::read(fixture.path()).unwrap(), b"a\r\nb");
+        assert_eq!(fixture.opener().path, fixture.path());
+        assert_eq!(
+            fixture
+                .opener()
+                .open()
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            vec!["a", "b"]
+        );
+
+        let fixture = Fixture::lines(["é"])
+            .encoding(FixtureEncoding::Utf16Le)
+            .bom(true)
+            .create()
+            .unwrap();
+        assert_eq!(
+            fs::read(fixture.path()).unwrap(),
+            vec![0xff, 0xfe, 0xe9, 0x00, 0x0a, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_fixture_cleanup() {
+        let fixture = Fixture::numbered(3).create().unwrap();
+        let path = fixture.path().to_path_buf();
+        let lines: Vec<String> = fixture
+            .builder()
+            .position("end")
+            .direction("backward")
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["line 3", "line 2", "line 1"]);
+
+        drop(fixture);
+        assert!(!path.exists());
+        assert_eq!(
+            fs::metadata(Fixture::sized(1600).create().unwrap().path())
+                .unwrap()
+                .len(),
+            1600
+        );
+
+        let dir = FixtureDir::create().unwrap();
+        let path = dir.path().to_path_buf();
+        fs::create_dir(path.join("nested")).unwrap();
+        fs::write(path.join("nested").join("file"), "a").unwrap();
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,43 @@
+use xxhash_rust::{xxh3::xxh3_64, xxh64::xxh64};
+
+// HashAlgo selects the non-cryptographic hash used for line fingerprints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgo {
+    #[default]
+    Xxh3,
+    Xxh64,
+}
+
+impl HashAlgo {
+    pub fn hash(&self, line: &str) -> u64 {
+        match self {
+            HashAlgo::Xxh3 => xxh3_64(line.as_bytes()),
+            HashAlgo::Xxh64 => xxh64(line.as_bytes(), 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenerBuilder;
+
+    #[test]
+    fn test_hashes() {
+        let hashes: Vec<(usize, u64)> = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .position("end")
+            .direction("backward")
+            .build()
+            .unwrap()
+            .hashes(HashAlgo::Xxh64)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(hashes.len(), 4);
+        assert_eq!(hashes[0], (4, HashAlgo::Xxh64.hash("up")));
+        assert_eq!(hashes[3], (1, HashAlgo::Xxh64.hash("hello")));
+        assert_ne!(HashAlgo::Xxh3.hash("up"), HashAlgo::Xxh3.hash("hello"));
+    }
+}
@@ -0,0 +1,299 @@
+use crate::{bom, read_line_ending, Error, SidecarStore, StateStore, SCAN_BUF_SIZE};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek},
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+// Key of the sidecar an index is saved in, so "app.log" is indexed in
+// "app.log.fwidx"
+const SIDECAR_KEY: &str = "fwidx";
+const MAGIC: &[u8; 6] = b"FWIDX1";
+// The file's size and modification time, then the index's length,
+// delimiter and line count, ahead of the offsets
+const HEADER_LEN: usize = 37;
+
+// LineIndex is the byte offset every line of a file starts at, built in one
+// pass. Given to an Opener through the index setting, it stands in for
+// counting lines and scanning for offsets, so walks from any line start
+// with a single seek. It is only used while the file is the size it was
+// indexed at and split on the same delimiter; otherwise walks scan as usual.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    // Where each line starts, counting from after a byte order mark
+    pub(crate) offsets: Vec<u64>,
+    pub(crate) len: u64,
+    pub(crate) delimiter: u8,
+}
+
+impl LineIndex {
+    // Indexes the file at path, split on its detected line ending.
+    pub fn build<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        LineIndex::build_from(File::open(path.as_ref())?)
+    }
+
+    // Indexes any seekable source, split on its detected line ending.
+    pub fn build_from<R: Read + Seek>(mut input: R) -> Result<Self, Error> {
+        let mark = bom::read(&mut input)?.map_or(0, |(_, mark)| mark);
+        let mut input = bom::Unmarked::new(input, mark)?;
+        let delimiter = read_line_ending(&mut input)?.delimiter();
+        input.rewind()?;
+
+        let mut reader = BufReader::with_capacity(SCAN_BUF_SIZE, input);
+        let mut offsets = vec![];
+        let mut len = 0;
+        loop {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            if len == 0 {
+                offsets.push(0);
+            }
+            offsets.extend(memchr::memchr_iter(delimiter, buf).map(|idx| len + idx as u64 + 1));
+            len += buf.len() as u64;
+            let consumed = buf.len();
+            reader.consume(consumed);
+        }
+        // A delimiter at the very end closes the last line rather than
+        // starting another one
+        if offsets.last() == Some(&len) {
+            offsets.pop();
+        }
+        Ok(LineIndex {
+            offsets,
+            len,
+            delimiter,
+        })
+    }
+
+    // Loads the index saved next to the file at path, or builds and saves
+    // one if there is none or the file changed size or modification time
+    // since. Opens after the first skip scanning the file altogether.
+    pub fn load_or_build<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        LineIndex::load_or_build_in(&SidecarStore, path)
+    }
+
+    // Like load_or_build, keeping the saved index in store instead of next
+    // to the file.
+    pub fn load_or_build_in<S, P>(store: &S, path: P) -> Result<Self, Error>
+    where
+        S: StateStore + ?Sized,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let stamp = Stamp::of(path)?;
+        let saved = store.get(path, SIDECAR_KEY)?;
+        if let Some(index) = saved.and_then(|saved| LineIndex::decode(&saved, stamp)) {
+            return Ok(index);
+        }
+        let index = LineIndex::build(path)?;
+        store.put(path, SIDECAR_KEY, &index.encode(stamp))?;
+        Ok(index)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    // The size of the file when it was indexed.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // The offset the 1-based line line_no starts at, if the file has it.
+    pub fn line_offset(&self, line_no: usize) -> Option<u64> {
+        self.offsets.get(line_no.checked_sub(1)?).copied()
+    }
+
+    // The 1-based number of the line offset falls in. Offsets past the end
+    // fall in the last line.
+    pub fn line_at(&self, offset: u64) -> usize {
+        self.offsets
+            .partition_point(|&start| start <= offset)
+            .max(1)
+    }
+
+    fn encode(&self, stamp: Stamp) -> Vec<u8> {
+        let mut out = Vec::with_capacity(MAGIC.len() + HEADER_LEN + self.offsets.len() * 8);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&stamp.size.to_le_bytes());
+        out.extend_from_slice(&stamp.modified.as_secs().to_le_bytes());
+        out.extend_from_slice(&stamp.modified.subsec_nanos().to_le_bytes());
+        out.extend_from_slice(&self.len.to_le_bytes());
+        out.push(self.delimiter);
+        out.extend_from_slice(&(self.offsets.len() as u64).to_le_bytes());
+        for offset in &self.offsets {
+            out.extend_from_slice(&offset.to_le_bytes());
+        }
+        out
+    }
+
+    // Reads back an index saved by encode, if it was saved for a file
+    // stamped the same. Anything malformed is treated as stale.
+    fn decode(saved: &[u8], stamp: Stamp) -> Option<Self> {
+        let rest = saved.strip_prefix(MAGIC)?;
+        let (header, offsets) = rest.split_at_checked(HEADER_LEN)?;
+        let u64_at = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+        let saved_stamp = Stamp {
+            size: u64_at(0),
+            modified: Duration::new(
+                u64_at(8),
+                u32::from_le_bytes(header[16..20].try_into().unwrap()),
+            ),
+        };
+        let count = u64_at(29);
+        if saved_stamp != stamp || offsets.len() as u64 != count.checked_mul(8)? {
+            return None;
+        }
+        Some(LineIndex {
+            offsets: offsets
+                .chunks_exact(8)
+                .map(|offset| u64::from_le_bytes(offset.try_into().unwrap()))
+                .collect(),
+            len: u64_at(20),
+            delimiter: header[28],
+        })
+    }
+
+    // Whether the index describes a source of len bytes split on delimiter.
+    pub(crate) fn fits(&self, len: u64, delimiter: u8) -> bool {
+        self.len == len && self.delimiter == delimiter
+    }
+
+    // Where line starts, like compute_offset finds it: lines past the end
+    // start at the end.
+    pub(crate) fn offset(&self, line: usize) -> u64 {
+        match line {
+            0 => 0,
+            line => self.line_offset(line).unwrap_or(self.len),
+        }
+    }
+}
+
+// Stamp is what a saved index is checked against to tell whether the file
+// changed since it was indexed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    size: u64,
+    // Since the epoch, zero where modification times aren't available
+    modified: Duration,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Result<Self, Error> {
+        let metadata = path.metadata()?;
+        let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+        Ok(Stamp {
+            size: metadata.len(),
+            modified: modified.duration_since(UNIX_EPOCH).unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, Position};
+    use std::{
+        fs::{self, OpenOptions},
+        io::Write,
+        sync::Arc,
+    };
+
+    #[test]
+    fn test_line_index() {
+        // "hello\nthere\nwhats\nup"
+        let index = LineIndex::build("./testfiles/1.txt").unwrap();
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_offset(3), Some(12));
+        assert_eq!(index.line_offset(5), None);
+        assert_eq!(index.line_at(13), 3);
+        assert_eq!(index.line_at(100), 4);
+
+        let fixture = Fixture::numbered(100).create().unwrap();
+        let index = Arc::new(LineIndex::build(fixture.path()).unwrap());
+        assert_eq!(index.line_count(), 100);
+        let walk = |position: Position, direction: &str, max: Position| -> Vec<String> {
+            fixture
+                .builder()
+                .index(index.clone())
+                .position(position)
+                .direction(direction)
+                .max_position(max)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+        assert_eq!(
+            walk(Position::FromEnd(1), "backward", Position::Middle(98)),
+            vec!["line 100", "line 99", "line 98"]
+        );
+        assert_eq!(
+            walk(Position::Middle(50), "forward", Position::Middle(51)),
+            vec!["line 50", "line 51"]
+        );
+        assert_eq!(
+            walk(Position::Byte(0), "forward", Position::Middle(2)),
+            vec!["line 1", "line 2"]
+        );
+
+        // Lines are looked up in the index rather than the file, so one of
+        // the same size with different lines is walked where it says
+        let other = Fixture::lines(["abc", "d"]).create().unwrap();
+        let same_size = Fixture::lines(["ab", "cd"]).create().unwrap();
+        let lines: Vec<String> = same_size
+            .builder()
+            .index(LineIndex::build(other.path()).unwrap())
+            .position(Position::Middle(2))
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["d"]);
+
+        // Saved next to the file, and reused until it changes
+        let sidecar = format!("{}.fwidx", fixture.path().display());
+        let built = LineIndex::load_or_build(fixture.path()).unwrap();
+        assert_eq!(built, *index);
+        assert!(Path::new(&sidecar).exists());
+        fs::write(&sidecar, {
+            let mut saved = fs::read(&sidecar).unwrap();
+            let last = saved.len() - 8;
+            saved[last..].copy_from_slice(&7u64.to_le_bytes());
+            saved
+        })
+        .unwrap();
+        assert_eq!(
+            LineIndex::load_or_build(fixture.path())
+                .unwrap()
+                .line_offset(100),
+            Some(7)
+        );
+
+        // Once the file changes size the index is ignored
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(fixture.path())
+            .unwrap();
+        file.write_all(b"line 101\n").unwrap();
+        assert_eq!(
+            walk(Position::FromEnd(1), "backward", Position::FromEnd(1)),
+            vec!["line 101"]
+        );
+        let rebuilt = LineIndex::load_or_build(fixture.path()).unwrap();
+        assert_eq!(rebuilt.line_count(), 101);
+        assert_eq!(rebuilt.line_offset(100), index.line_offset(100));
+        fs::remove_file(&sidecar).unwrap();
+    }
+}
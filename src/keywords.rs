@@ -0,0 +1,139 @@
+use crate::{case::fold, Case, Error, WalkItem};
+use aho_corasick::AhoCorasick;
+
+// Keywords is a set of literal patterns searched for together with
+// Aho-Corasick, so each line is scanned once however many there are
+#[derive(Debug, Clone)]
+pub struct Keywords {
+    searcher: AhoCorasick,
+    patterns: Vec<String>,
+    // Whether lines are case folded before searching them
+    folded: bool,
+}
+
+impl Keywords {
+    pub fn new<I, P>(patterns: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<String>,
+    {
+        Keywords::with_case(patterns, Case::Sensitive)
+    }
+
+    pub fn with_case<I, P>(patterns: I, case: Case) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<String>,
+    {
+        let patterns: Vec<String> = patterns.into_iter().map(Into::into).collect();
+        let folded = case.ignores_case(patterns.iter().map(String::as_str));
+        let searcher = match folded {
+            true => AhoCorasick::new(patterns.iter().map(|pattern| fold(pattern)))?,
+            false => AhoCorasick::new(&patterns)?,
+        };
+        Ok(Keywords {
+            searcher,
+            patterns,
+            folded,
+        })
+    }
+
+    // The patterns, in the order given. KeywordHit::patterns indexes into it.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    // Returns the indices of the patterns found in line, in ascending order.
+    pub fn find_all(&self, line: &str) -> Vec<usize> {
+        let mut found = vec![false; self.patterns.len()];
+        let folded;
+        let line = match self.folded {
+            true => {
+                folded = fold(line);
+                folded.as_str()
+            }
+            false => line,
+        };
+        for hit in self.searcher.find_overlapping_iter(line) {
+            found[hit.pattern().as_usize()] = true;
+        }
+        found
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, found)| found.then_some(idx))
+            .collect()
+    }
+}
+
+// KeywordHit is a line containing at least one of the keywords
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordHit {
+    pub line: String,
+    // Indices of the patterns found, in ascending order
+    pub patterns: Vec<usize>,
+}
+
+// KeywordHits is the iterator returned by WalkExt::keyword_hits
+pub struct KeywordHits<I> {
+    inner: I,
+    keywords: Keywords,
+}
+
+impl<I> KeywordHits<I> {
+    pub(crate) fn new(inner: I, keywords: Keywords) -> Self {
+        KeywordHits { inner, keywords }
+    }
+}
+
+impl<I: Iterator<Item: WalkItem>> Iterator for KeywordHits<I> {
+    type Item = <I::Item as WalkItem>::Map<KeywordHit>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let keywords = &self.keywords;
+        self.inner.find_map(|item| {
+            item.filter_map_line(|line| {
+                let patterns = keywords.find_all(&line);
+                (!patterns.is_empty()).then_some(KeywordHit { line, patterns })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, WalkExt};
+
+    #[test]
+    fn test_keyword_hits() {
+        let fixture = Fixture::lines([
+            "disk full on /var",
+            "all good",
+            "OOM killer: out of memory",
+            "disk error, out of memory",
+        ])
+        .create()
+        .unwrap();
+        let keywords = Keywords::new(["out of memory", "disk", "OOM", "disk full"]).unwrap();
+        let hits: Vec<KeywordHit> = fixture
+            .opener()
+            .open()
+            .unwrap()
+            .keyword_hits(keywords.clone())
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let found = |hit: &KeywordHit| -> Vec<&str> {
+            let patterns = keywords.patterns();
+            hit.patterns
+                .iter()
+                .map(|idx| patterns[*idx].as_str())
+                .collect()
+        };
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0].line, "disk full on /var");
+        assert_eq!(found(&hits[0]), vec!["disk", "disk full"]);
+        assert_eq!(found(&hits[1]), vec!["out of memory", "OOM"]);
+        assert_eq!(found(&hits[2]), vec!["out of memory", "disk"]);
+    }
+}
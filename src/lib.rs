@@ -1,25 +1,205 @@
 use derive_builder::Builder;
-use once_cell::sync::Lazy;
 use rev_buf_reader::RevBufReader;
 use std::{
+    fmt,
     fs::File,
-    io::{BufRead, BufReader, Seek, SeekFrom, self},
-    process::{Command, Stdio},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    ops::{Bound, RangeBounds},
+    path::{Path, PathBuf},
+    sync::Arc,
     vec::IntoIter,
 };
 use thiserror::Error;
 
+mod adapters;
+mod advise;
+mod atomic;
+mod auto;
+mod blocks;
+mod bom;
+mod buckets;
+mod bounded;
+mod case;
+mod compact;
+mod cursor;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+mod compress;
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+mod decompress;
+#[cfg(feature = "encoding")]
+mod encoding;
+mod estimate;
+#[cfg(feature = "tar")]
+mod export;
+mod filter;
+mod fingerprint;
+mod index;
+#[cfg(any(test, feature = "test-util"))]
+pub mod fixtures;
+mod hashes;
+mod keywords;
+mod lines;
+mod markers;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod mirror;
+#[cfg(feature = "test-util")]
+mod mock;
+mod patch;
+#[cfg(feature = "wasm")]
+mod plugin;
+mod pointer;
+mod preview;
+mod queue;
+mod ranges;
+mod reader;
+mod rewrite;
+mod round_trip;
+mod sample;
+mod segments;
+mod sessions;
+mod snapshot;
+mod sort;
+mod spool;
+mod state;
+mod stats;
+mod store;
+#[cfg(feature = "tokio")]
+mod stream;
+mod throttle;
+mod topk;
+mod tree;
+pub mod v2;
+mod walk;
+mod watch;
+
+pub use adapters::{ExtractPointer, Matching, PointerEquals, Record, Records, WalkExt, WalkItem};
+pub use advise::AccessHint;
+pub use atomic::AtomicFile;
+pub use auto::{AutoLines, Format, Layer, TextEncoding};
+pub use blocks::Blocks;
+pub use buckets::{bucket_by_time, parse_timestamp, TimeBucket};
+pub use case::Case;
+pub use compact::{reclaim, Reclaim};
+pub use cursor::Cursor;
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+pub use compress::Codec;
+#[cfg(feature = "tar")]
+pub use export::{export_tarball, ExportSlice};
+pub use estimate::estimate_lines;
+pub use filter::{Anchor, LineFilter, LineMap, LinePredicate};
+pub use fingerprint::{Fingerprint, IfChanged};
+pub use hashes::HashAlgo;
+pub use index::LineIndex;
+pub use keywords::{KeywordHit, KeywordHits, Keywords};
+pub use lines::{ExactLines, LineIter, LineRecord, LineRecords, Numbered, PauseHandle};
+pub use markers::{Marked, MarkerRules, Markers};
+pub use mirror::sync_append;
+#[cfg(feature = "test-util")]
+pub use mock::MockWalker;
+pub use patch::{apply_patch, make_patch, Patch};
+#[cfg(feature = "wasm")]
+pub use plugin::{Plugged, Plugin};
+pub use pointer::JsonPointer;
+pub use preview::{preview, Preview};
+pub use queue::FileQueue;
+pub use ranges::ByteRange;
+pub use rewrite::{rewrite, Rewrite};
+pub use round_trip::{round_trip, RawLine, RoundTrip, RoundTripWriter};
+pub use sample::{export_sample, Redaction};
+pub use segments::{Segment, Segments, Split};
+pub use sessions::{SessionItem, Sessions};
+pub use snapshot::{FileEntry, TreeDiff, TreeSnapshot};
+pub use sort::{field, parse_human_size, parse_numeric, sort_file, SortKey};
+pub use state::{StateDir, StateLock};
+pub use stats::{Lag, WalkStats};
+#[cfg(feature = "sled")]
+pub use store::SledStore;
+#[cfg(feature = "sqlite")]
+pub use store::SqliteStore;
+pub use store::{SidecarStore, StateStore};
+#[cfg(feature = "tokio")]
+pub use stream::{open_file_async, LineStream};
+pub use topk::{diff_counts, top_k, CountDiff};
+pub use tree::Walker;
+pub use walk::{FileWalk, LineWalker};
+pub use watch::AppendHandle;
+
 // Position stores the cursor location as a byte offset
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum Position {
+    #[default]
     Start,
     Middle(usize),
     End,
+    // A byte offset, such as one saved by an earlier walk, snapped to the
+    // line boundary at or after it when starting a forward walk and at or
+    // before it otherwise. Walks using one skip counting lines up front, and
+    // number their lines from 1 in the order they are read.
+    Byte(u64),
+    // The nth line counting back from the end, so a forward walk from it
+    // yields the last n lines. FromEnd(1) is the last line.
+    FromEnd(usize),
+    // A fraction of the file by bytes, from 0.0 at the start to 1.0 at the
+    // end, walked like the Byte position it works out to. Lets a scrollbar
+    // jump to a proportional location without knowing the line count.
+    Percent(f32),
 }
 
-impl Default for Position {
-    fn default() -> Self {
-        Position::Start
+impl Position {
+    // Turns a FromEnd position into the line it points to, given the number
+    // of lines.
+    pub(crate) fn resolve(self, total_lines: usize) -> Position {
+        match self {
+            Position::FromEnd(n) => Position::Middle((total_lines + 1).saturating_sub(n).max(1)),
+            position => position,
+        }
+    }
+
+    // The byte offset of a Byte or Percent position, given the length of the
+    // source. Positions by line have no offset of their own and map to 0.
+    pub(crate) fn byte_offset(self, len: u64) -> u64 {
+        match self {
+            Position::Byte(offset) => offset,
+            Position::Percent(fraction) => (len as f64 * fraction.clamp(0.0, 1.0) as f64) as u64,
+            _ => 0,
+        }
+    }
+
+    fn by_bytes(self) -> bool {
+        matches!(self, Position::Byte(_) | Position::Percent(_))
+    }
+
+    // Turns a range of 1-based line numbers into the position a forward walk
+    // starts at and the max position it stops at.
+    pub(crate) fn from_range<R: RangeBounds<usize>>(range: R) -> (Position, Option<Position>) {
+        let start = match range.start_bound() {
+            Bound::Included(0 | 1) | Bound::Unbounded => Position::Start,
+            Bound::Included(&n) => Position::Middle(n),
+            Bound::Excluded(&n) => Position::Middle(n + 1),
+        };
+        let max = match range.end_bound() {
+            Bound::Included(&n) => Some(Position::Middle(n)),
+            Bound::Excluded(&n) => Some(Position::Middle(n.saturating_sub(1))),
+            Bound::Unbounded => None,
+        };
+        (start, max)
+    }
+}
+
+// Formats the position the same way it is parsed from a string
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Position::Start => write!(f, "start"),
+            Position::Middle(n) => write!(f, "{}", n),
+            Position::End => write!(f, "end"),
+            Position::Byte(n) => write!(f, "byte:{}", n),
+            Position::FromEnd(n) => write!(f, "end-{}", n),
+            Position::Percent(fraction) => write!(f, "{}%", fraction * 100.0),
+        }
     }
 }
 
@@ -41,6 +221,12 @@ impl From<String> for Position {
             return Position::Middle(num);
         } else if value == "end" {
             return Position::End;
+        } else if let Some(Ok(offset)) = value.strip_prefix("byte:").map(str::parse) {
+            return Position::Byte(offset);
+        } else if let Some(Ok(lines)) = value.strip_prefix("end-").map(str::parse) {
+            return Position::FromEnd(lines);
+        } else if let Some(Ok(percent)) = value.strip_suffix('%').map(str::parse::<f32>) {
+            return Position::Percent(percent / 100.0);
         }
 
         Position::default()
@@ -55,250 +241,1785 @@ impl From<Option<String>> for Position {
 
         Position::default()
     }
-}
+}
+
+// Direction indicates whether to parse the file moving up or down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    #[default]
+    Forward,
+    Backward,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Forward => write!(f, "forward"),
+            Direction::Backward => write!(f, "backward"),
+        }
+    }
+}
+
+impl From<&str> for Direction {
+    fn from(value: &str) -> Self {
+        Direction::from(value.to_string())
+    }
+}
+
+impl From<String> for Direction {
+    fn from(value: String) -> Self {
+        if value == "backward" {
+            return Direction::Backward;
+        }
+
+        Direction::default()
+    }
+}
+
+impl From<Option<String>> for Direction {
+    fn from(value: Option<String>) -> Self {
+        if let Some(dir) = value {
+            return Direction::from(dir);
+        }
+
+        Direction::default()
+    }
+}
+
+// LineEnding is the newline convention used by a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    Cr,
+    // A record delimiter other than a line break, such as the NUL ending
+    // each path printed by find -print0. Never detected, only set.
+    Delimiter(u8),
+}
+
+impl LineEnding {
+    // Guesses the convention from a block of bytes, falling back to Lf when
+    // the block contains no line breaks at all.
+    pub fn detect(block: &[u8]) -> Self {
+        let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+        let mut bytes = block.iter().peekable();
+        while let Some(byte) = bytes.next() {
+            match byte {
+                b'\r' if bytes.peek() == Some(&&b'\n') => {
+                    crlf += 1;
+                    bytes.next();
+                }
+                b'\r' => cr += 1,
+                b'\n' => lf += 1,
+                _ => {}
+            }
+        }
+
+        if crlf > 0 && crlf >= lf && crlf >= cr {
+            LineEnding::CrLf
+        } else if cr > lf {
+            LineEnding::Cr
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    fn delimiter(&self) -> u8 {
+        match self {
+            LineEnding::Cr => b'\r',
+            LineEnding::Delimiter(delimiter) => *delimiter,
+            _ => b'\n',
+        }
+    }
+
+    // The bytes that end a line in this convention.
+    pub(crate) fn terminator(&self) -> &[u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+            LineEnding::Cr => b"\r",
+            LineEnding::Delimiter(delimiter) => std::slice::from_ref(delimiter),
+        }
+    }
+
+    fn strip(&self, line: &mut Vec<u8>) {
+        if line.last() == Some(&self.delimiter()) {
+            line.pop();
+        }
+        if matches!(self, LineEnding::CrLf) && line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    }
+}
+
+// Backend selects how the bytes of a file are read during a walk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    #[default]
+    Buffered,
+    // Memory-maps the file. The file must not be truncated or rewritten by
+    // anyone while it is walked. Walks that follow appended lines or read
+    // compressed files or files with a byte order mark fall back to
+    // buffered reads.
+    #[cfg(feature = "mmap")]
+    Mmap,
+    // Picks one per file: memory maps files of 64MiB or more on 64-bit
+    // platforms when they aren't read from the start forward, and reads
+    // everything else buffered. LineIter::backend reports the pick.
+    Auto,
+}
+
+// Files this large are worth mapping when walked in a way BufReader
+// serves poorly
+#[cfg(feature = "mmap")]
+const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+impl Backend {
+    // Picks the backend for a walk of a file of len bytes.
+    #[cfg_attr(not(feature = "mmap"), allow(unused_variables))]
+    fn resolve(self, len: u64, position: Position, direction: Direction) -> Backend {
+        match self {
+            #[cfg(feature = "mmap")]
+            Backend::Auto
+                if cfg!(target_pointer_width = "64")
+                    && len >= MMAP_THRESHOLD
+                    && (direction == Direction::Backward || position != Position::Start) =>
+            {
+                Backend::Mmap
+            }
+            Backend::Auto => Backend::Buffered,
+            backend => backend,
+        }
+    }
+}
+
+// Newlines sets which line breaks are stripped from the end of lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Newlines {
+    // Strips the line ending detected for the file, so a file detected as
+    // LF that has a few CRLF lines yields those with a trailing '\r'
+    #[default]
+    Detected,
+    // Strips "\r\n" as well as "\n" from every line of a file not detected as
+    // CR, for files mixing both. Such files read as CrLf.
+    Any,
+}
+
+impl Newlines {
+    fn apply(self, detected: LineEnding) -> LineEnding {
+        match (self, detected) {
+            (Newlines::Any, LineEnding::Lf) => LineEnding::CrLf,
+            _ => detected,
+        }
+    }
+}
+
+// Utf8Policy sets what happens to lines that aren't valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Utf8Policy {
+    // Fails the walk with Error::InvalidUtf8
+    #[default]
+    Strict,
+    // Replaces invalid sequences with U+FFFD
+    Lossy,
+    // Yields such lines one char per byte, as Latin-1, so the raw bytes can
+    // be recovered with line.chars().map(|c| c as u8)
+    Bytes,
+}
+
+impl Utf8Policy {
+    // Decodes line into text, taking over the buffer of line when text has
+    // none of its own and reusing that of text otherwise. Returns false if
+    // line isn't valid UTF-8 and the policy is Strict.
+    fn decode_into(self, line: &mut Vec<u8>, text: &mut String) -> bool {
+        text.clear();
+        if text.capacity() == 0 {
+            return self
+                .decode(std::mem::take(line))
+                .map(|decoded| *text = decoded)
+                .is_some();
+        }
+        match (std::str::from_utf8(line), self) {
+            (Ok(valid), _) => text.push_str(valid),
+            (Err(_), Utf8Policy::Strict) => return false,
+            (Err(_), Utf8Policy::Lossy) => text.push_str(&String::from_utf8_lossy(line)),
+            (Err(_), Utf8Policy::Bytes) => text.extend(line.iter().map(|&b| char::from(b))),
+        }
+        true
+    }
+
+    fn decode(self, line: Vec<u8>) -> Option<String> {
+        match String::from_utf8(line) {
+            Ok(line) => Some(line),
+            Err(e) => match self {
+                Utf8Policy::Strict => None,
+                Utf8Policy::Lossy => Some(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+                Utf8Policy::Bytes => Some(e.as_bytes().iter().map(|&b| char::from(b)).collect()),
+            },
+        }
+    }
+}
+
+// BlankLines controls what happens to lines that are empty or only whitespace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlankLines {
+    #[default]
+    Keep,
+    // Yields a single blank line for each run of them
+    Collapse,
+    Drop,
+}
+
+// Removal sets what a follow walk does when its file is deleted or renamed
+// away, such as by aggressive log rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Removal {
+    // Reads what is left through the handle still open, then finishes
+    #[default]
+    Finish,
+    // Fails the walk with Error::Removed
+    Fail,
+    // Follows the file created at the same path in its place, from its
+    // start, waiting for one if there is none yet
+    Successor,
+}
+
+fn is_glob(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.contains(['*', '?', '[']))
+}
+
+// Lists the files matching pattern in path order, failing if there are none.
+fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>, Error> {
+    let pattern = pattern.to_string_lossy();
+    let mut paths = vec![];
+    for path in glob::glob(&pattern)? {
+        let path = path.map_err(io::Error::from)?;
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    if paths.is_empty() {
+        return Err(Error::File(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no files match {}", pattern),
+        )));
+    }
+    Ok(paths)
+}
+
+// Detects the newline convention of a file from its first block.
+pub fn detect_line_ending<T: AsRef<Path>>(path: T) -> Result<LineEnding, Error> {
+    let input = File::open(path)?;
+    read_line_ending(input)
+}
+
+const DETECT_BLOCK_SIZE: usize = 8192;
+
+fn read_line_ending<R: Read>(input: R) -> Result<LineEnding, Error> {
+    let mut block = Vec::with_capacity(DETECT_BLOCK_SIZE);
+    input
+        .take(DETECT_BLOCK_SIZE as u64)
+        .read_to_end(&mut block)?;
+    Ok(LineEnding::detect(&block))
+}
+
+// Like read_line_ending, unless a record delimiter was set.
+fn line_ending_of<R: Read>(
+    input: R,
+    delimiter: Option<u8>,
+    newlines: Newlines,
+) -> Result<LineEnding, Error> {
+    match delimiter {
+        Some(delimiter) => Ok(LineEnding::Delimiter(delimiter)),
+        None => Ok(newlines.apply(read_line_ending(input)?)),
+    }
+}
+
+#[derive(Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct Opener {
+    // A file, or a glob pattern such as "logs/*.log" whose matches are walked
+    // one after the other: in path order forward and in reverse backward.
+    // Only open and the methods built on it accept patterns.
+    #[builder(setter(custom), default)]
+    path: PathBuf,
+    // Files walked one after the other in place of path, such as rotated
+    // logs listed oldest first. Backward walks take them in reverse. Only
+    // open and the methods built on it read more than one file.
+    #[builder(setter(custom), default)]
+    paths: Vec<PathBuf>,
+    // Set through the builder one setting at a time, see config_setters
+    #[builder(setter(custom), default)]
+    config: WalkConfig,
+}
+
+impl Opener {
+    pub fn open(&self) -> Result<LineIter, Error> {
+        self.open_with(self.config.walk_options(self.config.access_hint))
+    }
+
+    // Opens the walk as an ExactSizeIterator, counting its lines up front
+    // even where it otherwise wouldn't. Walks whose length can't be known
+    // before reading them, such as ones positioned by bytes, following the
+    // file or dropping lines, fail with Error::UnknownLength.
+    pub fn open_exact(&self) -> Result<ExactLines, Error> {
+        self.open_counted()?
+            .exact()
+            .map_err(|_| Error::UnknownLength)
+    }
+
+    // Like open, but counts lines up front wherever the walk could do
+    // without, so their count is known and they're numbered in the file.
+    pub(crate) fn open_counted(&self) -> Result<LineIter, Error> {
+        self.open_with(WalkOptions {
+            count_up_front: true,
+            ..self.config.walk_options(self.config.access_hint)
+        })
+    }
+
+    // The settings the opener walks with, defaults included.
+    pub fn config(&self) -> &WalkConfig {
+        &self.config
+    }
+
+    // Walks the file at path from the start through whatever compression,
+    // archive and text encoding it turns out to have. See
+    // WalkConfig::auto to walk it with other settings.
+    pub fn auto<T: AsRef<Path>>(path: T) -> Result<AutoLines, Error> {
+        WalkConfig::default().auto(path)
+    }
+
+    fn open_with(&self, options: WalkOptions) -> Result<LineIter, Error> {
+        let config = &self.config;
+        let mut paths = if !self.paths.is_empty() {
+            self.paths.clone()
+        } else if !is_glob(&self.path) || self.path.exists() {
+            // A file whose name only looks like a pattern is opened as is
+            return walk_file(
+                &self.path,
+                config.position,
+                config.direction,
+                config.max_position,
+                options,
+            );
+        } else {
+            expand_glob(&self.path)?
+        };
+        if matches!(config.direction, Direction::Backward) {
+            paths.reverse();
+        }
+        let mut paths = std::collections::VecDeque::from(paths);
+        let first = paths.pop_front().unwrap();
+        let mut lines = walk_file(
+            first,
+            config.position,
+            config.direction,
+            config.max_position,
+            options.clone(),
+        )?;
+        lines.chain = Some(Box::new(lines::Chain {
+            paths,
+            position: config.position,
+            direction: config.direction,
+            max_position: config.max_position,
+            options,
+        }));
+        Ok(lines)
+    }
+
+    // Walks the rotated logs at path, a glob pattern, newest first, each one
+    // backward from its end, marking where every file starts. When paths is
+    // set instead, they are taken as listed oldest first. Other settings
+    // apply as usual.
+    pub fn sessions(&self) -> Result<Sessions, Error> {
+        let paths = match self.paths.is_empty() {
+            true => sessions::newest_first(expand_glob(&self.path)?),
+            false => self.paths.iter().rev().cloned().collect(),
+        };
+        Ok(Sessions::new(paths, self.config.clone()))
+    }
+
+    // Streams the selected lines to async code. Must be called from within
+    // a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub async fn open_async(&self) -> Result<LineStream, Error> {
+        let path = self.path.clone();
+        let config = &self.config;
+        let (position, direction, max_position) =
+            (config.position, config.direction, config.max_position);
+        let options = config.walk_options(config.access_hint);
+        stream::spawn(move || walk_file(path, position, direction, max_position, options)).await
+    }
+
+    // Reads raw blocks instead of lines, starting at the configured position
+    // and moving in the configured direction.
+    pub fn blocks(&self) -> Result<Blocks, Error> {
+        let input = File::open(&self.path)?;
+        let direction = self.config.direction;
+        let offset = match self.config.position {
+            Position::Start => 0,
+            Position::Middle(line) => {
+                let delimiter = line_ending_of(&input, self.config.delimiter, self.config.newlines)?.delimiter();
+                let line = match direction {
+                    Direction::Forward => line,
+                    Direction::Backward => line + 1,
+                };
+                compute_offset(&mut &input, Position::Middle(line), delimiter)? as u64
+            }
+            Position::End => input.metadata()?.len(),
+            // Blocks are raw, so the offset isn't snapped to a line
+            position @ (Position::Byte(_) | Position::Percent(_)) => {
+                position.byte_offset(input.metadata()?.len())
+            }
+            Position::FromEnd(lines) => {
+                let delimiter = line_ending_of(&input, self.config.delimiter, self.config.newlines)?.delimiter();
+                let lines = match direction {
+                    Direction::Forward => lines,
+                    Direction::Backward => lines.saturating_sub(1),
+                };
+                offset_from_end(&mut &input, lines, delimiter)?
+            }
+        };
+
+        Blocks::new(input, offset, direction)
+    }
+
+    // Walks forward from the configured position like open, but yields the
+    // lines cut into segments by split, so lines of many megabytes such as
+    // minified JSON can be paged through without reading one whole.
+    pub fn segments(&self, split: Split) -> Result<Segments, Error> {
+        let mut input = File::open(&self.path)?;
+        let line_ending = line_ending_of(&mut input, self.config.delimiter, self.config.newlines)?;
+        let delimiter = line_ending.delimiter();
+        let len = input.metadata()?.len();
+        let (first_line, offset) = match self.config.position {
+            position @ (Position::Byte(_) | Position::Percent(_)) => {
+                (1, snap_forward(&mut input, position.byte_offset(len), delimiter)?)
+            }
+            position => {
+                let line = match position {
+                    Position::Middle(line) => line,
+                    Position::End => {
+                        input.seek(SeekFrom::Start(0))?;
+                        count_lines(&mut input, delimiter)?.max(1)
+                    }
+                    Position::FromEnd(lines) => {
+                        input.seek(SeekFrom::Start(0))?;
+                        let total_lines = count_lines(&mut input, delimiter)?;
+                        (total_lines + 1).saturating_sub(lines).max(1)
+                    }
+                    _ => 1,
+                };
+                let offset = compute_offset(&mut input, Position::Middle(line), delimiter)?;
+                (line, offset as u64)
+            }
+        };
+        let (last_line, end) = match self.config.max_position {
+            Some(Position::Start) => (0, None),
+            Some(Position::Middle(line)) => (line, None),
+            Some(max @ (Position::Byte(_) | Position::Percent(_))) => {
+                let end = snap_forward(&mut input, max.byte_offset(len), delimiter)?;
+                (usize::MAX, Some(end))
+            }
+            Some(Position::FromEnd(lines)) => {
+                input.seek(SeekFrom::Start(0))?;
+                let total_lines = count_lines(&mut input, delimiter)?;
+                ((total_lines + 1).saturating_sub(lines), None)
+            }
+            Some(Position::End) | None => (usize::MAX, None),
+        };
+        input.seek(SeekFrom::Start(offset))?;
+        let capacity = buffer_capacity(self.config.buffer_size);
+        let reader: Box<dyn BufRead + Send> = match end {
+            Some(end) => {
+                let input = input.take(end.saturating_sub(offset));
+                Box::new(BufReader::with_capacity(capacity, input))
+            }
+            None => Box::new(BufReader::with_capacity(capacity, input)),
+        };
+        Segments::new(reader, line_ending, split, first_line, last_line)
+    }
+
+    // Yields the file line number and hash of every selected line, for
+    // near-duplicate detection over large files.
+    pub fn hashes(
+        &self,
+        algo: HashAlgo,
+    ) -> Result<impl Iterator<Item = Result<(usize, u64), Error>>, Error> {
+        Ok(self.open_counted()?.numbered().map(move |line| {
+            let (number, line) = line?;
+            Ok((number, algo.hash(&line)))
+        }))
+    }
+
+    // Reads the bytes in range, clamped to the file, along with the line
+    // numbers they span. With to_lines the range first widens to the lines
+    // it touches, so no line is cut.
+    pub fn read_bytes(&self, range: std::ops::Range<u64>, to_lines: bool) -> Result<ByteRange, Error> {
+        let input = File::open(&self.path)?;
+        let delimiter = line_ending_of(&input, self.config.delimiter, self.config.newlines)?.delimiter();
+        ranges::read_bytes(input, range, to_lines, delimiter)
+    }
+
+    // Opens a cursor on the file, placed at the start of the walk's position
+    // and reading lines the way the walk would.
+    pub fn cursor(&self) -> Result<Cursor, Error> {
+        self.cursor_at(self.config.position)
+    }
+
+    // Reads the 1-based line line_no on its own, or None past the last line.
+    // The line is looked up in the index when one is set and still fits the
+    // file, and found by scanning from the start otherwise.
+    pub fn get_line(&self, line_no: usize) -> Result<Option<String>, Error> {
+        if line_no == 0 {
+            return Ok(None);
+        }
+        self.cursor_at(Position::Middle(line_no))?.next_line()
+    }
+
+    // Reads the 1-based line line_no along with up to before lines ahead of
+    // it and after lines following it, in file order and paired with their
+    // line numbers, such as to show the region around a line an error points
+    // at. Empty when the file has no such line.
+    pub fn around(&self, line_no: usize, before: usize, after: usize) -> Result<Vec<(usize, String)>, Error> {
+        if line_no == 0 {
+            return Ok(vec![]);
+        }
+        let first = line_no.saturating_sub(before).max(1);
+        let mut cursor = self.cursor_at(Position::Middle(first))?;
+        let mut lines = vec![];
+        for number in first..=line_no.saturating_add(after) {
+            match cursor.next_line()? {
+                Some(line) => lines.push((number, line)),
+                None => break,
+            }
+        }
+        if lines.last().is_some_and(|&(number, _)| number < line_no) {
+            lines.clear();
+        }
+        Ok(lines)
+    }
+
+    fn cursor_at(&self, position: Position) -> Result<Cursor, Error> {
+        let input = File::open(&self.path)?;
+        let line_ending = line_ending_of(&input, self.config.delimiter, self.config.newlines)?;
+        Cursor::new(
+            input,
+            line_ending,
+            self.config.invalid_utf8,
+            self.config.index.clone(),
+            position,
+        )
+    }
+
+    // Hints that the given byte range is about to be walked so it can be
+    // pulled into the page cache ahead of time.
+    pub fn prefault(&self, range: std::ops::Range<u64>) -> Result<(), Error> {
+        let mut input = File::open(&self.path)?;
+        advise::prefault(&mut input, range)
+    }
+
+    // Walks the file only if its fingerprint differs from previous, such as
+    // the one returned by the last walk. Nothing is read beyond the sampled
+    // blocks when it doesn't.
+    pub fn if_changed(&self, previous: Option<&Fingerprint>) -> Result<IfChanged, Error> {
+        let fingerprint = Fingerprint::of(&self.path)?;
+        if previous == Some(&fingerprint) {
+            return Ok(IfChanged::NotModified);
+        }
+        Ok(IfChanged::Modified {
+            lines: Box::new(self.open()?),
+            fingerprint,
+        })
+    }
+
+    // Reports the newline convention lines are normalized from when opened.
+    // This reads the file on its own; a walk already open has it from
+    // LineIter::line_ending.
+    pub fn line_ending(&self) -> Result<LineEnding, Error> {
+        line_ending_of(File::open(&self.path)?, self.config.delimiter, self.config.newlines)
+    }
+
+    // Calls callback on a background thread with every batch of lines
+    // appended to the file from now on, until the returned handle is shut
+    // down. Lines are only delivered once their delimiter has been written.
+    pub fn on_append<F>(&self, callback: F) -> Result<AppendHandle, Error>
+    where
+        F: FnMut(Vec<String>) + Send + 'static,
+    {
+        let offset = File::open(&self.path)?.metadata()?.len();
+        let line_ending = self.line_ending()?;
+        Ok(watch::spawn(self.path.clone(), offset, line_ending, callback))
+    }
+
+    // Writes the selected lines into a compressed file at path, returning the line count.
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+    pub fn write_compressed<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        codec: Codec,
+    ) -> Result<u64, Error> {
+        let access_hint = self.config.access_hint.or(Some(AccessHint::OneShot));
+        let lines = self.open_with(self.config.walk_options(access_hint))?;
+        compress::write_compressed(lines, path, codec)
+    }
+}
+
+impl OpenerBuilder {
+    // Sets the file to walk, or a glob pattern matching the files to walk.
+    pub fn path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    // Sets the files to walk as a single stream, in order, in place of path.
+    pub fn paths<I, P>(&mut self, paths: I) -> &mut Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let paths = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect();
+        self.paths = Some(paths);
+        self
+    }
+
+    // Replaces every walk setting at once, such as with one shared between
+    // openers.
+    pub fn config(&mut self, config: WalkConfig) -> &mut Self {
+        self.config = Some(config);
+        self
+    }
+
+    // Walks forward over a range of 1-based line numbers, such as 10..=200,
+    // in place of setting position, direction and max_position.
+    pub fn range<R: RangeBounds<usize>>(&mut self, range: R) -> &mut Self {
+        let (position, max_position) = Position::from_range(range);
+        let config = self.config_mut();
+        config.position = position;
+        config.direction = Direction::Forward;
+        config.max_position = max_position;
+        self
+    }
+
+    fn config_mut(&mut self) -> &mut WalkConfig {
+        self.config.get_or_insert_with(WalkConfig::default)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.path.is_none() && self.paths.as_ref().is_none_or(Vec::is_empty) {
+            return Err("Either `path` or `paths` must be set".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Gives OpenerBuilder a setter per WalkConfig field, taking the value the
+// way WalkConfigBuilder does, so the builder stays a thin layer over it.
+macro_rules! config_setters {
+    ($($(#[$attr:meta])* $field:ident: $ty:ty => $wrap:ident),* $(,)?) => {
+        impl OpenerBuilder {
+            $(
+                $(#[$attr])*
+                pub fn $field<V: Into<$ty>>(&mut self, value: V) -> &mut Self {
+                    self.config_mut().$field = config_setters!(@$wrap value.into());
+                    self
+                }
+            )*
+        }
+    };
+    (@set $value:expr) => { $value };
+    (@some $value:expr) => { Some($value) };
+}
+
+config_setters! {
+    position: Position => set,
+    direction: Direction => set,
+    max_position: Position => some,
+    blank_lines: BlankLines => set,
+    access_hint: AccessHint => some,
+    nice_io: bool => set,
+    backend: Backend => set,
+    delimiter: u8 => some,
+    newlines: Newlines => set,
+    keep_terminator: bool => set,
+    invalid_utf8: Utf8Policy => set,
+    #[cfg(feature = "encoding")]
+    encoding: String => some,
+    follow: bool => set,
+    on_removal: Removal => set,
+    stats: WalkStats => some,
+    step: usize => some,
+    limit: usize => some,
+    filter: LinePredicate => some,
+    map: LineMap => some,
+    index: Arc<LineIndex> => some,
+    buffer_size: usize => some,
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+    decompress_ahead: usize => some,
+}
+
+// WalkConfig holds every Opener setting except the path, with its default
+// filled in, so it can be inspected or applied to many files without
+// rebuilding it.
+#[derive(Builder, Clone, Default)]
+#[builder(default)]
+pub struct WalkConfig {
+    #[builder(setter(into))]
+    pub position: Position,
+    #[builder(setter(into))]
+    pub direction: Direction,
+    // Where the walk stops, the end of the file when None
+    #[builder(setter(into, strip_option))]
+    pub max_position: Option<Position>,
+    #[builder(setter(into))]
+    pub blank_lines: BlankLines,
+    // Picked from the direction of the walk when None
+    #[builder(setter(into, strip_option))]
+    pub access_hint: Option<AccessHint>,
+    // Lowers IO priority and backs off between reads, for background scans
+    #[builder(setter(into))]
+    pub nice_io: bool,
+    #[builder(setter(into))]
+    pub backend: Backend,
+    // Splits records on this byte, such as b'\0' or b';', instead of the
+    // detected line break, in either direction
+    #[builder(setter(into, strip_option))]
+    pub delimiter: Option<u8>,
+    #[builder(setter(into))]
+    pub newlines: Newlines,
+    // Yields lines with their terminator, so joining them gives back the
+    // bytes as stored. The last line has none if the file doesn't end with one.
+    #[builder(setter(into))]
+    pub keep_terminator: bool,
+    #[builder(setter(into))]
+    pub invalid_utf8: Utf8Policy,
+    // Transcodes files from this encoding to UTF-8 before walking them,
+    // given by a WHATWG label such as "utf-16le" or "latin1". The
+    // transcoded text is spooled up front, like a compressed file's.
+    #[cfg(feature = "encoding")]
+    #[builder(setter(into, strip_option))]
+    pub encoding: Option<String>,
+    // Keeps walking forward as lines are appended instead of stopping at the
+    // end of the file, like tail -f
+    #[builder(setter(into))]
+    pub follow: bool,
+    // What following does once the file is removed
+    #[builder(setter(into))]
+    pub on_removal: Removal,
+    // Shared counters updated as lines are read, by every walk using this
+    // config
+    #[builder(setter(into, strip_option))]
+    pub stats: Option<WalkStats>,
+    // Yields every nth line, such as to downsample a large file. The lines
+    // in between are skipped as they're read, without being decoded.
+    #[builder(setter(into, strip_option))]
+    pub step: Option<usize>,
+    // Stops after yielding this many lines, from wherever the walk starts
+    // and in either direction, unlike max_position which is a line number
+    #[builder(setter(into, strip_option))]
+    pub limit: Option<usize>,
+    // Skips the lines it returns false for, after blank lines are handled
+    #[builder(setter(into, strip_option))]
+    pub filter: Option<LinePredicate>,
+    // Transforms every line yielded, after filter has kept it
+    #[builder(setter(into, strip_option))]
+    pub map: Option<LineMap>,
+    // Looks lines up in a prebuilt index instead of counting them, as long
+    // as the file is still the size it was indexed at
+    #[builder(setter(into, strip_option))]
+    pub index: Option<Arc<LineIndex>>,
+    // Bytes read from the file at a time, 8 KiB by default. Larger buffers
+    // cut down on reads when walking large files sequentially.
+    #[builder(setter(into, strip_option))]
+    pub buffer_size: Option<usize>,
+    // Decompresses compressed files on a background thread while lines are
+    // read, buffering up to this many chunks ahead. Only forward walks from
+    // the start or a line use it; other walks decompress up front.
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+    #[builder(setter(into, strip_option))]
+    pub decompress_ahead: Option<usize>,
+}
+
+impl From<WalkConfig> for OpenerBuilder {
+    fn from(config: WalkConfig) -> Self {
+        let mut builder = OpenerBuilder::default();
+        builder.config(config);
+        builder
+    }
+}
+
+impl From<&Opener> for WalkConfig {
+    fn from(opener: &Opener) -> Self {
+        opener.config.clone()
+    }
+}
+
+impl From<Opener> for WalkConfig {
+    fn from(opener: Opener) -> Self {
+        opener.config
+    }
+}
+
+// The names WalkConfig went by before Opener stored one
+pub type OpenerTemplate = WalkConfig;
+pub type OpenerTemplateBuilder = WalkConfigBuilder;
+pub type OpenerTemplateBuilderError = WalkConfigBuilderError;
+
+impl WalkConfig {
+    pub fn open<T: AsRef<Path>>(&self, path: T) -> Result<LineIter, Error> {
+        self.opener(path).open()
+    }
+
+    // Lazily opens each path in turn with these settings.
+    pub fn open_all<'a, I, T>(
+        &'a self,
+        paths: I,
+    ) -> impl Iterator<Item = Result<LineIter, Error>> + 'a
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: 'a,
+        T: AsRef<Path>,
+    {
+        paths.into_iter().map(move |path| self.open(path))
+    }
+
+    // Walks any seekable source, such as an in-memory buffer, with these
+    // settings. Following appended lines only works on files.
+    pub fn open_reader<R: Read + Seek + Send + 'static>(&self, reader: R) -> Result<LineIter, Error> {
+        walk_source(
+            reader,
+            None,
+            self.position,
+            self.direction,
+            self.max_position,
+            self.walk_options(self.access_hint),
+        )
+    }
+
+    // Peels the compression, archive and text encoding layers off the file
+    // at path, judged by their magic numbers, and walks the lines inside
+    // with these settings. The layers found are reported with the lines.
+    pub fn auto<T: AsRef<Path>>(&self, path: T) -> Result<AutoLines, Error> {
+        let path = path.as_ref();
+        let (decoded, layers) = auto::resolve(path)?;
+        let lines = match decoded {
+            Some(decoded) => self.open_reader(decoded)?,
+            None => self.open(path)?,
+        };
+        Ok(AutoLines::new(lines, layers))
+    }
+
+    // Builds a standalone Opener for path with these settings.
+    pub fn opener<T: AsRef<Path>>(&self, path: T) -> Opener {
+        Opener {
+            path: path.as_ref().to_path_buf(),
+            paths: vec![],
+            config: self.clone(),
+        }
+    }
+
+    fn walk_options(&self, access_hint: Option<AccessHint>) -> WalkOptions {
+        WalkOptions {
+            blank_lines: self.blank_lines,
+            access_hint,
+            nice_io: self.nice_io,
+            backend: self.backend,
+            delimiter: self.delimiter,
+            newlines: self.newlines,
+            keep_terminator: self.keep_terminator,
+            invalid_utf8: self.invalid_utf8,
+            #[cfg(feature = "encoding")]
+            encoding: self.encoding.clone(),
+            follow: self.follow,
+            on_removal: self.on_removal,
+            stats: self.stats.clone(),
+            step: self.step,
+            limit: self.limit,
+            filter: self.filter.clone(),
+            map: self.map.clone(),
+            index: self.index.clone(),
+            buffer_size: self.buffer_size,
+            count_up_front: false,
+            #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+            decompress_ahead: self.decompress_ahead,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("File error.")]
+    File(#[from] io::Error),
+
+    #[cfg(feature = "prometheus")]
+    #[error("Metrics error.")]
+    Metrics(#[from] prometheus::Error),
+
+    #[error("Invalid pattern.")]
+    Pattern(#[from] regex::Error),
+
+    #[cfg(feature = "sled")]
+    #[error("State store error.")]
+    Sled(#[from] sled::Error),
+
+    #[cfg(feature = "sqlite")]
+    #[error("State store error.")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Invalid glob pattern.")]
+    Glob(#[from] glob::PatternError),
+
+    #[error("Invalid keyword patterns.")]
+    Keywords(#[from] aho_corasick::BuildError),
+
+    #[error("Invalid JSON pointer {pointer}.")]
+    InvalidPointer { pointer: String },
+
+    #[error("Invalid rewrite expression {expr}.")]
+    InvalidRewrite { expr: String },
+
+    #[cfg(feature = "icu")]
+    #[error("Unsupported locale {locale}.")]
+    Locale { locale: String },
+
+    #[error("Invalid patch.")]
+    InvalidPatch,
+
+    #[error("The patch was made against a different base file.")]
+    PatchBase,
+
+    #[cfg(feature = "wasm")]
+    #[error("Plugin error.")]
+    Plugin(#[from] wasmi::Error),
+
+    #[cfg(feature = "wasm")]
+    #[error("Plugin is missing the {name} export.")]
+    PluginExport { name: String },
+
+    #[cfg(feature = "wasm")]
+    #[error("Plugin returned an invalid line.")]
+    PluginOutput,
+
+    #[cfg(feature = "encoding")]
+    #[error("Unknown encoding {label}.")]
+    UnknownEncoding { label: String },
+
+    #[error("Line {line} is not valid UTF-8.")]
+    InvalidUtf8 { line: usize },
+
+    #[error("The line at byte {offset} is not valid UTF-8.")]
+    InvalidUtf8At { offset: u64 },
+
+    #[error("Cannot go {dir} from the {pos} position.")]
+    InvalidDirection { pos: Position, dir: Direction },
+
+    #[error("Max line position {max} is behind the current line position {pos} when the direction is {dir}.")]
+    MaxLinePosition {
+        pos: usize,
+        max: usize,
+        dir: Direction,
+    },
+
+    #[error("Max position {max} is behind the position {pos} when the direction is {dir}.")]
+    InvalidRange {
+        pos: Position,
+        max: Position,
+        dir: Direction,
+    },
+
+    #[error("Only walks over a single uncompressed file can be read from both ends.")]
+    OneEnded,
+
+    #[error("The number of lines the walk yields isn't known before reading them.")]
+    UnknownLength,
+
+    #[error("The file was removed while it was followed.")]
+    Removed,
+}
+
+// The main file of this crate. Opens a file and reads it according to your specification.
+// Every line is read before it returns; v2::open reads them on demand.
+#[deprecated(note = "Use v2::open, which reads lines on demand.")]
+pub fn open_file<T: AsRef<Path>, P: Into<Position>, D: Into<Direction>>(
+    path: T,
+    position: P,
+    direction: D,
+    max_position: Option<Position>,
+) -> Result<IntoIter<String>, Error> {
+    let lines = walk_file(path, position, direction, max_position, WalkOptions::default())?;
+    Ok(lines.collect::<Result<Vec<_>, _>>()?.into_iter())
+}
+
+// Like open_file, but reads lines on demand instead of all up front, so a
+// read error partway through surfaces as that line's Result rather than
+// failing the whole call.
+pub fn open_lines<T: AsRef<Path>, P: Into<Position>, D: Into<Direction>>(
+    path: T,
+    position: P,
+    direction: D,
+    max_position: Option<Position>,
+) -> Result<LineIter, Error> {
+    walk_file(path, position, direction, max_position, WalkOptions::default())
+}
+
+// Like open_lines, but walks forward over a range of 1-based line numbers,
+// such as 10..=200. A range past the end of the file yields no lines, while
+// an empty one such as 5..5 is an error.
+pub fn open_range<T: AsRef<Path>, R: RangeBounds<usize>>(path: T, range: R) -> Result<LineIter, Error> {
+    let (position, max_position) = Position::from_range(range);
+    walk_file(path, position, Direction::Forward, max_position, WalkOptions::default())
+}
+
+// Like open_lines, but pairs every line with its line number in the file.
+pub fn open_numbered<T: AsRef<Path>, P: Into<Position>, D: Into<Direction>>(
+    path: T,
+    position: P,
+    direction: D,
+    max_position: Option<Position>,
+) -> Result<Numbered, Error> {
+    let options = WalkOptions {
+        count_up_front: true,
+        ..WalkOptions::default()
+    };
+    Ok(walk_file(path, position, direction, max_position, options)?.numbered())
+}
+
+// Reads the first n lines of the file at path, stopping as soon as they're
+// read.
+pub fn head<T: AsRef<Path>>(path: T, n: usize) -> Result<Vec<String>, Error> {
+    let options = WalkOptions {
+        limit: Some(n),
+        ..WalkOptions::default()
+    };
+    walk_file(path, Position::Start, Direction::Forward, None, options)?.collect()
+}
+
+// Reads the last n lines of the file at path, in file order. They're read
+// backward from the end, so nothing before them is scanned.
+pub fn tail<T: AsRef<Path>>(path: T, n: usize) -> Result<Vec<String>, Error> {
+    let options = WalkOptions {
+        limit: Some(n),
+        ..WalkOptions::default()
+    };
+    let mut lines = walk_file(path, Position::End, Direction::Backward, None, options)?.collect::<Result<Vec<_>, _>>()?;
+    lines.reverse();
+    Ok(lines)
+}
+
+// WalkOptions carries the optional settings of a walk beyond its position
+// and bounds
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WalkOptions {
+    pub(crate) blank_lines: BlankLines,
+    // Picked from the direction of the walk when not set
+    pub(crate) access_hint: Option<AccessHint>,
+    pub(crate) nice_io: bool,
+    pub(crate) backend: Backend,
+    // Overrides the detected line ending
+    pub(crate) delimiter: Option<u8>,
+    pub(crate) newlines: Newlines,
+    pub(crate) keep_terminator: bool,
+    pub(crate) invalid_utf8: Utf8Policy,
+    // Label of the encoding to transcode from, for files that aren't UTF-8
+    #[cfg(feature = "encoding")]
+    pub(crate) encoding: Option<String>,
+    // Only applies to forward walks
+    pub(crate) follow: bool,
+    pub(crate) on_removal: Removal,
+    pub(crate) stats: Option<WalkStats>,
+    pub(crate) step: Option<usize>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) filter: Option<LinePredicate>,
+    pub(crate) map: Option<LineMap>,
+    pub(crate) index: Option<Arc<LineIndex>>,
+    pub(crate) buffer_size: Option<usize>,
+    // Counts lines up front even where the walk could do without, so their
+    // count is known and they're numbered in the file rather than as read
+    pub(crate) count_up_front: bool,
+    // Chunks to decompress ahead of the walk, for compressed files
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+    pub(crate) decompress_ahead: Option<usize>,
+}
+
+impl WalkOptions {
+    // The index to look lines up in, if one was given and still fits input.
+    // Leaves input at its start.
+    fn index_for<R: Seek>(&self, input: &mut R, delimiter: u8) -> Result<Option<&LineIndex>, Error> {
+        let Some(index) = &self.index else {
+            return Ok(None);
+        };
+        let len = input.seek(SeekFrom::End(0))?;
+        input.seek(SeekFrom::Start(0))?;
+        Ok(index.fits(len, delimiter).then_some(&**index))
+    }
+}
+
+// Walks the lines of any seekable source, such as an in-memory buffer or a
+// file that is already open, with the same rules as open_file. Lines are
+// read on demand.
+pub fn open_reader<R, P, D>(
+    reader: R,
+    position: P,
+    direction: D,
+    max_position: Option<Position>,
+) -> Result<LineIter, Error>
+where
+    R: Read + Seek + Send + 'static,
+    P: Into<Position>,
+    D: Into<Direction>,
+{
+    walk_source(
+        reader,
+        None,
+        position.into(),
+        direction.into(),
+        max_position,
+        WalkOptions::default(),
+    )
+}
+
+// Does the work behind open_file.
+pub(crate) fn walk_file<T: AsRef<Path>, P: Into<Position>, D: Into<Direction>>(
+    path: T,
+    position: P,
+    direction: D,
+    max_position: Option<Position>,
+    options: WalkOptions,
+) -> Result<LineIter, Error> {
+    let path = path.as_ref();
+    let input = File::open(path)?;
+    let (position, direction) = (position.into(), direction.into());
+    #[cfg(feature = "encoding")]
+    let encoding = encoding::lookup(options.encoding.as_deref())?;
+    // Compressed files are walked by their decompressed contents, which
+    // can't be followed as they grow
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+    if let Some(decoder) = decompress::decoder(&input, path)? {
+        #[cfg(feature = "encoding")]
+        if let Some(encoding) = encoding {
+            let transcoded = encoding::transcode(decoder, encoding)?;
+            return walk_source(transcoded, None, position, direction, max_position, options);
+        }
+        // The end of the contents isn't known until it is reached
+        let by_lines = !matches!(
+            max_position,
+            Some(Position::Byte(_) | Position::FromEnd(_) | Position::Percent(_))
+        );
+        if let (Some(capacity), Direction::Forward, Position::Start | Position::Middle(_), true) =
+            (options.decompress_ahead, direction, position, by_lines)
+        {
+            return decompress::walk_ahead(decoder, capacity, position, max_position, options);
+        }
+        let spooled = spool::spool(decoder)?;
+        return walk_source(spooled, None, position, direction, max_position, options);
+    }
+    // So are files in another encoding, by their UTF-8 transcoding
+    #[cfg(feature = "encoding")]
+    if let Some(encoding) = encoding {
+        let transcoded = encoding::transcode(&input, encoding)?;
+        return walk_source(transcoded, None, position, direction, max_position, options);
+    }
+    // Plain files can be read from the other end as well, by a second walk
+    // opened when first asked for
+    let back = (!options.follow).then(|| lines::Back::Unopened {
+        path: path.to_path_buf(),
+        options: WalkOptions {
+            limit: None,
+            ..options.clone()
+        },
+    });
+    let on_removal = options.on_removal;
+    let mut lines = match options.backend.resolve(input.metadata()?.len(), position, direction) {
+        #[cfg(feature = "mmap")]
+        Backend::Mmap if !options.follow && bom::read(&mut &input)?.is_none() => {
+            mmap::walk_mapped(input, position, direction, max_position, options)?
+        }
+        _ => {
+            let hinted = input.try_clone()?;
+            walk_source(
+                input,
+                Some(hinted),
+                position,
+                direction,
+                max_position,
+                options,
+            )?
+        }
+    };
+    lines.back = back.map(Box::new);
+    if let Some(follow) = lines.follow.as_mut() {
+        follow.watch(path, on_removal);
+    }
+    Ok(lines)
+}
+
+// Does the work behind walk_file and open_reader. The file, when the source
+// is one, is used for access hints and for following appended lines. A
+// byte order mark is stripped, and UTF-16 text is walked by its UTF-8
+// transcoding, which can't be followed.
+fn walk_source<R: Read + Seek + Send + 'static>(
+    mut input: R,
+    file: Option<File>,
+    position: Position,
+    direction: Direction,
+    max_position: Option<Position>,
+    options: WalkOptions,
+) -> Result<LineIter, Error> {
+    match bom::read(&mut input)? {
+        None => walk_unmarked(input, file, position, direction, max_position, options),
+        Some((TextEncoding::Utf8, mark)) => {
+            let input = bom::Unmarked::new(input, mark)?;
+            walk_unmarked(input, file, position, direction, max_position, options)
+        }
+        Some((encoding, mark)) => {
+            input.seek(SeekFrom::Start(mark))?;
+            let little_endian = encoding == TextEncoding::Utf16Le;
+            let transcoded = auto::transcode_utf16(Box::new(input), little_endian)?;
+            walk_unmarked(transcoded, None, position, direction, max_position, options)
+        }
+    }
+}
+
+fn walk_unmarked<R: Read + Seek + Send + 'static>(
+    input: R,
+    file: Option<File>,
+    position: Position,
+    direction: Direction,
+    max_position: Option<Position>,
+    options: WalkOptions,
+) -> Result<LineIter, Error> {
+    let follow = options.follow && file.is_some();
+    let mut input = throttle::Throttled::new(input, options.nice_io);
+    let plan = WalkPlan::new(&mut input, position, direction, max_position, follow, &options)?;
+    let capacity = buffer_capacity(options.buffer_size);
+    let reader: Box<dyn BufRead + Send> = match (direction, plan.window) {
+        (Direction::Forward, None) => Box::new(BufReader::with_capacity(capacity, input)),
+        (Direction::Forward, Some((start, end))) => {
+            let window = bounded::Bounded::window(input, start, end)?;
+            Box::new(BufReader::with_capacity(capacity, window))
+        }
+        (Direction::Backward, window) => {
+            let (lower, upper) = window.unwrap_or((0, plan.start_offset));
+            let window = bounded::Bounded::window(input, lower, upper)?;
+            Box::new(RevBufReader::with_capacity(capacity, window))
+        }
+    };
+    Ok(plan.into_lines(reader, file, options))
+}
+
+// WalkPlan is where a walk starts and ends, worked out before any line is
+// read, so each backend only has to supply a reader positioned at the start
+pub(crate) struct WalkPlan {
+    line_ending: LineEnding,
+    direction: Direction,
+    total_lines: usize,
+    start_offset: u64,
+    // The byte range the walk is confined to, when positioned by bytes
+    pub(crate) window: Option<(u64, u64)>,
+    curr_line: usize,
+    last_line: usize,
+    counted: bool,
+    follow: bool,
+}
+
+impl WalkPlan {
+    // Validates the bounds of the walk and leaves input at its start. Only
+    // forward walks can follow.
+    pub(crate) fn new<R: Read + Seek>(
+        input: &mut R,
+        position: Position,
+        direction: Direction,
+        max_position: Option<Position>,
+        follow: bool,
+        options: &WalkOptions,
+    ) -> Result<Self, Error> {
+        input.seek(SeekFrom::Start(0))?;
+        let line_ending = line_ending_of(&mut *input, options.delimiter, options.newlines)?;
+        input.seek(SeekFrom::Start(0))?;
+        let index = options.index_for(&mut *input, line_ending.delimiter())?;
+
+        // A backward walk from the end to the start reads lines as it scans
+        // back, without counting them first, unless an index already has
+        let from_end = matches!(
+            (direction, position, max_position),
+            (Direction::Backward, Position::End, None | Some(Position::Start))
+        ) && index.is_none()
+            && !options.count_up_front;
+        if from_end || position.by_bytes() || max_position.is_some_and(Position::by_bytes) {
+            return Self::by_bytes(input, line_ending, position, direction, max_position, follow, options);
+        }
+
+        // Forward walks stop where the file does, so they only need the line
+        // count to place a position or bound relative to the end
+        let from_end = |position: Position| matches!(position, Position::End | Position::FromEnd(_));
+        let counted = matches!(direction, Direction::Backward)
+            || options.count_up_front
+            || follow
+            || from_end(position)
+            || max_position.is_some_and(from_end);
+        let total_lines = match index {
+            Some(index) => index.line_count(),
+            None if counted => count_lines(&mut *input, line_ending.delimiter())?,
+            None => 0,
+        };
+        let counted = counted || index.is_some();
+        // Byte positions were handled above, so only Start, Middle and End are
+        // left once FromEnd is resolved
+        let position = position.resolve(total_lines);
+        let max_position = max_position.map(|max_position| max_position.resolve(total_lines));
+
+        let position_number = match position {
+            Position::Start => 1,
+            Position::Middle(n) => n,
+            _ => total_lines,
+        };
+
+        let max_position_number = max_position.map(|pos| match pos {
+            Position::Start => 0,
+            Position::Middle(n) => n,
+            _ => total_lines,
+        });
+
+        let follow = follow && matches!(direction, Direction::Forward);
+        // Following from the end waits for the first appended line
+        if matches!(
+            (direction, position),
+            (Direction::Backward, Position::Start) | (Direction::Forward, Position::End)
+        ) && !(follow && position == Position::End)
+        {
+            return Err(Error::InvalidDirection { pos: position, dir: direction });
+        } else if let Some(max_position_number) = max_position_number {
+            if (matches!(direction, Direction::Forward) && max_position_number < position_number)
+                || (matches!(direction, Direction::Backward) && max_position_number > position_number)
+            {
+                return Err(Error::MaxLinePosition {
+                    pos: position_number,
+                    max: max_position_number,
+                    dir: direction,
+                });
+            }
+        }
+
+        // A backward walk from a line starts at the end of it, which is where the
+        // next line (or the file) begins.
+        let new_line_pos = match position {
+            Position::Middle(num) if matches!(direction, Direction::Backward) && num >= total_lines => {
+                Position::End
+            }
+            Position::Middle(num) if matches!(direction, Direction::Backward) => {
+                Position::Middle(num + 1)
+            }
+            _ => position,
+        };
+
+        let seek_to = match new_line_pos {
+            Position::Start => SeekFrom::Start(0),
+            Position::Middle(line) => SeekFrom::Start(match index {
+                Some(index) => index.offset(line),
+                None => compute_offset(&mut *input, new_line_pos, line_ending.delimiter())? as u64,
+            }),
+            _ => SeekFrom::End(0),
+        };
+        let start_offset = input.seek(seek_to)?;
+
+        if let (Some(stats), Direction::Forward) = (&options.stats, direction) {
+            let len = input.seek(SeekFrom::End(0))?;
+            input.seek(SeekFrom::Start(start_offset))?;
+            stats.record_start(start_offset, len);
+        }
+
+        let curr_line = match position {
+            Position::Start => 1,
+            Position::Middle(line) => line,
+            _ if follow => total_lines + 1,
+            _ => total_lines,
+        };
+        let last_line = match (direction, max_position_number) {
+            (Direction::Forward, Some(max)) if follow => max,
+            (Direction::Forward, None) if follow => usize::MAX,
+            (Direction::Forward, Some(max)) if counted => max.min(total_lines),
+            (Direction::Forward, None) if counted => total_lines,
+            (Direction::Forward, Some(max)) => max,
+            (Direction::Forward, None) => usize::MAX,
+            (Direction::Backward, Some(max)) => max.max(1),
+            (Direction::Backward, None) => 1,
+        };
+
+        Ok(WalkPlan {
+            line_ending,
+            direction,
+            total_lines,
+            start_offset,
+            window: None,
+            curr_line,
+            last_line,
+            counted,
+            follow,
+        })
+    }
 
-// Direction indicates whether to parse the file moving up or down
-#[derive(Debug, Clone, Copy)]
-pub enum Direction {
-    Forward,
-    Backward,
-}
+    // Works out the byte range of a walk positioned by byte offsets, which
+    // are snapped to line boundaries instead of counting lines, or of a
+    // backward walk from the end. Lines are numbered from 1 in the order
+    // they are read.
+    fn by_bytes<R: Read + Seek>(
+        input: &mut R,
+        line_ending: LineEnding,
+        position: Position,
+        direction: Direction,
+        max_position: Option<Position>,
+        follow: bool,
+        options: &WalkOptions,
+    ) -> Result<Self, Error> {
+        let delimiter = line_ending.delimiter();
+        let index = options.index_for(&mut *input, delimiter)?;
+        let len = input.seek(SeekFrom::End(0))?;
+        let line_offset = |input: &mut R, line: usize| match index {
+            Some(index) => Ok(index.offset(line)),
+            None => compute_offset(input, Position::Middle(line), delimiter).map(|offset| offset as u64),
+        };
+        let follow = follow && matches!(direction, Direction::Forward) && max_position.is_none();
+        let invalid_range = || Error::InvalidRange {
+            pos: position,
+            max: max_position.unwrap_or_default(),
+            dir: direction,
+        };
+
+        let (start_offset, window) = match direction {
+            Direction::Forward => {
+                let start = match position {
+                    Position::Start => 0,
+                    Position::Middle(line) => line_offset(input, line)?,
+                    Position::End if follow => len,
+                    Position::End => {
+                        return Err(Error::InvalidDirection { pos: position, dir: direction })
+                    }
+                    Position::Byte(_) | Position::Percent(_) => {
+                        snap_forward(input, position.byte_offset(len), delimiter)?
+                    }
+                    Position::FromEnd(lines) => offset_from_end(input, lines, delimiter)?,
+                };
+                let end = match max_position {
+                    Some(Position::Start) => Some(0),
+                    Some(Position::Middle(line)) => Some(line_offset(input, line + 1)?),
+                    Some(max @ (Position::Byte(_) | Position::Percent(_))) => {
+                        Some(snap_forward(input, max.byte_offset(len), delimiter)?)
+                    }
+                    Some(Position::FromEnd(lines)) => {
+                        Some(offset_from_end(input, lines.saturating_sub(1), delimiter)?)
+                    }
+                    Some(Position::End) | None => None,
+                };
+                if end.is_some_and(|end| end < start) {
+                    return Err(invalid_range());
+                }
+                (start, end.map(|end| (start, end)))
+            }
+            Direction::Backward => {
+                let start = match position {
+                    Position::Start => {
+                        return Err(Error::InvalidDirection { pos: position, dir: direction })
+                    }
+                    Position::Middle(line) => line_offset(input, line + 1)?,
+                    Position::End => len,
+                    Position::Byte(_) | Position::Percent(_) => {
+                        snap_backward(input, position.byte_offset(len), delimiter)?
+                    }
+                    Position::FromEnd(lines) => {
+                        offset_from_end(input, lines.saturating_sub(1), delimiter)?
+                    }
+                };
+                let lower = match max_position {
+                    Some(Position::Start) | None => 0,
+                    Some(Position::Middle(line)) => line_offset(input, line)?,
+                    Some(max @ (Position::Byte(_) | Position::Percent(_))) => {
+                        snap_backward(input, max.byte_offset(len), delimiter)?
+                    }
+                    Some(Position::FromEnd(lines)) => offset_from_end(input, lines, delimiter)?,
+                    Some(Position::End) => len,
+                };
+                if lower > start {
+                    return Err(invalid_range());
+                }
+                (start, Some((lower, start)))
+            }
+        };
+        input.seek(SeekFrom::Start(start_offset))?;
+
+        if let (Some(stats), Direction::Forward) = (&options.stats, direction) {
+            stats.record_start(start_offset, len);
+        }
 
-impl Default for Direction {
-    fn default() -> Self {
-        Direction::Forward
+        Ok(WalkPlan {
+            line_ending,
+            direction,
+            total_lines: 0,
+            start_offset,
+            window,
+            curr_line: 1,
+            last_line: usize::MAX,
+            counted: false,
+            follow,
+        })
     }
-}
 
-impl From<&str> for Direction {
-    fn from(value: &str) -> Self {
-        Direction::from(value.to_string())
+    // Plans a forward walk over a stream that can't be scanned up front,
+    // such as decompressed contents, from line first_line at start_offset
+    // up to line last_line. Lines aren't counted, so the walk simply ends
+    // with the stream.
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+    pub(crate) fn streaming(line_ending: LineEnding, first_line: usize, last_line: usize, start_offset: u64) -> Self {
+        WalkPlan {
+            line_ending,
+            direction: Direction::Forward,
+            total_lines: 0,
+            start_offset,
+            window: None,
+            curr_line: first_line,
+            last_line,
+            counted: false,
+            follow: false,
+        }
     }
-}
 
-impl From<String> for Direction {
-    fn from(value: String) -> Self {
-        if value == "backward" {
-            return Direction::Backward;
+    // Builds the walk over reader, which reads forward from the start of the
+    // walk or backward from it.
+    pub(crate) fn into_lines(
+        self,
+        reader: Box<dyn BufRead + Send>,
+        file: Option<File>,
+        options: WalkOptions,
+    ) -> LineIter {
+        let access_hint = options
+            .access_hint
+            .unwrap_or_else(|| AccessHint::for_walk(self.direction));
+        if let Some(file) = &file {
+            advise::advise(file, access_hint);
         }
 
-        Direction::default()
+        LineIter {
+            reader,
+            line_ending: self.line_ending,
+            direction: self.direction,
+            curr_line: self.curr_line,
+            last_line: self.last_line,
+            keep_terminator: options.keep_terminator,
+            invalid_utf8: options.invalid_utf8,
+            blank_lines: options.blank_lines,
+            previous_blank: false,
+            filter: options.filter.clone(),
+            map: options.map.clone(),
+            step: options.step.unwrap_or(1).max(1),
+            skip: 0,
+            remaining: options.limit,
+            offset: self.start_offset,
+            window: self.window,
+            stats: options.stats,
+            hinted: file,
+            access_hint,
+            follow: self.follow.then(|| {
+                lines::Follow::new(
+                    self.start_offset,
+                    buffer_capacity(options.buffer_size),
+                    options.nice_io,
+                )
+            }),
+            backend: Backend::Buffered,
+            chain: None,
+            back: None,
+            peeked: std::collections::VecDeque::new(),
+            counted: self.counted,
+            // Walks that start past the last line yield nothing
+            done: self.counted && self.curr_line > self.total_lines && !self.follow,
+        }
     }
 }
 
-impl From<Option<String>> for Direction {
-    fn from(value: Option<String>) -> Self {
-        if let Some(dir) = value {
-            return Direction::from(dir);
-        }
+// Returns offset if it is the start of a line, or else the start of the line
+// after it. Offsets past the end map to the end.
+fn snap_forward<R: Read + Seek>(input: &mut R, offset: u64, delimiter: u8) -> Result<u64, Error> {
+    let len = input.seek(SeekFrom::End(0))?;
+    if offset == 0 || offset >= len {
+        return Ok(offset.min(len));
+    }
 
-        Direction::default()
+    input.seek(SeekFrom::Start(offset - 1))?;
+    let mut reader = BufReader::new(input);
+    let mut snapped = offset - 1;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(len);
+        }
+        if let Some(idx) = memchr::memchr(delimiter, buf) {
+            return Ok(snapped + idx as u64 + 1);
+        }
+        snapped += buf.len() as u64;
+        let consumed = buf.len();
+        reader.consume(consumed);
     }
 }
 
-#[derive(Builder)]
-pub struct Opener {
-    path: String,
-    #[builder(setter(into, strip_option), default)]
-    position: Option<Position>,
-    #[builder(setter(into, strip_option), default)]
-    direction: Option<Direction>,
-    #[builder(setter(into, strip_option), default)]
-    max_position: Option<Position>,
-}
+// Returns the start of the line offset falls in, or offset itself if a line
+// starts there. Offsets past the end map to the end.
+fn snap_backward<R: Read + Seek>(input: &mut R, offset: u64, delimiter: u8) -> Result<u64, Error> {
+    let len = input.seek(SeekFrom::End(0))?;
+    if offset >= len {
+        return Ok(len);
+    }
 
-impl Opener {
-    pub fn open(&self) -> Result<IntoIter<String>, Error> {
-        open_file(
-            &self.path,
-            self.position.unwrap_or_default(),
-            self.direction.unwrap_or_default(),
-            self.max_position,
-        )
+    let mut reader = RevBufReader::new(bounded::Bounded::new(input, offset)?);
+    let mut snapped = offset;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(idx) = memchr::memrchr(delimiter, buf) {
+            return Ok(snapped - (buf.len() - idx - 1) as u64);
+        }
+        snapped -= buf.len() as u64;
+        let consumed = buf.len();
+        reader.consume(consumed);
     }
 }
 
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error("File error.")]
-    File(#[from] io::Error),
-
-    #[error("Cannot go {dir:?} from the {pos:?} position.")]
-    InvalidDirection {
-        pos: String,
-        dir: String
-    },
+// Returns the offset the nth line from the end starts at, scanning backward
+// from the end. Lines before the first map to the start.
+fn offset_from_end<R: Read + Seek>(input: &mut R, lines: usize, delimiter: u8) -> Result<u64, Error> {
+    let len = input.seek(SeekFrom::End(0))?;
+    if lines == 0 {
+        return Ok(len);
+    }
 
-    #[error("Cannot have a max line position {cmp:?} than the current line position when the direction is {dir:?}.")]
-    MaxLinePosition {
-        cmp: String,
-        dir: String,
+    let mut reader = RevBufReader::new(input);
+    let mut offset = len;
+    let mut remaining = lines;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // The delimiter ending the last line doesn't start another one
+        let searched = match offset == len && buf.last() == Some(&delimiter) {
+            true => buf.len() - 1,
+            false => buf.len(),
+        };
+        for idx in memchr::memrchr_iter(delimiter, &buf[..searched]) {
+            remaining -= 1;
+            if remaining == 0 {
+                return Ok(offset - (buf.len() - idx - 1) as u64);
+            }
+        }
+        offset -= buf.len() as u64;
+        let consumed = buf.len();
+        reader.consume(consumed);
     }
 }
 
-// The main file of this crate. Opens a file and reads it according to your specification.
-pub fn open_file<T: Into<String>, P: Into<Position>, D: Into<Direction>>(
-    path: T,
-    position: P,
-    direction: D,
-    max_position: Option<Position>,
-) -> Result<IntoIter<String>, Error> {
-    let path = path.into();
-    let position = position.into();
-    let direction = direction.into();
+// Bytes read at a time by a walk without a buffer size set
+const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
 
-    let mut input = match File::open(path.as_str()) {
-        Ok(v) => v,
-        Err(e) => return Err(Error::File(e))
-    };
-        
-    let buf = BufReader::new(&input);
-    let total_lines = buf.lines().count();
-
-    let position_number = match position {
-        Position::Start => 1,
-        Position::Middle(n) => n,
-        Position::End => total_lines,
-    };
+// The capacity of the buffer a walk reads through. An empty buffer would
+// read nothing, so it holds at least a byte.
+fn buffer_capacity(buffer_size: Option<usize>) -> usize {
+    buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE).max(1)
+}
 
-    let max_position_number = if let Some(pos) = max_position {
-        let pos = pos.into();
-        Some(match pos {
-            Position::Start => 0,
-            Position::Middle(n) => n,
-            Position::End => total_lines,
-        })
-    } else {
-        None
-    };
+// Bytes scanned for delimiters at a time when counting lines or finding
+// where one starts
+const SCAN_BUF_SIZE: usize = 64 * 1024;
 
-    if matches!(direction, Direction::Backward) && matches!(position, Position::Start) {
-        return Err(Error::InvalidDirection {
-            pos: "start".to_string(),
-            dir: "backwards".to_string()
-        })
-    } else if matches!(direction, Direction::Forward) && matches!(position, Position::End) {
-        return Err(Error::InvalidDirection {
-            pos: "end".to_string(),
-            dir: "forwards".to_string()
-        })
-    } else if max_position_number.is_some() {
-        if matches!(direction, Direction::Forward) && max_position_number.unwrap() < position_number
-        {
-            return Err(Error::MaxLinePosition { 
-                cmp: "less".to_string(),
-                dir: "forward".to_string()
-            });
-        } else if matches!(direction, Direction::Backward)
-            && max_position_number.unwrap() > position_number
-        {
-            return Err(Error::MaxLinePosition { 
-                cmp: "greater".to_string(),
-                dir: "backward".to_string()
-            });
-        }
+// Counts lines by scanning for delimiters a buffer at a time, without
+// splitting the lines out. A last line without a delimiter still counts.
+fn count_lines<R: Read>(input: R, delimiter: u8) -> Result<usize, Error> {
+    let mut reader = BufReader::with_capacity(SCAN_BUF_SIZE, input);
+    let mut count = 0;
+    let mut last = None;
+    loop {
+        let buf = reader.fill_buf()?;
+        let Some(&end) = buf.last() else {
+            break;
+        };
+        count += memchr::memchr_iter(delimiter, buf).count();
+        last = Some(end);
+        let consumed = buf.len();
+        reader.consume(consumed);
     }
+    Ok(count + last.is_some_and(|end| end != delimiter) as usize)
+}
 
-    let new_line_pos = if let Position::Middle(num) = position {
-        if matches!(direction, Direction::Backward) {
-            Position::Middle(num + 1)
-        } else {
-            Position::Middle(num)
-        }
-    } else {
-        position
-    };
+// Returns the byte offset the 1-based line line_no starts at in the file at
+// path, such as to checkpoint a walk by line number and resume it from a
+// Byte position. Like those, offsets count from after a byte order mark.
+// Lines past the end map to the end of the file.
+pub fn byte_offset_of_line<T: AsRef<Path>>(path: T, line_no: usize) -> Result<u64, Error> {
+    byte_offset_of_line_in(File::open(path)?, line_no)
+}
 
-    if let Err(e) = input
-        .seek(match position {
-            Position::Start => SeekFrom::Start(0),
-            Position::Middle(_) => {
-                let byte_offset = compute_offset(&path, new_line_pos);
-                SeekFrom::Start(byte_offset as u64)
-            }
-            Position::End => SeekFrom::End(0),
-        }) {
-        return Err(Error::File(e))
-    }
-        
-    let mut offset_buf: Box<dyn BufRead + Send> = match direction {
-        Direction::Forward => Box::new(BufReader::new(input)),
-        Direction::Backward => Box::new(RevBufReader::new(input)),
-    };
+// Like byte_offset_of_line, for any seekable source.
+pub fn byte_offset_of_line_in<R: Read + Seek>(mut input: R, line_no: usize) -> Result<u64, Error> {
+    let mark = bom::read(&mut input)?.map_or(0, |(_, mark)| mark);
+    let mut input = bom::Unmarked::new(input, mark)?;
+    let delimiter = read_line_ending(&mut input)?.delimiter();
+    Ok(compute_offset(&mut input, Position::Middle(line_no.max(1)), delimiter)? as u64)
+}
 
-    let mut curr_line = match position {
-        Position::Start => 1,
+// Finds the byte offset of a line by scanning for delimiters up to it.
+// Offsets count the full terminator, so CRLF files line up with what was
+// written on disk. Lines past the end map to the end of the file.
+fn compute_offset<R: Read + Seek>(
+    input: &mut R,
+    position: Position,
+    delimiter: u8,
+) -> Result<usize, Error> {
+    let line = match position {
         Position::Middle(line) => line,
-        Position::End => total_lines,
+        _ => return Ok(0),
     };
 
-    let mut lines = vec![];
-    while curr_line > 0 && curr_line <= total_lines {
-        if max_position_number.is_some() {
-            let max_position_number = max_position_number.unwrap();
-            if (curr_line > max_position_number && matches!(direction, Direction::Forward))
-                || (curr_line < max_position_number && matches!(direction, Direction::Backward))
-            {
+    input.seek(SeekFrom::Start(0))?;
+    let mut reader = BufReader::with_capacity(SCAN_BUF_SIZE, input);
+    let mut offset = 0;
+    let mut remaining = line.saturating_sub(1);
+    while remaining > 0 {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        let mut consumed = buf.len();
+        for idx in memchr::memchr_iter(delimiter, buf) {
+            remaining -= 1;
+            if remaining == 0 {
+                consumed = idx + 1;
                 break;
             }
         }
-
-        let mut line = String::new();
-        offset_buf.as_mut().read_line(&mut line).unwrap();
-        lines.push(line.replace("\n", ""));
-        if curr_line <= total_lines && matches!(direction, Direction::Forward) {
-            curr_line += 1;
-        } else if curr_line > 0 && matches!(direction, Direction::Backward) {
-            curr_line -= 1;
-        } else {
-            continue;
-        }
-    }
-
-    Ok(lines.into_iter())
-}
-
-fn compute_offset(input_file: &str, position: Position) -> usize {
-    match position {
-        Position::Middle(line) => {
-            let init_grep = Command::new("grep")
-                .args(["-b", "-n", "", input_file])
-                .stdout(Stdio::piped())
-                .spawn()
-                .expect("Failed to launch first grep command");
-            let final_grep = Command::new("grep")
-                .arg(format!("^{}:", line))
-                .stdin(
-                    init_grep
-                        .stdout
-                        .expect("Unable to get stdout from previous grep command."),
-                )
-                .output()
-                .expect("Failed to launch second grep command");
-            String::from_utf8_lossy(&final_grep.stdout)
-                .into_owned()
-                .split(":")
-                .nth(1)
-                .expect("Unable to access offset element of extraction result.")
-                .parse()
-                .expect("Unable to parse resulting position.")
-        }
-        _ => 0,
+        offset += consumed;
+        reader.consume(consumed);
     }
+    Ok(offset)
 }
 
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use super::*;
+    use once_cell::sync::Lazy;
 
     static RESULTS_1: Lazy<Vec<String>> = Lazy::new(|| {
-        vec!["hello", "there", "whats", "up"]
+        ["hello", "there", "whats", "up"]
             .iter()
             .map(|i| i.to_string())
             .collect()
     });
-    const RESULTS_2: Lazy<Vec<String>> = Lazy::new(|| {
-        vec!["am i clear now"]
+    static RESULTS_2: Lazy<Vec<String>> = Lazy::new(|| {
+        ["am i clear now"]
             .iter()
             .map(|i| i.to_string())
             .collect()
@@ -397,40 +2118,599 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_line_endings() {
+        assert_eq!(detect_line_ending("./testfiles/1.txt").unwrap(), LineEnding::Lf);
+        assert_eq!(detect_line_ending("./testfiles/4.txt").unwrap(), LineEnding::CrLf);
+        assert_eq!(detect_line_ending("./testfiles/5.txt").unwrap(), LineEnding::Cr);
+        let mut lines = open_lines("./testfiles/4.txt", None, None, None).unwrap();
+        assert_eq!(lines.line_ending(), LineEnding::CrLf);
+        assert_eq!(lines.next().unwrap().unwrap(), "hello");
+        let lines = open_lines("./testfiles/4.txt", "end", "backward", None).unwrap();
+        assert_eq!(lines.line_ending(), LineEnding::CrLf);
+        assert_eq!(open_lines("./testfiles/1.txt", None, None, None).unwrap().line_ending(), LineEnding::Lf);
+
+        let mut results: Vec<String> = RESULTS_1.clone();
+        for path in ["./testfiles/4.txt", "./testfiles/5.txt"] {
+            let forward: Vec<String> = open_file(path, None, None, None).unwrap().collect();
+            assert_eq!(forward, results);
+            let middle: Vec<String> = open_file(path, 3, None, None).unwrap().collect();
+            assert_eq!(middle, results[2..]);
+        }
+
+        results.reverse();
+        for path in ["./testfiles/4.txt", "./testfiles/5.txt"] {
+            let backward: Vec<String> = open_file(path, "end", "backward", None)
+                .unwrap()
+                .collect();
+            assert_eq!(backward, results);
+        }
+    }
+
+    #[test]
+    fn test_backward_from_middle() {
+        let lines: Vec<String> = open_file("./testfiles/6.txt", 4, "backward", None)
+            .unwrap()
+            .collect();
+        assert_eq!(lines, vec!["", "", "there", "hello"]);
+
+        let lines: Vec<String> = open_file("./testfiles/6.txt", 7, "backward", Some(Position::Middle(5)))
+            .unwrap()
+            .collect();
+        assert_eq!(lines, vec![" ", "", "whats"]);
+
+        let lines: Vec<String> = open_file("./testfiles/1.txt", 4, "backward", None)
+            .unwrap()
+            .collect();
+        assert_eq!(lines, vec!["up", "whats", "there", "hello"]);
+    }
+
+    #[test]
+    fn test_byte_position() {
+        let walk = |position: Position, direction: &str, max_position: Option<Position>| {
+            open_reader(
+                io::Cursor::new(b"hello\nthere\nwhats\nup\n".to_vec()),
+                position,
+                direction,
+                max_position,
+            )
+            .and_then(|lines| lines.collect::<Result<Vec<_>, _>>())
+        };
+
+        assert_eq!(walk(Position::Byte(6), "forward", None).unwrap(), RESULTS_1[1..].to_vec());
+        assert_eq!(walk(Position::Byte(8), "forward", None).unwrap(), vec!["whats", "up"]);
+        assert_eq!(walk(Position::Byte(8), "backward", None).unwrap(), vec!["hello"]);
+        assert_eq!(walk(Position::Byte(12), "backward", None).unwrap(), vec!["there", "hello"]);
+        assert!(walk(Position::Byte(100), "forward", None).unwrap().is_empty());
+        assert_eq!(
+            walk(Position::Byte(6), "forward", Some(Position::Byte(13))).unwrap(),
+            vec!["there", "whats"]
+        );
+        assert_eq!(
+            walk(Position::Byte(0), "forward", Some(Position::Middle(1))).unwrap(),
+            vec!["hello"]
+        );
+        assert_eq!(
+            walk(Position::End, "backward", Some(Position::Byte(7))).unwrap(),
+            vec!["up", "whats", "there"]
+        );
+
+        let error = walk(Position::Byte(13), "forward", Some(Position::Byte(7))).unwrap_err();
+        assert_eq!(
+            "Max position byte:7 is behind the position byte:13 when the direction is forward.",
+            error.to_string()
+        );
+        assert_eq!(Position::from("byte:12".to_string()), Position::Byte(12));
+        assert_eq!(Position::Byte(12).to_string(), "byte:12");
+
+        // Lines are numbered in the order they are read
+        let numbered: Vec<_> = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .position(Position::Byte(12))
+            .direction("backward")
+            .build()
+            .unwrap()
+            .hashes(HashAlgo::Xxh3)
+            .unwrap()
+            .map(|hash| hash.unwrap().0)
+            .collect();
+        assert_eq!(numbered, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_from_end() {
+        let walk = |position: &str, direction: &str| -> Vec<String> {
+            open_file("./testfiles/1.txt", position, direction, None)
+                .unwrap()
+                .collect()
+        };
+        assert_eq!(walk("end-2", "forward"), vec!["whats", "up"]);
+        assert_eq!(walk("end-2", "backward"), vec!["whats", "there", "hello"]);
+        assert_eq!(walk("end-10", "forward"), *RESULTS_1);
+        assert!(walk("end-0", "forward").is_empty());
+        assert_eq!(Position::FromEnd(2).to_string(), "end-2");
+
+        for contents in ["a\nb\nc\n", "a\nb\nc"] {
+            let mut input = io::Cursor::new(contents.as_bytes());
+            assert_eq!(offset_from_end(&mut input, 1, b'\n').unwrap(), 4);
+            assert_eq!(offset_from_end(&mut input, 3, b'\n').unwrap(), 0);
+            assert_eq!(offset_from_end(&mut input, 5, b'\n').unwrap(), 0);
+
+            let lines = open_reader(input, Position::Byte(0), None, Some(Position::FromEnd(2)))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(lines, vec!["a", "b"]);
+        }
+    }
+
+    #[test]
+    fn test_percent_position() {
+        let walk = |position: Position, direction: &str, max_position: Option<Position>| -> Vec<String> {
+            open_file("./testfiles/1.txt", position, direction, max_position)
+                .unwrap()
+                .collect()
+        };
+        assert_eq!(walk(Position::from("50%"), "forward", None), vec!["whats", "up"]);
+        assert_eq!(walk(Position::Percent(0.5), "backward", None), vec!["hello"]);
+        assert_eq!(walk(Position::Percent(0.0), "forward", None), *RESULTS_1);
+        assert_eq!(walk(Position::Percent(1.0), "backward", None).len(), 4);
+        assert_eq!(
+            walk(Position::Percent(0.3), "forward", Some(Position::Percent(0.6))),
+            vec!["there"]
+        );
+        assert_eq!(Position::Percent(0.25).to_string(), "25%");
+    }
+
+    #[test]
+    fn test_delimiter() {
+        let template = |direction: &str| {
+            OpenerTemplateBuilder::default()
+                .position(if direction == "backward" { "end" } else { "start" })
+                .direction(direction)
+                .delimiter(b'\0')
+                .build()
+                .unwrap()
+        };
+        let walk = |contents: &'static [u8], direction: &str| {
+            template(direction)
+                .open_reader(io::Cursor::new(contents))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        assert_eq!(walk(b"./a\n b\0./c\0", "forward"), vec!["./a\n b", "./c"]);
+        assert_eq!(walk(b"./a\n b\0./c\0", "backward"), vec!["./c", "./a\n b"]);
+        assert_eq!(walk(b"x\0\0y", "backward"), vec!["y", "", "x"]);
+
+        let opener = template("forward").opener("./testfiles/1.txt");
+        assert_eq!(opener.line_ending().unwrap(), LineEnding::Delimiter(0));
+        assert_eq!(opener.open().unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_newlines() {
+        let walk = |newlines: Newlines, direction: &str| {
+            OpenerTemplateBuilder::default()
+                .position(if direction == "backward" { "end" } else { "start" })
+                .direction(direction)
+                .newlines(newlines)
+                .build()
+                .unwrap()
+                .open_reader(io::Cursor::new(b"a\nb\nc\r\nd\n"))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        assert_eq!(walk(Newlines::Detected, "forward"), vec!["a", "b", "c\r", "d"]);
+        assert_eq!(walk(Newlines::Any, "forward"), vec!["a", "b", "c", "d"]);
+        assert_eq!(walk(Newlines::Any, "backward"), vec!["d", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_keep_terminator() {
+        let contents = b"a\r\n\r\nb\r\nc";
+        let walk = |direction: &str| {
+            OpenerTemplateBuilder::default()
+                .position(if direction == "backward" { "end" } else { "start" })
+                .direction(direction)
+                .keep_terminator(true)
+                .build()
+                .unwrap()
+                .open_reader(io::Cursor::new(contents))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        assert_eq!(walk("forward"), vec!["a\r\n", "\r\n", "b\r\n", "c"]);
+        assert_eq!(walk("forward").concat().as_bytes(), contents);
+        assert_eq!(walk("backward"), vec!["c", "b\r\n", "\r\n", "a\r\n"]);
+    }
+
+    #[test]
+    fn test_blank_lines() {
+        let open = |blank_lines: BlankLines, direction: &str| -> Vec<String> {
+            OpenerBuilder::default()
+                .path("./testfiles/6.txt")
+                .position(if direction == "backward" { "end" } else { "start" })
+                .direction(direction)
+                .blank_lines(blank_lines)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+
+        assert_eq!(open(BlankLines::Keep, "forward").len(), 8);
+        assert_eq!(
+            open(BlankLines::Collapse, "forward"),
+            vec!["hello", "there", "", "whats", "", "up"]
+        );
+        assert_eq!(
+            open(BlankLines::Collapse, "backward"),
+            vec!["up", " ", "whats", "", "there", "hello"]
+        );
+        assert_eq!(
+            open(BlankLines::Drop, "backward"),
+            vec!["up", "whats", "there", "hello"]
+        );
+    }
+
+    #[test]
+    fn test_count_lines() {
+        let count = |contents: &[u8]| count_lines(contents, b'\n').unwrap();
+        assert_eq!(count(b""), 0);
+        assert_eq!(count(b"\n"), 1);
+        assert_eq!(count(b"a\nb"), 2);
+        assert_eq!(count(b"a\nb\n"), 2);
+        assert_eq!(count(b"a\n\n"), 2);
+        // Lines spanning buffers
+        let long = "x\n".repeat(SCAN_BUF_SIZE) + "tail";
+        assert_eq!(count(long.as_bytes()), SCAN_BUF_SIZE + 1);
+        assert_eq!(count_lines(&b"a\rb\r"[..], b'\r').unwrap(), 2);
+    }
+
+    #[test]
+    fn test_offsets_match_across_platforms() {
+        for path in ["./testfiles/1.txt", "./testfiles/4.txt", "./testfiles/6.txt"] {
+            let contents = std::fs::read(path).unwrap();
+            let mut input = File::open(path).unwrap();
+            let mut expected = 0;
+            for (idx, line) in contents.split_inclusive(|b| *b == b'\n').enumerate() {
+                let offset = compute_offset(&mut input, Position::Middle(idx + 1), b'\n').unwrap();
+                assert_eq!(offset, expected);
+                expected += line.len();
+            }
+            let past_end = compute_offset(&mut input, Position::Middle(100), b'\n').unwrap();
+            assert_eq!(past_end, contents.len());
+        }
+        let mut input = File::open("./testfiles/4.txt").unwrap();
+        assert_eq!(compute_offset(&mut input, Position::Middle(3), b'\n').unwrap(), 14);
+        let mut input = File::open("./testfiles/5.txt").unwrap();
+        assert_eq!(compute_offset(&mut input, Position::Middle(2), b'\r').unwrap(), 6);
+    }
+
+    #[test]
+    fn test_open_lines() {
+        let lines = open_lines("./testfiles/1.txt", None, None, None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, *RESULTS_1);
+
+        let fixture = fixtures::Fixture::lines(["ok"]).create().unwrap();
+        std::fs::write(fixture.path(), b"ok\n\xff\n").unwrap();
+        let mut lines = open_lines(fixture.path(), None, None, None).unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "ok");
+        assert!(matches!(lines.next(), Some(Err(Error::InvalidUtf8 { line: 2 }))));
+        assert!(open_file(fixture.path(), None, None, None).is_err());
+    }
+
+    #[test]
+    fn test_open_numbered() {
+        let path = "./testfiles/1.txt";
+        let lines: Vec<(usize, String)> = open_numbered(path, 3, None, None)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec![(3, "whats".to_string()), (4, "up".to_string())]);
+
+        let lines: Vec<usize> = open_numbered(path, "end-3", "backward", None)
+            .unwrap()
+            .map(|line| line.unwrap().0)
+            .collect();
+        assert_eq!(lines, vec![2, 1]);
+
+        // Backward walks from the end count lines up front only when they're
+        // to be numbered in the file
+        let lines: Vec<usize> = open_numbered(path, "end", "backward", None)
+            .unwrap()
+            .map(|line| line.unwrap().0)
+            .collect();
+        assert_eq!(lines, vec![4, 3, 2, 1]);
+        let lines: Vec<usize> = open_lines(path, "end", "backward", None)
+            .unwrap()
+            .numbered()
+            .map(|line| line.unwrap().0)
+            .collect();
+        assert_eq!(lines, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_byte_offset_of_line() {
+        // "hello\nthere\nwhats\nup"
+        let path = "./testfiles/1.txt";
+        let offsets: Vec<u64> = [0, 1, 2, 4, 5, 100]
+            .into_iter()
+            .map(|line| byte_offset_of_line(path, line).unwrap())
+            .collect();
+        assert_eq!(offsets, vec![0, 0, 6, 18, 20, 20]);
+
+        let input = std::io::Cursor::new(b"\xef\xbb\xbfa\r\nb\r\n".to_vec());
+        assert_eq!(byte_offset_of_line_in(input, 2).unwrap(), 3);
+
+        // Resuming from the offset picks up at the line
+        let offset = byte_offset_of_line(path, 3).unwrap();
+        let lines = open_lines(path, Position::Byte(offset), None, None).unwrap();
+        assert_eq!(lines.map(Result::unwrap).collect::<Vec<_>>(), vec!["whats", "up"]);
+    }
+
+    #[test]
+    fn test_head_tail() {
+        let path = "./testfiles/1.txt";
+        assert_eq!(head(path, 2).unwrap(), vec!["hello", "there"]);
+        assert_eq!(tail(path, 3).unwrap(), vec!["there", "whats", "up"]);
+        assert_eq!(head(path, 10).unwrap().len(), 4);
+        assert_eq!(tail(path, 10).unwrap(), head(path, 10).unwrap());
+        assert!(tail(path, 0).unwrap().is_empty());
+
+        let fixture = fixtures::Fixture::lines(["a", "b", ""]).create().unwrap();
+        assert_eq!(tail(fixture.path(), 2).unwrap(), vec!["b", ""]);
+        let empty = fixtures::Fixture::lines(Vec::<String>::new()).create().unwrap();
+        assert!(head(empty.path(), 1).unwrap().is_empty());
+        assert!(tail(empty.path(), 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_open_range() {
+        let path = "./testfiles/1.txt";
+        let range = |range: (Bound<usize>, Bound<usize>)| -> Vec<String> {
+            open_range(path, range).unwrap().map(Result::unwrap).collect()
+        };
+        assert_eq!(range((Bound::Included(2), Bound::Included(3))), vec!["there", "whats"]);
+        assert_eq!(range((Bound::Excluded(2), Bound::Unbounded)), vec!["whats", "up"]);
+        assert_eq!(range((Bound::Unbounded, Bound::Excluded(2))), vec!["hello"]);
+        assert_eq!(range((Bound::Included(10), Bound::Included(200))), Vec::<String>::new());
+        assert!(matches!(open_range(path, 3..3), Err(Error::MaxLinePosition { .. })));
+
+        let lines: Vec<String> = OpenerBuilder::default()
+            .path(path)
+            .position("end")
+            .direction("backward")
+            .range(2..)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["there", "whats", "up"]);
+    }
+
+    #[test]
+    fn test_buffer_size() {
+        let fixture = fixtures::Fixture::numbered(50).create().unwrap();
+        for buffer_size in [0usize, 1, 3, 1 << 20] {
+            let walk = |position: Position, direction: &str| -> Vec<String> {
+                fixture
+                    .builder()
+                    .position(position)
+                    .direction(direction)
+                    .buffer_size(buffer_size)
+                    .build()
+                    .unwrap()
+                    .open()
+                    .unwrap()
+                    .collect::<Result<_, _>>()
+                    .unwrap()
+            };
+            let forward = walk(Position::Start, "forward");
+            assert_eq!(forward.len(), 50);
+            assert_eq!(forward[49], "line 50");
+            let backward = walk(Position::End, "backward");
+            assert_eq!(backward, forward.into_iter().rev().collect::<Vec<_>>());
+            assert_eq!(walk(Position::Byte(60), "forward")[0], "line 10");
+        }
+    }
+
+    #[test]
+    fn test_open_reader() {
+        let contents = std::fs::read("./testfiles/4.txt").unwrap();
+        let lines = open_reader(io::Cursor::new(contents.clone()), Position::Middle(2), None, None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, RESULTS_1[1..].to_vec());
+
+        let template = OpenerTemplateBuilder::default()
+            .position("end")
+            .direction("backward")
+            .blank_lines(BlankLines::Drop)
+            .build()
+            .unwrap();
+        let lines = template
+            .open_reader(io::Cursor::new(b"a\n\nb\n".to_vec()))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn test_glob() {
+        let opener = |pattern: &str, direction: &str| {
+            OpenerBuilder::default()
+                .path(pattern)
+                .position(if direction == "forward" { "start" } else { "end" })
+                .direction(direction)
+                .build()
+                .unwrap()
+        };
+
+        let lines = opener("./testfiles/[12].txt", "forward")
+            .open()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, [RESULTS_1.clone(), RESULTS_2.clone()].concat());
+
+        let lines = opener("./testfiles/[12].txt", "backward")
+            .open()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let mut expected = [RESULTS_1.clone(), RESULTS_2.clone()].concat();
+        expected.reverse();
+        assert_eq!(lines, expected);
+
+        assert!(opener("./testfiles/*.missing", "forward").open().is_err());
+        assert!(matches!(
+            opener("./testfiles/[", "forward").open(),
+            Err(Error::Glob(_))
+        ));
+    }
+
+    #[test]
+    fn test_paths() {
+        let opener = |direction: &str| {
+            OpenerBuilder::default()
+                .paths(vec!["./testfiles/2.txt", "./testfiles/1.txt"])
+                .position(if direction == "forward" { "start" } else { "end" })
+                .direction(direction)
+                .build()
+                .unwrap()
+        };
+
+        let lines = opener("forward")
+            .open()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let mut expected = [RESULTS_2.clone(), RESULTS_1.clone()].concat();
+        assert_eq!(lines, expected);
+
+        let lines = opener("backward")
+            .open()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        expected.reverse();
+        assert_eq!(lines, expected);
+
+        assert!(OpenerBuilder::default().build().is_err());
+        assert!(OpenerBuilder::default()
+            .paths(Vec::<PathBuf>::new())
+            .build()
+            .is_err());
+    }
+
     #[test]
     fn test_builder() {
         let opener = OpenerBuilder::default()
-            .path("./testfiles/3.txt".to_string())
+            .path("./testfiles/3.txt")
+            .build()
+            .unwrap()
+            .open()
+            .unwrap();
+
+        assert_eq!(opener.count(), 0);
+
+        let path = std::ffi::OsStr::new("./testfiles/1.txt");
+        let opener = OpenerBuilder::default().path(path).build().unwrap();
+        assert_eq!(opener.open().unwrap().count(), 4);
+        assert_eq!(open_file(Path::new(path), None, None, None).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn test_walk_config() {
+        let opener = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .direction("backward")
+            .max_position(2usize)
+            .build()
+            .unwrap();
+        let config = opener.config();
+        assert_eq!(config.position, Position::Start);
+        assert_eq!(config.direction, Direction::Backward);
+        assert_eq!(config.max_position, Some(Position::Middle(2)));
+        assert!(!config.follow);
+
+        let mut config = config.clone();
+        config.position = Position::End;
+        let lines = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .config(config)
             .build()
             .unwrap()
             .open()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["up", "whats", "there"]);
+    }
+
+    #[test]
+    fn test_template() {
+        let template = OpenerTemplateBuilder::default()
+            .direction("backward")
+            .position("end")
+            .build()
             .unwrap();
 
-        assert_eq!(opener.len(), 0)
+        let mut results: Vec<String> = RESULTS_1.clone();
+        results.reverse();
+        assert_eq!(template.open("./testfiles/1.txt").unwrap().collect::<Result<Vec<_>, _>>().unwrap(), results);
+
+        let opened: Vec<Vec<String>> = template
+            .open_all(["./testfiles/1.txt", "./testfiles/2.txt"])
+            .map(|lines| lines.unwrap().collect::<Result<_, _>>().unwrap())
+            .collect();
+        assert_eq!(opened, vec![results, RESULTS_2.clone()]);
+        assert!(template.open_all(["./testfiles/missing.txt"]).all(|lines| lines.is_err()));
     }
 
     #[test]
     fn test_error_cases() {
         let opener = OpenerBuilder::default()
-            .path("./testfiles/3.txt".to_string())
+            .path("./testfiles/3.txt")
             .position("start")
             .direction("backward")
             .build()
             .unwrap()
             .open()
             .unwrap_err();
-        assert_eq!("Cannot go \"backwards\" from the \"start\" position.", opener.to_string()); 
+        assert_eq!("Cannot go backward from the start position.", opener.to_string());
+        assert!(matches!(
+            opener,
+            Error::InvalidDirection {
+                pos: Position::Start,
+                dir: Direction::Backward
+            }
+        ));
         let opener = OpenerBuilder::default()
-            .path("./testfiles/3.txt".to_string())
+            .path("./testfiles/3.txt")
             .position("end")
             .direction("forward")
             .build()
             .unwrap()
             .open()
             .unwrap_err();
-        assert_eq!("Cannot go \"forwards\" from the \"end\" position.", opener.to_string());
+        assert_eq!("Cannot go forward from the end position.", opener.to_string());
         let opener = OpenerBuilder::default()
-            .path("./testfiles/3.txt".to_string())
+            .path("./testfiles/3.txt")
             .position("3")
             .direction("forward")
             .max_position("2")
@@ -438,9 +2718,17 @@ mod tests {
             .unwrap()
             .open()
             .unwrap_err();
-        assert_eq!("Cannot have a max line position \"less\" than the current line position when the direction is \"forward\".", opener.to_string()); 
+        assert_eq!("Max line position 2 is behind the current line position 3 when the direction is forward.", opener.to_string());
+        assert!(matches!(
+            opener,
+            Error::MaxLinePosition {
+                pos: 3,
+                max: 2,
+                dir: Direction::Forward
+            }
+        ));
         let opener = OpenerBuilder::default()
-            .path("./testfiles/3.txt".to_string())
+            .path("./testfiles/3.txt")
             .position("2")
             .direction("backward")
             .max_position("3")
@@ -448,6 +2736,6 @@ mod tests {
             .unwrap()
             .open()
             .unwrap_err();
-        assert_eq!("Cannot have a max line position \"greater\" than the current line position when the direction is \"backward\".", opener.to_string()); 
+        assert_eq!("Max line position 3 is behind the current line position 2 when the direction is backward.", opener.to_string());
     }
 }
@@ -0,0 +1,1357 @@
+use crate::{
+    advise, bom, reader::LinesReader, throttle::Throttled, walk_file, watch::POLL_INTERVAL,
+    AccessHint, Backend, BlankLines, Direction, Error, LineEnding, LineMap, LinePredicate,
+    Position, Removal, Utf8Policy, WalkOptions, WalkStats,
+};
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+// LineIter reads the lines of a walk on demand, so only the line being
+// yielded is held in memory regardless of the file size. Once an error is
+// returned the iterator is finished.
+pub struct LineIter {
+    pub(crate) reader: Box<dyn BufRead + Send>,
+    pub(crate) line_ending: LineEnding,
+    pub(crate) direction: Direction,
+    // Number of the next line to read, and the last line to read in the
+    // direction of the walk
+    pub(crate) curr_line: usize,
+    pub(crate) last_line: usize,
+    // Yields lines with their terminator, exactly as stored
+    pub(crate) keep_terminator: bool,
+    pub(crate) invalid_utf8: Utf8Policy,
+    pub(crate) blank_lines: BlankLines,
+    pub(crate) previous_blank: bool,
+    pub(crate) filter: Option<LinePredicate>,
+    pub(crate) map: Option<LineMap>,
+    // Yields every step-th line, skipping the ones between unread
+    pub(crate) step: usize,
+    pub(crate) skip: usize,
+    // Lines left to yield before stopping, across every file of the walk
+    pub(crate) remaining: Option<usize>,
+    // Offset in the source where the next line starts walking forward, or
+    // where it ends walking backward
+    pub(crate) offset: u64,
+    // The byte range the walk is confined to, when positioned by bytes
+    pub(crate) window: Option<(u64, u64)>,
+    pub(crate) stats: Option<WalkStats>,
+    // A second handle to the file, so the access hint can be released
+    pub(crate) hinted: Option<File>,
+    pub(crate) access_hint: AccessHint,
+    pub(crate) follow: Option<Follow>,
+    pub(crate) backend: Backend,
+    pub(crate) chain: Option<Box<Chain>>,
+    // Reads the walk from its other end, for next_back
+    pub(crate) back: Option<Box<Back>>,
+    // Lines read ahead by peek, yielded before any others
+    pub(crate) peeked: VecDeque<Result<LineRecord, Error>>,
+    // Whether last_line comes from counting the lines of the source, so the
+    // walk is known to reach it
+    pub(crate) counted: bool,
+    pub(crate) done: bool,
+}
+
+// Chain holds the files a walk over several files moves on to, in order,
+// once the current one is done
+pub(crate) struct Chain {
+    pub(crate) paths: VecDeque<PathBuf>,
+    pub(crate) position: Position,
+    pub(crate) direction: Direction,
+    pub(crate) max_position: Option<Position>,
+    pub(crate) options: WalkOptions,
+}
+
+// Back is the walk next_back reads, which goes the other way over the lines
+// the walk has yet to read. It is opened on the first call.
+pub(crate) enum Back {
+    Unopened { path: PathBuf, options: WalkOptions },
+    Open(LineIter),
+}
+
+// Follow is the state of a walk that waits for appended lines at the end of
+// the file: how far it has read, and the line being written so far.
+pub(crate) struct Follow {
+    offset: u64,
+    partial: Vec<u8>,
+    // The capacity of the reader the file is reopened with, and whether
+    // its reads are throttled
+    buffer_size: usize,
+    nice_io: bool,
+    // Set through a PauseHandle, and whether the walk has dropped its
+    // reader for it
+    paused: Arc<AtomicBool>,
+    released: bool,
+    // Where the file was opened from, watched for it being removed, and
+    // what was done when it was
+    path: Option<PathBuf>,
+    on_removal: Removal,
+    removal: Option<Removal>,
+}
+
+impl Follow {
+    pub(crate) fn new(offset: u64, buffer_size: usize, nice_io: bool) -> Self {
+        Follow {
+            offset,
+            partial: vec![],
+            buffer_size,
+            nice_io,
+            paused: Arc::new(AtomicBool::new(false)),
+            released: false,
+            path: None,
+            on_removal: Removal::default(),
+            removal: None,
+        }
+    }
+
+    // Watches path for the followed file being removed, handling it as
+    // on_removal says.
+    pub(crate) fn watch(&mut self, path: &Path, on_removal: Removal) {
+        self.path = Some(path.to_path_buf());
+        self.on_removal = on_removal;
+    }
+
+    // Whether the file at path is no longer the one open, because it was
+    // deleted or renamed away.
+    fn removed(&self, file: &File) -> Result<bool, Error> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+        let current = match fs::metadata(path) {
+            Ok(current) => current,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+            Err(e) => return Err(e.into()),
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let open = file.metadata()?;
+            Ok((current.dev(), current.ino()) != (open.dev(), open.ino()))
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (current, file);
+            Ok(false)
+        }
+    }
+
+    // Opens the file that took the removed one's place, if there is one yet.
+    fn open_successor(&self) -> Result<Option<File>, Error> {
+        match self.path.as_ref().map(File::open) {
+            Some(Ok(file)) => Ok(Some(file)),
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(None),
+        }
+    }
+
+    // Reopens the followed file at offset, counting from after a byte order
+    // mark like the walk does.
+    fn reopen(&self, file: &File, offset: u64) -> Result<Box<dyn BufRead + Send>, Error> {
+        let mut file = file.try_clone()?;
+        let mark = bom::read(&mut file)?.map_or(0, |(_, mark)| mark);
+        let mut input = bom::Unmarked::new(file, mark)?;
+        input.seek(SeekFrom::Start(offset))?;
+        let input = Throttled::new(input, self.nice_io);
+        Ok(Box::new(BufReader::with_capacity(self.buffer_size, input)))
+    }
+}
+
+// PauseHandle pauses and resumes a follow walk from any thread, as returned
+// by LineIter::pause_handle. While paused the walk drops its reader and
+// buffers, and next waits for resume like it waits for lines to be
+// appended, rather than ending the walk.
+#[derive(Debug, Clone)]
+pub struct PauseHandle {
+    paused: Arc<AtomicBool>,
+}
+
+impl PauseHandle {
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for LineIter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineIter")
+            .field("direction", &self.direction)
+            .field("curr_line", &self.curr_line)
+            .field("last_line", &self.last_line)
+            .finish()
+    }
+}
+
+impl LineIter {
+    fn finished(&self) -> bool {
+        self.done
+            || self.curr_line == 0
+            || match self.direction {
+                Direction::Backward if self.counted => self.curr_line < self.last_line,
+                _ => self.curr_line > self.last_line,
+            }
+    }
+
+    // Reads the next line into record, through line as scratch space.
+    // Returns false at the end of a source whose line count wasn't known up
+    // front.
+    fn read_line(&mut self, line: &mut Vec<u8>, record: &mut LineRecord) -> Result<bool, Error> {
+        line.clear();
+        let read = match self.follow.is_some() {
+            true => {
+                let (followed, read) = self.read_followed()?;
+                *line = followed;
+                read
+            }
+            false => self.reader.read_until(self.line_ending.delimiter(), line)?,
+        };
+        if read == 0 {
+            return Ok(false);
+        }
+        record.byte_offset = self.consumed(read);
+        record.byte_len = read as u64;
+        record.line_no = self.curr_line;
+        if !self.keep_terminator {
+            self.line_ending.strip(line);
+        }
+        match self.invalid_utf8.decode_into(line, &mut record.text) {
+            true => Ok(true),
+            false => Err(Error::InvalidUtf8 {
+                line: self.curr_line,
+            }),
+        }
+    }
+
+    // Passes over the next line without keeping or decoding it. Returns false
+    // at the end of a source whose line count wasn't known up front.
+    fn skip_line(&mut self) -> Result<bool, Error> {
+        let read = match self.follow.is_some() {
+            true => self.read_followed()?.1,
+            false => self.reader.skip_until(self.line_ending.delimiter())?,
+        };
+        self.consumed(read);
+        Ok(read > 0)
+    }
+
+    // Accounts for a line of read bytes having been consumed, returning the
+    // offset it starts at.
+    fn consumed(&mut self, read: usize) -> u64 {
+        if let Some(stats) = &self.stats {
+            stats.record_line(read);
+        }
+        let read = read as u64;
+        match self.direction {
+            Direction::Forward => {
+                self.offset += read;
+                self.offset - read
+            }
+            Direction::Backward => {
+                self.offset = self.offset.saturating_sub(read);
+                self.offset
+            }
+        }
+    }
+}
+
+impl LineIter {
+    // Reads the next complete line, polling until one has been appended. If
+    // the file shrinks below what was read it was truncated, and reading
+    // starts over from the top. If it is removed, the walk finishes, fails
+    // or moves on to its successor as the follow walk was set up to.
+    fn read_followed(&mut self) -> Result<(Vec<u8>, usize), Error> {
+        let delimiter = self.line_ending.delimiter();
+        let follow = self.follow.as_mut().unwrap();
+        loop {
+            // A paused walk holds no reader, and picks up on resume exactly
+            // where it stopped
+            if follow.paused.load(Ordering::Relaxed) {
+                if !follow.released {
+                    self.reader = Box::new(io::empty());
+                    follow.released = true;
+                }
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            if follow.released {
+                // Following is only enabled for walks over a file
+                self.reader = follow.reopen(self.hinted.as_ref().unwrap(), follow.offset)?;
+                follow.released = false;
+            }
+            let read = self.reader.read_until(delimiter, &mut follow.partial)?;
+            follow.offset += read as u64;
+            // A removed file is finished once the handle has no more to read,
+            // its last line yielded even if it was never ended
+            if follow.partial.last() == Some(&delimiter) || follow.removal == Some(Removal::Finish)
+            {
+                let line = std::mem::take(&mut follow.partial);
+                let len = line.len();
+                return Ok((line, len));
+            }
+
+            // Following is only enabled for walks over a file
+            let hinted = self.hinted.as_ref().unwrap();
+            let len = hinted.metadata()?.len();
+            if let Some(stats) = &self.stats {
+                stats.record_len(len);
+            }
+            if len < follow.offset {
+                self.reader = follow.reopen(hinted, 0)?;
+                self.offset = 0;
+                follow.offset = 0;
+                follow.partial.clear();
+                continue;
+            }
+            if follow.removed(hinted)? {
+                follow.removal = Some(follow.on_removal);
+                match follow.on_removal {
+                    Removal::Finish => continue,
+                    Removal::Fail => return Err(Error::Removed),
+                    // Until a file takes its place, the walk waits as it
+                    // would for more lines
+                    Removal::Successor => {
+                        if let Some(successor) = follow.open_successor()? {
+                            self.reader = follow.reopen(&successor, 0)?;
+                            self.hinted = Some(successor);
+                            self.offset = 0;
+                            follow.offset = 0;
+                            // What was written of a line the old file never
+                            // ended is dropped
+                            follow.partial.clear();
+                            continue;
+                        }
+                    }
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl LineIter {
+    // The backend the current file is read with, as picked when the walk
+    // asked for Backend::Auto or had to fall back to buffered reads.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    // The newline convention of the current file, as detected when the walk
+    // opened it, that lines are normalized from.
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    // A handle pausing and resuming a follow walk, or None if the walk
+    // doesn't follow.
+    pub fn pause_handle(&self) -> Option<PauseHandle> {
+        let follow = self.follow.as_ref()?;
+        Some(PauseHandle {
+            paused: follow.paused.clone(),
+        })
+    }
+
+    // What a follow walk did about its file being removed, if it was.
+    pub fn removal(&self) -> Option<Removal> {
+        self.follow.as_ref()?.removal
+    }
+
+    // Pairs every line with its line number in the file, in either
+    // direction. Walks from a Byte or Percent position, and backward walks
+    // from the end to the start, number their lines in the order they're
+    // read instead, as they don't count the lines of the file up front.
+    pub fn numbered(self) -> Numbered {
+        Numbered { lines: self }
+    }
+
+    // The walk as an ExactSizeIterator, if the number of lines it yields is
+    // known: its lines were counted up front, and none are dropped by blank
+    // line handling or a filter. Otherwise the walk is handed back as is.
+    pub fn exact(self) -> Result<ExactLines, Box<LineIter>> {
+        match self.size_hint() {
+            (lower, Some(upper)) if lower == upper => Ok(ExactLines { lines: self }),
+            _ => Err(Box::new(self)),
+        }
+    }
+
+    // Returns the next line without consuming it, so next yields it again.
+    pub fn peek(&mut self) -> Option<Result<&str, &Error>> {
+        self.peek_n(0)
+    }
+
+    // Returns the line n lines past the next one without consuming any, so
+    // peek_n(0) is the line peek returns. The lines up to it are read ahead
+    // and held until yielded.
+    pub fn peek_n(&mut self, n: usize) -> Option<Result<&str, &Error>> {
+        while self.peeked.len() <= n {
+            let mut record = LineRecord::default();
+            let read = self.read_record_into(&mut Vec::new(), &mut record)?;
+            self.peeked.push_back(read.map(|()| record));
+        }
+        Some(match &self.peeked[n] {
+            Ok(record) => Ok(&record.text),
+            Err(e) => Err(e),
+        })
+    }
+
+    // Reads the lines of the walk as bytes, each one followed by a '\n'.
+    pub fn into_reader(self) -> impl Read + Send {
+        LinesReader::new(self)
+    }
+
+    // Yields every line as a LineRecord, with where it is in the file.
+    pub fn line_records(self) -> LineRecords {
+        LineRecords { lines: self }
+    }
+
+    // Like next, along with the line number of the line in the file.
+    pub(crate) fn next_numbered(&mut self) -> Option<Result<(usize, String), Error>> {
+        Some(
+            self.next_record()?
+                .map(|record| (record.line_no, record.text)),
+        )
+    }
+
+    // Reads every line of the walk into the same buffer, handing each one to
+    // f in turn, rather than allocating a String per line as next does. The
+    // walk is left finished, or where it failed.
+    pub fn read_lines_with<F: FnMut(&str)>(&mut self, mut f: F) -> Result<(), Error> {
+        let mut line = Vec::new();
+        let mut record = LineRecord::default();
+        while let Some(read) = self.next_record_into(&mut line, &mut record) {
+            read?;
+            f(&record.text);
+        }
+        Ok(())
+    }
+
+    fn next_record(&mut self) -> Option<Result<LineRecord, Error>> {
+        let mut record = LineRecord::default();
+        let read = self.next_record_into(&mut Vec::new(), &mut record)?;
+        Some(read.map(|()| record))
+    }
+
+    // Moves the next line of the walk into record, a peeked one if any.
+    fn next_record_into(
+        &mut self,
+        line: &mut Vec<u8>,
+        record: &mut LineRecord,
+    ) -> Option<Result<(), Error>> {
+        match self.peeked.pop_front() {
+            Some(peeked) => Some(peeked.map(|peeked| *record = peeked)),
+            None => self.read_record_into(line, record),
+        }
+    }
+
+    // Reads the next line of the walk into record, moving on to the next
+    // file when the current one is done.
+    fn read_record_into(
+        &mut self,
+        line: &mut Vec<u8>,
+        record: &mut LineRecord,
+    ) -> Option<Result<(), Error>> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        loop {
+            if let Some(read) = self.next_in_file(line, record) {
+                if let Some(remaining) = self.remaining.as_mut() {
+                    *remaining -= 1;
+                }
+                return Some(read);
+            }
+            if let Err(e) = self.next_file()? {
+                return Some(Err(e));
+            }
+        }
+    }
+
+    // Opens the next file of the chain in place of the current one. Returns
+    // None once there are none left.
+    fn next_file(&mut self) -> Option<Result<(), Error>> {
+        let chain = self.chain.as_mut()?;
+        let path = chain.paths.pop_front()?;
+        let next = walk_file(
+            path,
+            chain.position,
+            chain.direction,
+            chain.max_position,
+            chain.options.clone(),
+        );
+        Some(next.map(|mut next| {
+            next.chain = self.chain.take();
+            next.remaining = self.remaining;
+            next.peeked = std::mem::take(&mut self.peeked);
+            *self = next;
+        }))
+    }
+
+    fn next_in_file(
+        &mut self,
+        line: &mut Vec<u8>,
+        record: &mut LineRecord,
+    ) -> Option<Result<(), Error>> {
+        while !self.finished() {
+            if self.skip > 0 {
+                self.skip -= 1;
+                let skipped = self.skip_line();
+                self.advance();
+                match skipped {
+                    Ok(true) => continue,
+                    Ok(false) => self.done = true,
+                    Err(e) => {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                }
+                return None;
+            }
+
+            let read = self.read_line(line, record);
+            self.advance();
+            match read {
+                Ok(true) if self.read_from_back(record) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(true) => {}
+                Ok(false) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+            let blank = record.text.trim().is_empty();
+            let skip = match self.blank_lines {
+                BlankLines::Keep => false,
+                BlankLines::Collapse => blank && self.previous_blank,
+                BlankLines::Drop => blank,
+            };
+            self.previous_blank = blank;
+            let skip = skip
+                || self
+                    .filter
+                    .as_ref()
+                    .is_some_and(|filter| !filter.matches(&record.text));
+            if !skip {
+                self.skip = self.step - 1;
+                if let Some(map) = &self.map {
+                    record.text = map.apply(std::mem::take(&mut record.text));
+                }
+                return Some(Ok(()));
+            }
+        }
+
+        None
+    }
+
+    // Whether record was already read from the other end of the walk, so the
+    // two ends have met.
+    fn read_from_both_ends(&self) -> bool {
+        matches!(self.back.as_deref(), Some(Back::Open(_)))
+    }
+
+    fn read_from_back(&self, record: &LineRecord) -> bool {
+        match (self.back.as_deref(), self.direction) {
+            (Some(Back::Open(back)), Direction::Forward) => record.byte_offset >= back.offset,
+            (Some(Back::Open(back)), Direction::Backward) => record.byte_offset < back.offset,
+            _ => false,
+        }
+    }
+
+    // Reads the last line of the walk not yet yielded. Once the back meets
+    // the front, that's the last line the front peeked.
+    fn next_back_record(&mut self) -> Option<Result<LineRecord, Error>> {
+        self.read_back_record().or_else(|| self.peeked.pop_back())
+    }
+
+    fn read_back_record(&mut self) -> Option<Result<LineRecord, Error>> {
+        if self.remaining == Some(0) || self.finished() {
+            return None;
+        }
+        let mut back = match self.back.take() {
+            Some(back) if self.chain.is_none() => back,
+            _ => {
+                self.done = true;
+                return Some(Err(Error::OneEnded));
+            }
+        };
+        let read = self.read_back(&mut back);
+        self.back = Some(back);
+
+        let record = match read? {
+            Ok(record) => record,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+        // The two ends have met once the back reads a line the front has
+        let read_from_front = match self.direction {
+            Direction::Forward => record.byte_offset < self.offset,
+            Direction::Backward => record.byte_offset + record.byte_len > self.offset,
+        };
+        if read_from_front {
+            self.done = true;
+            return None;
+        }
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining -= 1;
+        }
+        Some(Ok(record))
+    }
+
+    // Reads the next line from the back, opening it over the lines left.
+    fn read_back(&self, back: &mut Back) -> Option<Result<LineRecord, Error>> {
+        match back {
+            Back::Open(lines) => lines.next_record(),
+            Back::Unopened { path, options } => match self.open_back(path, options.clone()) {
+                Ok(lines) => {
+                    *back = Back::Open(lines);
+                    self.read_back(back)
+                }
+                Err(e) => Some(Err(e)),
+            },
+        }
+    }
+
+    // Opens the walk from the far end of this one back to where it has read
+    // up to. Counted walks are bounded by line, the rest by byte.
+    fn open_back(&self, path: &Path, options: WalkOptions) -> Result<LineIter, Error> {
+        let far_end = match (self.direction, self.window) {
+            _ if self.counted || self.last_line != usize::MAX => Position::Middle(self.last_line),
+            (Direction::Forward, Some((_, upper))) => Position::Byte(upper),
+            (Direction::Forward, None) => Position::End,
+            (Direction::Backward, window) => Position::Byte(window.map_or(0, |(lower, _)| lower)),
+        };
+        let read_up_to = match self.counted {
+            true => Position::Middle(self.curr_line),
+            false => Position::Byte(self.offset),
+        };
+        let direction = match self.direction {
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        };
+        walk_file(path, far_end, direction, Some(read_up_to), options)
+    }
+
+    fn advance(&mut self) {
+        // Lines of a walk that wasn't counted are numbered as they're read
+        match self.direction {
+            Direction::Backward if self.counted => self.curr_line -= 1,
+            _ => self.curr_line += 1,
+        }
+    }
+}
+
+// Lines are read from the back by a second walk over the same file going
+// the other way, which ends where the front has read up to. Settings such
+// as step and blank line handling apply to each end as it reads, while the
+// limit counts the lines from both.
+impl DoubleEndedIterator for LineIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(self.next_back_record()?.map(|record| record.text))
+    }
+}
+
+impl Iterator for LineIter {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_numbered()?.map(|(_, line)| line))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.size_hint_unlimited();
+        let (lower, upper) = match self.remaining {
+            Some(remaining) => (
+                lower.min(remaining),
+                Some(upper.map_or(remaining, |upper| upper.min(remaining))),
+            ),
+            None => (lower, upper),
+        };
+        // Peeked lines were taken off the limit as they were read
+        let peeked = self.peeked.len();
+        (
+            lower.saturating_add(peeked),
+            upper.and_then(|upper| upper.checked_add(peeked)),
+        )
+    }
+}
+
+impl LineIter {
+    // The size hint of the walk ignoring its limit.
+    fn size_hint_unlimited(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.size_hint_in_file();
+        // The files still to walk could hold any number of lines
+        match &self.chain {
+            Some(chain) if !chain.paths.is_empty() => (lower, None),
+            _ => (lower, upper),
+        }
+    }
+
+    fn size_hint_in_file(&self) -> (usize, Option<usize>) {
+        if self.finished() {
+            return (0, Some(0));
+        }
+        if (self.follow.is_some() || !self.counted) && self.last_line == usize::MAX {
+            return (0, None);
+        }
+        let remaining = (self.curr_line.abs_diff(self.last_line) + 1)
+            .saturating_sub(self.skip)
+            .div_ceil(self.step);
+        match self.blank_lines {
+            BlankLines::Keep
+                if self.counted && self.filter.is_none() && !self.read_from_both_ends() =>
+            {
+                (remaining, Some(remaining))
+            }
+            _ => (0, Some(remaining)),
+        }
+    }
+}
+
+// LineRecord is a line along with where it is in the file, as yielded by
+// LineIter::line_records
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LineRecord {
+    // Numbered like LineIter::numbered numbers lines
+    pub line_no: usize,
+    // The span of the line as stored, terminator included, so the next line
+    // starts at byte_offset + byte_len. Offsets count from after a byte
+    // order mark, and into the decompressed or transcoded contents of files
+    // walked through those.
+    pub byte_offset: u64,
+    pub byte_len: u64,
+    pub text: String,
+}
+
+// LineRecords is the iterator returned by LineIter::line_records
+#[derive(Debug)]
+pub struct LineRecords {
+    lines: LineIter,
+}
+
+impl Iterator for LineRecords {
+    type Item = Result<LineRecord, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.next_record()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.lines.size_hint()
+    }
+}
+
+// ExactLines is a walk whose number of lines is known up front, as returned
+// by LineIter::exact and Opener::open_exact. Its length stays exact as long
+// as no error cuts the walk short.
+#[derive(Debug)]
+pub struct ExactLines {
+    lines: LineIter,
+}
+
+impl Iterator for ExactLines {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.lines.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ExactLines {}
+
+// Numbered is the iterator returned by LineIter::numbered
+#[derive(Debug)]
+pub struct Numbered {
+    lines: LineIter,
+}
+
+impl Iterator for Numbered {
+    type Item = Result<(usize, String), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.next_numbered()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.lines.size_hint()
+    }
+}
+
+impl Drop for LineIter {
+    fn drop(&mut self) {
+        if let Some(hinted) = &self.hinted {
+            advise::release(hinted, self.access_hint);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fixtures::Fixture, Error, LineIter, LineRecord, OpenerBuilder, Position, Removal,
+        Utf8Policy,
+    };
+    use std::{
+        fs::OpenOptions,
+        io::Write,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn test_lazy_lines() {
+        let opener = |max_position: Option<Position>| {
+            let mut builder = OpenerBuilder::default();
+            builder.path("./testfiles/1.txt");
+            if let Some(max_position) = max_position {
+                builder.max_position(max_position);
+            }
+            builder.build().unwrap()
+        };
+        // Forward walks only count lines when bounded relative to the end
+        let lines = opener(None).open().unwrap();
+        assert_eq!(lines.size_hint(), (0, None));
+        assert_eq!(lines.count(), 4);
+        let mut lines = opener(Some(Position::End)).open().unwrap();
+        assert_eq!(lines.size_hint(), (4, Some(4)));
+        assert_eq!(lines.next().unwrap().unwrap(), "hello");
+        assert_eq!(lines.size_hint(), (3, Some(3)));
+        assert_eq!(lines.count(), 3);
+
+        let fixture = Fixture::lines(["ok"]).create().unwrap();
+        std::fs::write(fixture.path(), b"ok\n\xff\xfe\nnever\n").unwrap();
+        let mut lines = fixture.opener().open().unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "ok");
+        assert!(matches!(
+            lines.next(),
+            Some(Err(Error::InvalidUtf8 { line: 2 }))
+        ));
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let fixture = Fixture::lines(["ok"]).create().unwrap();
+        std::fs::write(fixture.path(), b"ok\nb\xe9t\xff\nnext\n").unwrap();
+        let walk = |policy: Utf8Policy| -> Vec<String> {
+            fixture
+                .builder()
+                .invalid_utf8(policy)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+
+        assert_eq!(
+            walk(Utf8Policy::Lossy),
+            vec!["ok", "b\u{fffd}t\u{fffd}", "next"]
+        );
+        let lines = walk(Utf8Policy::Bytes);
+        assert_eq!(lines[2], "next");
+        let raw: Vec<u8> = lines[1].chars().map(|c| c as u8).collect();
+        assert_eq!(raw, b"b\xe9t\xff");
+    }
+
+    #[test]
+    fn test_step() {
+        let fixture = Fixture::numbered(10).create().unwrap();
+        let walk = |position: &str, direction: &str| -> Vec<String> {
+            let mut lines = fixture
+                .builder()
+                .position(position)
+                .direction(direction)
+                .step(3usize)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap();
+            assert_eq!(lines.size_hint(), (4, Some(4)));
+            lines.by_ref().collect::<Result<_, _>>().unwrap()
+        };
+        assert_eq!(
+            walk("end-10", "forward"),
+            vec!["line 1", "line 4", "line 7", "line 10"]
+        );
+        assert_eq!(
+            walk("10", "backward"),
+            vec!["line 10", "line 7", "line 4", "line 1"]
+        );
+
+        // Lines whose count isn't known up front step the same way
+        let uncounted = |position: Position, direction: &str| -> Vec<String> {
+            let mut lines = fixture
+                .builder()
+                .position(position)
+                .direction(direction)
+                .step(4usize)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap();
+            assert_eq!(lines.size_hint(), (0, None));
+            lines.by_ref().collect::<Result<_, _>>().unwrap()
+        };
+        assert_eq!(
+            uncounted(Position::Byte(0), "forward"),
+            vec!["line 1", "line 5", "line 9"]
+        );
+        assert_eq!(
+            uncounted(Position::End, "backward"),
+            vec!["line 10", "line 6", "line 2"]
+        );
+    }
+
+    #[test]
+    fn test_limit() {
+        let fixture = Fixture::numbered(10).create().unwrap();
+        let walk = |position: Position, direction: &str| -> Vec<String> {
+            let mut lines = fixture
+                .builder()
+                .position(position)
+                .direction(direction)
+                .limit(2usize)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap();
+            assert_eq!(lines.size_hint(), (2, Some(2)));
+            lines.by_ref().collect::<Result<_, _>>().unwrap()
+        };
+        assert_eq!(
+            walk(Position::FromEnd(6), "forward"),
+            vec!["line 5", "line 6"]
+        );
+        assert_eq!(
+            walk(Position::Middle(5), "backward"),
+            vec!["line 5", "line 4"]
+        );
+
+        // The limit holds across the files of a walk over several
+        let lines = OpenerBuilder::default()
+            .paths([fixture.path(), fixture.path()])
+            .position(9)
+            .limit(3usize)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap();
+        assert_eq!(lines.count(), 3);
+    }
+
+    #[test]
+    fn test_exact() {
+        let fixture = Fixture::numbered(10).create().unwrap();
+        let mut lines = fixture.opener().open_exact().unwrap();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines.next().unwrap().unwrap(), "line 1");
+        assert_eq!(lines.len(), 9);
+        let lines = fixture
+            .builder()
+            .position(Position::Middle(4))
+            .direction("backward")
+            .build()
+            .unwrap()
+            .open_exact()
+            .unwrap();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines.collect::<Result<Vec<_>, _>>().unwrap().len(), 4);
+
+        // Lines dropped on the way, or a start found by scanning, leave the
+        // length unknown
+        let filtered = fixture
+            .builder()
+            .filter(|line: &str| line.ends_with('1'))
+            .build()
+            .unwrap();
+        assert!(matches!(filtered.open_exact(), Err(Error::UnknownLength)));
+        let by_bytes = fixture
+            .builder()
+            .position(Position::Byte(20))
+            .build()
+            .unwrap();
+        assert!(matches!(by_bytes.open_exact(), Err(Error::UnknownLength)));
+        let lines = by_bytes.open().unwrap();
+        assert!(lines.exact().is_err());
+
+        // Nothing is known of the files after the first
+        let lines = OpenerBuilder::default()
+            .paths([fixture.path(), fixture.path()])
+            .max_position(Position::End)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap();
+        assert_eq!(lines.size_hint(), (10, None));
+        assert_eq!(lines.count(), 20);
+    }
+
+    #[test]
+    fn test_peek() {
+        // Joins lines indented under the one before them
+        let fixture = Fixture::lines(["a", " b", " c", "d", " e"])
+            .create()
+            .unwrap();
+        let mut lines = fixture.opener().open().unwrap();
+        let mut joined = vec![];
+        while let Some(line) = lines.next() {
+            let mut line = line.unwrap();
+            while let Some(Ok(next)) = lines.peek() {
+                if !next.starts_with(' ') {
+                    break;
+                }
+                line += next.trim();
+                lines.next();
+            }
+            joined.push(line);
+        }
+        assert_eq!(joined, vec!["abc", "de"]);
+
+        let mut lines = fixture
+            .builder()
+            .max_position(Position::End)
+            .limit(4usize)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap();
+        assert_eq!(lines.peek_n(2).unwrap().unwrap(), " c");
+        assert_eq!(lines.size_hint(), (4, Some(4)));
+        assert!(lines.peek_n(4).is_none());
+        assert_eq!(lines.peek().unwrap().unwrap(), "a");
+        assert_eq!(lines.next_back().unwrap().unwrap(), "d");
+        assert_eq!(lines.next_back().unwrap().unwrap(), " c");
+        assert_eq!(
+            lines.numbered().collect::<Result<Vec<_>, _>>().unwrap(),
+            vec![(1, "a".to_string()), (2, " b".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_read_lines_with() {
+        let mut lines = vec![];
+        OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .position("end")
+            .direction("backward")
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .read_lines_with(|line| lines.push(line.to_string()))
+            .unwrap();
+        assert_eq!(lines, vec!["up", "whats", "there", "hello"]);
+
+        // Invalid lines are replaced or fail as the policy says, even once
+        // the buffer holds an earlier line
+        let fixture = Fixture::lines(["ok"]).create().unwrap();
+        std::fs::write(fixture.path(), b"longer line\nbad \xff\nok\n").unwrap();
+        let read_with = |policy: Utf8Policy| {
+            let mut lines = vec![];
+            let mut walk = fixture
+                .builder()
+                .invalid_utf8(policy)
+                .map(|line: String| line.to_uppercase())
+                .build()
+                .unwrap()
+                .open()
+                .unwrap();
+            let read = walk.read_lines_with(|line| lines.push(line.to_string()));
+            (lines, read)
+        };
+        let (lines, read) = read_with(Utf8Policy::Lossy);
+        assert!(read.is_ok());
+        assert_eq!(lines, vec!["LONGER LINE", "BAD \u{fffd}", "OK"]);
+        let (lines, read) = read_with(Utf8Policy::Strict);
+        assert!(matches!(read, Err(Error::InvalidUtf8 { line: 2 })));
+        assert_eq!(lines, vec!["LONGER LINE"]);
+    }
+
+    #[test]
+    fn test_double_ended() {
+        let fixture = Fixture::numbered(10).create().unwrap();
+        let open = |position: Position, direction: &str, max_position: Option<Position>| {
+            let mut builder = fixture.builder();
+            builder.position(position).direction(direction);
+            if let Some(max_position) = max_position {
+                builder.max_position(max_position);
+            }
+            builder.build().unwrap().open().unwrap()
+        };
+        // Takes from alternating ends until the two meet
+        let alternate = |mut lines: LineIter| -> Vec<String> {
+            let mut taken = vec![];
+            for idx in 0.. {
+                let line = match idx % 2 {
+                    0 => lines.next(),
+                    _ => lines.next_back(),
+                };
+                match line {
+                    Some(line) => taken.push(line.unwrap()),
+                    None => break,
+                }
+            }
+            assert!(lines.next().is_none() && lines.next_back().is_none());
+            taken
+        };
+        let numbers = |lines: &[&str]| {
+            lines
+                .iter()
+                .map(|n| format!("line {}", n))
+                .collect::<Vec<_>>()
+        };
+
+        let lines: Vec<String> = open(Position::Start, "forward", None)
+            .rev()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            lines,
+            numbers(&["10", "9", "8", "7", "6", "5", "4", "3", "2", "1"])
+        );
+        assert_eq!(
+            alternate(open(Position::Start, "forward", None)),
+            numbers(&["1", "10", "2", "9", "3", "8", "4", "7", "5", "6"])
+        );
+        // Counted, bounded by line and by byte
+        assert_eq!(
+            alternate(open(Position::FromEnd(8), "forward", Some(Position::End))),
+            numbers(&["3", "10", "4", "9", "5", "8", "6", "7"])
+        );
+        assert_eq!(
+            alternate(open(
+                Position::Middle(7),
+                "backward",
+                Some(Position::Middle(4))
+            )),
+            numbers(&["7", "4", "6", "5"])
+        );
+        assert_eq!(
+            alternate(open(
+                Position::Middle(3),
+                "forward",
+                Some(Position::Byte(35))
+            )),
+            numbers(&["3", "5", "4"])
+        );
+        assert_eq!(
+            alternate(open(Position::End, "backward", None)),
+            numbers(&["10", "1", "9", "2", "8", "3", "7", "4", "6", "5"])
+        );
+
+        let mut lines = open(Position::Start, "forward", None);
+        assert_eq!(lines.next().unwrap().unwrap(), "line 1");
+        let found = lines.rfind(|line| line.as_ref().unwrap().ends_with('5'));
+        assert_eq!(found.unwrap().unwrap(), "line 5");
+        assert_eq!(lines.next().unwrap().unwrap(), "line 2");
+
+        #[cfg(feature = "mmap")]
+        {
+            let mut lines = fixture
+                .builder()
+                .backend(crate::Backend::Mmap)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap();
+            assert_eq!(lines.next_back().unwrap().unwrap(), "line 10");
+            assert_eq!(lines.next().unwrap().unwrap(), "line 1");
+            assert_eq!(lines.rev().count(), 8);
+        }
+
+        // Sources other than a file can only be read from the front
+        let mut lines =
+            crate::open_reader(std::io::Cursor::new(b"a\nb".to_vec()), None, None, None).unwrap();
+        assert!(matches!(lines.next_back(), Some(Err(Error::OneEnded))));
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn test_line_records() {
+        // "hello\nthere\nwhats\nup"
+        let walk = |position: &str, direction: &str| -> Vec<(usize, u64, u64)> {
+            OpenerBuilder::default()
+                .path("./testfiles/1.txt")
+                .position(position)
+                .direction(direction)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap()
+                .line_records()
+                .map(|record| {
+                    let record = record.unwrap();
+                    (record.line_no, record.byte_offset, record.byte_len)
+                })
+                .collect()
+        };
+        assert_eq!(
+            walk("2", "forward"),
+            vec![(2, 6, 6), (3, 12, 6), (4, 18, 2)]
+        );
+        assert_eq!(
+            walk("3", "backward"),
+            vec![(3, 12, 6), (2, 6, 6), (1, 0, 6)]
+        );
+        assert_eq!(walk("byte:12", "forward"), vec![(1, 12, 6), (2, 18, 2)]);
+
+        let fixture = Fixture::lines(["one", "two"]).bom(true).create().unwrap();
+        let record = fixture.opener().open().unwrap().line_records().nth(1);
+        assert_eq!(
+            record.unwrap().unwrap(),
+            LineRecord {
+                line_no: 2,
+                byte_offset: 4,
+                byte_len: 4,
+                text: "two".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_follow() {
+        let fixture = Fixture::lines(["one"]).create().unwrap();
+        let mut lines = fixture
+            .builder()
+            .position("end")
+            .follow(true)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap();
+
+        let path = fixture.path().to_path_buf();
+        let writer = std::thread::spawn(move || {
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(b"two\nthr").unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(150));
+            file.write_all(b"ee\n").unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(150));
+            // Truncating starts the walk over from the top
+            std::fs::write(&path, b"four\n").unwrap();
+        });
+
+        assert_eq!(lines.size_hint(), (0, None));
+        assert_eq!(lines.next().unwrap().unwrap(), "two");
+        assert_eq!(lines.next().unwrap().unwrap(), "three");
+        assert_eq!(lines.next().unwrap().unwrap(), "four");
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_removal() {
+        let follow = |fixture: &Fixture, on_removal: Removal| {
+            fixture
+                .builder()
+                .position("end")
+                .follow(true)
+                .on_removal(on_removal)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap()
+        };
+        let append = |fixture: &Fixture, bytes: &[u8]| {
+            OpenOptions::new()
+                .append(true)
+                .open(fixture.path())
+                .unwrap()
+                .write_all(bytes)
+                .unwrap();
+        };
+
+        // Finished from the open handle, an unended last line included
+        let fixture = Fixture::lines(["one"]).create().unwrap();
+        let mut lines = follow(&fixture, Removal::Finish);
+        append(&fixture, b"two\nthr");
+        assert_eq!(lines.next().unwrap().unwrap(), "two");
+        std::fs::remove_file(fixture.path()).unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "thr");
+        assert!(lines.next().is_none());
+        assert_eq!(lines.removal(), Some(Removal::Finish));
+
+        let fixture = Fixture::lines(["one"]).create().unwrap();
+        let mut lines = follow(&fixture, Removal::Fail);
+        assert_eq!(lines.removal(), None);
+        std::fs::remove_file(fixture.path()).unwrap();
+        assert!(matches!(lines.next(), Some(Err(Error::Removed))));
+        assert_eq!(lines.removal(), Some(Removal::Fail));
+
+        // Rotated away and replaced by a new file at the same path
+        let fixture = Fixture::lines(["one"]).create().unwrap();
+        let mut lines = follow(&fixture, Removal::Successor);
+        append(&fixture, b"two\n");
+        assert_eq!(lines.next().unwrap().unwrap(), "two");
+        let rotated = fixture.path().with_extension("1");
+        std::fs::rename(fixture.path(), &rotated).unwrap();
+        std::fs::write(fixture.path(), b"three\n").unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "three");
+        assert_eq!(lines.removal(), Some(Removal::Successor));
+        std::fs::remove_file(rotated).unwrap();
+    }
+
+    #[test]
+    fn test_pause() {
+        let fixture = Fixture::lines(["one"]).create().unwrap();
+        let mut lines = fixture
+            .builder()
+            .position("end")
+            .follow(true)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(fixture.path())
+            .unwrap();
+        file.write_all(b"two\n").unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "two");
+
+        // Nothing is read while paused, however much is appended: next
+        // waits for resume instead of ending the walk
+        let handle = lines.pause_handle().unwrap();
+        handle.pause();
+        file.write_all(b"thr").unwrap();
+        let resumer = handle.clone();
+        let started = Instant::now();
+        let resumed = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            file.write_all(b"ee\nfour\n").unwrap();
+            resumer.resume();
+        });
+        assert_eq!(lines.next().unwrap().unwrap(), "three");
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(!handle.is_paused());
+        assert_eq!(lines.next().unwrap().unwrap(), "four");
+        resumed.join().unwrap();
+
+        // Walks that don't follow can't be paused
+        assert!(fixture.opener().open().unwrap().pause_handle().is_none());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{parse_timestamp, WalkItem};
+use std::{collections::VecDeque, time::Duration};
+
+// MarkerRules sets which anomalies WalkExt::markers flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MarkerRules {
+    max_gap: Option<Duration>,
+    burst: Option<(u64, Duration)>,
+}
+
+impl MarkerRules {
+    // Flags consecutive timestamped lines further apart than gap.
+    pub fn max_gap(mut self, gap: Duration) -> Self {
+        self.max_gap = Some(gap);
+        self
+    }
+
+    // Flags more than lines lines logged within window of each other, over
+    // a window that slides with every line rather than aligned buckets.
+    // Timestamps have whole seconds, so lines logged in the same second are
+    // always in the same window, however short.
+    pub fn burst(mut self, lines: u64, window: Duration) -> Self {
+        self.burst = Some((lines, window));
+        self
+    }
+}
+
+// Marked is an item yielded by WalkExt::markers. Timestamps are Unix seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Marked {
+    Line(String),
+    // Nothing was logged between the two timestamps
+    Gap { from: i64, to: i64 },
+    // The lines logged from start on went over the burst limit
+    Burst { start: i64, lines: u64 },
+}
+
+// Markers is the iterator returned by WalkExt::markers
+pub struct Markers<I: Iterator<Item: WalkItem>> {
+    inner: I,
+    rules: MarkerRules,
+    previous: Option<i64>,
+    // Timestamps of the lines within the burst window of the latest one,
+    // and whether they are over the limit yet
+    recent: VecDeque<i64>,
+    bursting: bool,
+    pending: VecDeque<<I::Item as WalkItem>::Map<Marked>>,
+}
+
+impl<I: Iterator<Item: WalkItem>> Markers<I> {
+    pub(crate) fn new(inner: I, rules: MarkerRules) -> Self {
+        Markers {
+            inner,
+            rules,
+            previous: None,
+            recent: VecDeque::new(),
+            bursting: false,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn check(&mut self, timestamp: i64) {
+        if let (Some(gap), Some(previous)) = (self.rules.max_gap, self.previous) {
+            if previous.abs_diff(timestamp) > gap.as_secs() {
+                self.pending.push_back(I::Item::settle(
+                    Marked::Gap {
+                        from: previous,
+                        to: timestamp,
+                    },
+                    None,
+                ));
+            }
+        }
+        self.previous = Some(timestamp);
+
+        if let Some((limit, window)) = self.rules.burst {
+            let within = |earlier: i64| {
+                let apart = Duration::from_secs(timestamp.saturating_sub(earlier).max(0) as u64);
+                apart < window || earlier == timestamp
+            };
+            while self.recent.front().is_some_and(|&earlier| !within(earlier)) {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(timestamp);
+            let lines = self.recent.len() as u64;
+            // Only the line that crosses the limit is marked, until the
+            // window drops back under it
+            match lines > limit {
+                true if !self.bursting => {
+                    let start = self.recent[0];
+                    self.pending
+                        .push_back(I::Item::settle(Marked::Burst { start, lines }, None));
+                    self.bursting = true;
+                }
+                true => {}
+                false => self.bursting = false,
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item: WalkItem>> Iterator for Markers<I> {
+    type Item = <I::Item as WalkItem>::Map<Marked>;
+
+    // Errors are passed through without affecting the markers
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(marker) = self.pending.pop_front() {
+            return Some(marker);
+        }
+
+        let item = self.inner.next()?;
+        if let Some(timestamp) = item.line().and_then(parse_timestamp) {
+            self.check(timestamp);
+        }
+        self.pending.push_back(item.map_line(Marked::Line));
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WalkExt;
+
+    #[test]
+    fn test_markers() {
+        let lines = [
+            "2023-01-02T03:00:00Z a",
+            "2023-01-02T03:00:01Z b",
+            "2023-01-02T03:00:02Z c",
+            "no timestamp",
+            "2023-01-02T03:10:00Z d",
+        ]
+        .map(String::from);
+        let rules = MarkerRules::default()
+            .max_gap(Duration::from_secs(300))
+            .burst(2, Duration::from_secs(60));
+
+        let marked: Vec<Marked> = lines.into_iter().markers(rules).collect();
+        assert_eq!(
+            marked,
+            vec![
+                Marked::Line("2023-01-02T03:00:00Z a".to_string()),
+                Marked::Line("2023-01-02T03:00:01Z b".to_string()),
+                Marked::Burst {
+                    start: 1672628400,
+                    lines: 3
+                },
+                Marked::Line("2023-01-02T03:00:02Z c".to_string()),
+                Marked::Line("no timestamp".to_string()),
+                Marked::Gap {
+                    from: 1672628402,
+                    to: 1672629000
+                },
+                Marked::Line("2023-01-02T03:10:00Z d".to_string()),
+            ]
+        );
+
+        // Bursts across a minute boundary are caught, and windows shorter
+        // than a second hold the lines of the same second
+        let bursts = |lines: &[&str], rules: MarkerRules| -> Vec<Marked> {
+            lines
+                .iter()
+                .map(|line| line.to_string())
+                .markers(rules)
+                .filter(|marked| !matches!(marked, Marked::Line(_)))
+                .collect()
+        };
+        let straddling = [
+            "2023-01-02T03:00:59Z a",
+            "2023-01-02T03:01:00Z b",
+            "2023-01-02T03:01:01Z c",
+        ];
+        assert_eq!(
+            bursts(
+                &straddling,
+                MarkerRules::default().burst(2, Duration::from_secs(60))
+            ),
+            vec![Marked::Burst {
+                start: 1672628459,
+                lines: 3
+            }]
+        );
+        let short = MarkerRules::default().burst(1, Duration::from_millis(500));
+        assert!(bursts(&straddling, short).is_empty());
+        assert_eq!(
+            bursts(&["2023-01-02T03:00:59Z a", "2023-01-02T03:00:59Z b"], short),
+            vec![Marked::Burst {
+                start: 1672628459,
+                lines: 2
+            }]
+        );
+    }
+}
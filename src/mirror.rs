@@ -0,0 +1,159 @@
+use crate::{blocks::BLOCK_SIZE, Error, HashAlgo};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+use xxhash_rust::xxh3::Xxh3;
+
+// How many trailing lines of the destination are matched against the
+// source when it isn't a plain prefix of it.
+const TAIL_LINES: usize = 8;
+
+// Appends to dst the lines of src it doesn't have yet, so a mirror of a
+// growing log only copies the new part. Returns the number of bytes
+// appended. Only complete lines are copied.
+//
+// When dst is a byte-for-byte prefix of src the rest of src is appended.
+// Otherwise the last few lines of dst are looked up in src by hash and
+// everything after the latest place matching the most of them is appended;
+// if not even the last line is found, all of src is.
+pub fn sync_append<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<u64, Error> {
+    let mut source = File::open(src.as_ref())?;
+    let mut target = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(dst.as_ref())?;
+    let source_len = source.metadata()?.len();
+    let target_len = target.metadata()?.len();
+
+    let start = if target_len <= source_len
+        && hash_prefix(&mut source, target_len)? == hash_prefix(&mut target, target_len)?
+    {
+        target_len
+    } else {
+        match_tail(&mut source, &mut target)?.unwrap_or(0)
+    };
+
+    let end = last_line_end(&mut source, start, source_len)?;
+    source.seek(SeekFrom::Start(start))?;
+    let appended = io::copy(&mut (&mut source).take(end - start), &mut target)?;
+    target.sync_data()?;
+    Ok(appended)
+}
+
+// Returns the offset just past the last newline between start and len, or
+// start if there is none.
+fn last_line_end(file: &mut File, start: u64, len: u64) -> io::Result<u64> {
+    let mut end = len;
+    let mut buf = vec![0; BLOCK_SIZE as usize];
+    while end > start {
+        let block_start = end.saturating_sub(BLOCK_SIZE).max(start);
+        let block = &mut buf[..(end - block_start) as usize];
+        file.seek(SeekFrom::Start(block_start))?;
+        file.read_exact(block)?;
+        if let Some(idx) = memchr::memrchr(b'\n', block) {
+            return Ok(block_start + idx as u64 + 1);
+        }
+        end = block_start;
+    }
+    Ok(start)
+}
+
+fn hash_prefix(file: &mut File, len: u64) -> io::Result<u64> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut hasher = Xxh3::new();
+    let mut reader = BufReader::new(file.take(len));
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        hasher.update(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+    Ok(hasher.digest())
+}
+
+// Finds the byte offset in source just past the latest run of lines equal
+// to the longest suffix of the last lines of target.
+fn match_tail(source: &mut File, target: &mut File) -> Result<Option<u64>, Error> {
+    target.seek(SeekFrom::Start(0))?;
+    let mut tail = vec![];
+    for line in BufReader::new(&mut *target).split(b'\n') {
+        tail.push(hash_line(&line?));
+        if tail.len() > TAIL_LINES {
+            tail.remove(0);
+        }
+    }
+    if tail.is_empty() {
+        return Ok(None);
+    }
+
+    source.seek(SeekFrom::Start(0))?;
+    let mut reader = BufReader::new(&mut *source);
+    let mut window = vec![];
+    let mut offset = 0;
+    let mut found = None;
+    let mut line = vec![];
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        offset += read as u64;
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        window.push(hash_line(&line));
+        if window.len() > tail.len() {
+            window.remove(0);
+        }
+        let matched = window
+            .iter()
+            .rev()
+            .zip(tail.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if matched > 0 && found.is_none_or(|(best, _)| matched >= best) {
+            found = Some((matched, offset));
+        }
+    }
+    Ok(found.map(|(_, offset)| offset))
+}
+
+fn hash_line(line: &[u8]) -> u64 {
+    HashAlgo::Xxh3.hash(&String::from_utf8_lossy(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+    use std::fs;
+
+    #[test]
+    fn test_sync_append() {
+        let source = Fixture::numbered(5).create().unwrap();
+        let mirror = Fixture::numbered(2).create().unwrap();
+
+        let appended = sync_append(source.path(), mirror.path()).unwrap();
+        assert_eq!(appended, 21);
+        assert_eq!(
+            fs::read(mirror.path()).unwrap(),
+            fs::read(source.path()).unwrap()
+        );
+        assert_eq!(sync_append(source.path(), mirror.path()).unwrap(), 0);
+
+        // A mirror with its own header is matched by its last lines
+        fs::write(mirror.path(), "# mirrored\nline 3\nline 4\n").unwrap();
+        assert_eq!(sync_append(source.path(), mirror.path()).unwrap(), 7);
+        assert_eq!(
+            fs::read_to_string(mirror.path()).unwrap(),
+            "# mirrored\nline 3\nline 4\nline 5\n"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{Backend, Direction, Error, LineIter, Position, WalkOptions, WalkPlan};
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::{self, BufRead, Cursor, Read},
+    sync::Arc,
+};
+
+// Walks a file through a memory map instead of buffered reads. Lines are
+// found with memchr straight in the mapped pages, in either direction.
+//
+// Mapping is only sound while no other process truncates or rewrites the
+// file, which is why Backend::Mmap has to be chosen explicitly, or allowed
+// through Backend::Auto.
+pub(crate) fn walk_mapped(
+    file: File,
+    position: Position,
+    direction: Direction,
+    max_position: Option<Position>,
+    options: WalkOptions,
+) -> Result<LineIter, Error> {
+    let map = Arc::new(unsafe { Mmap::map(&file)? });
+    let mut input = Cursor::new(Mapped {
+        len: map.len(),
+        map: map.clone(),
+    });
+    let plan = WalkPlan::new(
+        &mut input,
+        position,
+        direction,
+        max_position,
+        false,
+        &options,
+    )?;
+    let reader: Box<dyn BufRead + Send> = match (direction, plan.window) {
+        (Direction::Forward, None) => Box::new(input),
+        (Direction::Forward, Some((start, end))) => {
+            let mut window = Cursor::new(Mapped {
+                len: end as usize,
+                map,
+            });
+            window.set_position(start);
+            Box::new(window)
+        }
+        (Direction::Backward, window) => Box::new(RevMapped {
+            pos: input.position() as usize,
+            floor: window.map_or(0, |(lower, _)| lower as usize),
+            map: input.into_inner(),
+        }),
+    };
+    let mut lines = plan.into_lines(reader, Some(file), options);
+    lines.backend = Backend::Mmap;
+    Ok(lines)
+}
+
+// Mapped is the first len bytes of a mapped file
+struct Mapped {
+    map: Arc<Mmap>,
+    len: usize,
+}
+
+impl AsRef<[u8]> for Mapped {
+    fn as_ref(&self) -> &[u8] {
+        &self.map[..self.len]
+    }
+}
+
+// RevMapped reads the mapped bytes between floor and pos backward, one line
+// per read_until, like RevBufReader does for buffered walks
+struct RevMapped {
+    map: Mapped,
+    floor: usize,
+    pos: usize,
+}
+
+impl Read for RevMapped {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.pos - self.floor);
+        let start = self.pos - len;
+        buf[..len].copy_from_slice(&self.map.as_ref()[start..self.pos]);
+        self.pos = start;
+        Ok(len)
+    }
+}
+
+impl BufRead for RevMapped {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(&self.map.as_ref()[self.floor..self.pos])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = self.pos.saturating_sub(amt).max(self.floor);
+    }
+
+    // Prepends the line ending at pos, terminator included, to buf. The
+    // byte just before pos always belongs to it, delimiter or not.
+    fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
+        if self.pos == self.floor {
+            return Ok(0);
+        }
+        let bytes = self.map.as_ref();
+        let start = memchr::memrchr(byte, &bytes[self.floor..self.pos - 1])
+            .map_or(self.floor, |idx| self.floor + idx + 1);
+        buf.splice(..0, bytes[start..self.pos].iter().copied());
+        let read = self.pos - start;
+        self.pos = start;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fixtures::Fixture, Backend, Direction, LineEnding, Position};
+
+    #[test]
+    fn test_mmap_backend() {
+        let fixture = Fixture::lines(["one", "", "three", "four"])
+            .line_ending(LineEnding::CrLf)
+            .trailing_newline(false)
+            .create()
+            .unwrap();
+        let walk = |position: &str, direction: &str| {
+            fixture
+                .builder()
+                .backend(Backend::Mmap)
+                .position(position)
+                .direction(direction)
+                .build()
+                .unwrap()
+                .open()
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        assert_eq!(walk("start", "forward"), vec!["one", "", "three", "four"]);
+        assert_eq!(walk("end", "backward"), vec!["four", "three", "", "one"]);
+        assert_eq!(walk("3", "backward"), vec!["three", "", "one"]);
+        assert_eq!(walk("2", "forward"), vec!["", "three", "four"]);
+        assert_eq!(walk("byte:7", "forward"), vec!["three", "four"]);
+        assert_eq!(walk("byte:7", "backward"), vec!["", "one"]);
+
+        let window = fixture
+            .builder()
+            .backend(Backend::Mmap)
+            .position(Position::Byte(9))
+            .max_position(Position::Byte(6))
+            .direction("backward")
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(window, vec![""]);
+
+        let empty = Fixture::lines(Vec::<String>::new()).create().unwrap();
+        let lines = empty
+            .builder()
+            .backend(Backend::Mmap)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap();
+        assert_eq!(lines.count(), 0);
+    }
+
+    #[test]
+    fn test_auto_backend() {
+        let fixture = Fixture::numbered(10).create().unwrap();
+        let backend = |backend: Backend| {
+            fixture
+                .builder()
+                .backend(backend)
+                .position("end")
+                .direction("backward")
+                .build()
+                .unwrap()
+                .open()
+                .unwrap()
+                .backend()
+        };
+        assert_eq!(backend(Backend::Mmap), Backend::Mmap);
+        assert_eq!(backend(Backend::Auto), Backend::Buffered);
+
+        let large = crate::MMAP_THRESHOLD;
+        let auto = |position: Position, direction: Direction| {
+            Backend::Auto.resolve(large, position, direction)
+        };
+        assert_eq!(auto(Position::End, Direction::Backward), Backend::Mmap);
+        assert_eq!(
+            auto(Position::Middle(500), Direction::Forward),
+            Backend::Mmap
+        );
+        assert_eq!(auto(Position::Start, Direction::Forward), Backend::Buffered);
+        assert_eq!(
+            Backend::Auto.resolve(large - 1, Position::End, Direction::Backward),
+            Backend::Buffered
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{open_lines, Direction, Error, FileWalk, Position, Record};
+use std::{collections::HashMap, io, path::Path, thread, time::Duration};
+
+// MockWalker is a FileWalk over scripted lines, with optional delays and
+// failures, for testing code that consumes walkers without touching disk
+#[derive(Debug, Clone, Default)]
+pub struct MockWalker {
+    lines: Vec<String>,
+    direction: Direction,
+    cursor: usize,
+    delay: Option<Duration>,
+    failures: HashMap<usize, io::ErrorKind>,
+}
+
+impl MockWalker {
+    pub fn from_lines<I, T>(lines: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        MockWalker {
+            lines: lines.into_iter().map(Into::into).collect(),
+            cursor: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        if direction == Direction::Backward {
+            self.cursor = self.lines.len();
+        }
+        self
+    }
+
+    // Sleeps for delay before every record is returned.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    // Makes the first attempt to read line_number fail with kind. The next
+    // attempt returns the line, like a transient IO error would.
+    pub fn fail_at(mut self, line_number: usize, kind: io::ErrorKind) -> Self {
+        self.failures.insert(line_number, kind);
+        self
+    }
+}
+
+impl FileWalk for MockWalker {
+    // Loads the whole file up front, then serves it like from_lines would.
+    fn open_at<T: AsRef<Path>>(
+        path: T,
+        position: Position,
+        direction: Direction,
+    ) -> Result<Self, Error> {
+        let mut walker = MockWalker::from_lines(
+            open_lines(path, None, None, None)?.collect::<Result<Vec<_>, _>>()?,
+        );
+        walker.direction = direction;
+        walker.seek(position)?;
+        Ok(walker)
+    }
+
+    fn next_record(&mut self) -> Option<Result<Record, Error>> {
+        if self.cursor == 0 || self.cursor > self.lines.len() {
+            return None;
+        }
+
+        if let Some(delay) = self.delay {
+            thread::sleep(delay);
+        }
+
+        if let Some(kind) = self.failures.remove(&self.cursor) {
+            return Some(Err(Error::File(io::Error::new(
+                kind,
+                "Scripted mock failure.",
+            ))));
+        }
+
+        let number = self.cursor;
+        self.cursor = match self.direction {
+            Direction::Forward => self.cursor + 1,
+            Direction::Backward => self.cursor - 1,
+        };
+        Some(Ok(Record {
+            number,
+            text: self.lines[number - 1].clone(),
+        }))
+    }
+
+    fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn seek(&mut self, position: Position) -> Result<(), Error> {
+        self.cursor = match position {
+            Position::Start => 1,
+            Position::Middle(line) => line,
+            Position::End => self.lines.len(),
+            Position::FromEnd(lines) => (self.lines.len() + 1).saturating_sub(lines).max(1),
+            // Offsets count a newline after every line
+            Position::Byte(_) | Position::Percent(_) => {
+                let len = self.lines.iter().map(|text| text.len() as u64 + 1).sum();
+                let offset = position.byte_offset(len);
+                let (mut line, mut start) = (1, 0);
+                for text in &self.lines {
+                    if start >= offset {
+                        break;
+                    }
+                    start += text.len() as u64 + 1;
+                    line += 1;
+                }
+                match self.direction {
+                    Direction::Forward => line,
+                    Direction::Backward if start <= offset => line - 1,
+                    Direction::Backward => line.saturating_sub(2),
+                }
+            }
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_walker() {
+        let mut walker = MockWalker::from_lines(["a", "b", "c"])
+            .direction(Direction::Backward)
+            .fail_at(2, io::ErrorKind::Interrupted);
+
+        assert_eq!(walker.next_record().unwrap().unwrap().text, "c");
+        assert!(walker.next_record().unwrap().is_err());
+        assert_eq!(walker.next_record().unwrap().unwrap().number, 2);
+        assert_eq!(walker.next_record().unwrap().unwrap().text, "a");
+        assert!(walker.next_record().is_none());
+
+        let mut walker =
+            MockWalker::open_at("./testfiles/1.txt", Position::Middle(4), Direction::Forward)
+                .unwrap();
+        assert_eq!(walker.next_record().unwrap().unwrap().text, "up");
+        assert!(walker.next_record().is_none());
+    }
+}
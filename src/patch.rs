@@ -0,0 +1,244 @@
+use crate::Error;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
+
+const MAGIC: &[u8] = b"FWPATCH1";
+
+// Patch turns one version of a text file into the next, as runs of lines
+// copied from the old version and runs of new lines carried in full, so a
+// file that mostly gains or shifts lines patches in a fraction of its size.
+// Lines are compared by hash; two different lines hashing the same would
+// go unnoticed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    // Size and hash of the old version, checked before applying
+    base_len: u64,
+    base_hash: u64,
+    ops: Vec<PatchOp>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatchOp {
+    // count lines of the old version, starting at the 0-based line start
+    Copy { start: u64, count: u64 },
+    // Lines of the new version, terminators included
+    Insert(Vec<u8>),
+}
+
+impl Patch {
+    // Encodes the patch compactly, to store or send it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        write_varint(&mut bytes, self.base_len);
+        bytes.extend_from_slice(&self.base_hash.to_le_bytes());
+        for op in &self.ops {
+            match op {
+                PatchOp::Copy { start, count } => {
+                    bytes.push(0);
+                    write_varint(&mut bytes, *start);
+                    write_varint(&mut bytes, *count);
+                }
+                PatchOp::Insert(lines) => {
+                    bytes.push(1);
+                    write_varint(&mut bytes, lines.len() as u64);
+                    bytes.extend_from_slice(lines);
+                }
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, Error> {
+        bytes = bytes.strip_prefix(MAGIC).ok_or(Error::InvalidPatch)?;
+        let base_len = read_varint(&mut bytes)?;
+        let (hash, rest) = bytes.split_first_chunk::<8>().ok_or(Error::InvalidPatch)?;
+        let base_hash = u64::from_le_bytes(*hash);
+        bytes = rest;
+
+        let mut ops = vec![];
+        while let Some((&tag, rest)) = bytes.split_first() {
+            bytes = rest;
+            let op = match tag {
+                0 => PatchOp::Copy {
+                    start: read_varint(&mut bytes)?,
+                    count: read_varint(&mut bytes)?,
+                },
+                1 => {
+                    let len = read_varint(&mut bytes)? as usize;
+                    if len > bytes.len() {
+                        return Err(Error::InvalidPatch);
+                    }
+                    let (lines, rest) = bytes.split_at(len);
+                    bytes = rest;
+                    PatchOp::Insert(lines.to_vec())
+                }
+                _ => return Err(Error::InvalidPatch),
+            };
+            ops.push(op);
+        }
+        Ok(Patch {
+            base_len,
+            base_hash,
+            ops,
+        })
+    }
+}
+
+// Diffs new against old line by line. Only the hashes of the old lines and
+// the lines new adds are held in memory, so both files are streamed.
+pub fn make_patch<P: AsRef<Path>, Q: AsRef<Path>>(old: P, new: Q) -> Result<Patch, Error> {
+    let mut base_hasher = Xxh3::new();
+    let mut base_len = 0;
+    let mut hashes = vec![];
+    let mut first_seen = HashMap::new();
+    for_each_line(File::open(old.as_ref())?, |line| {
+        base_hasher.update(line);
+        base_len += line.len() as u64;
+        let hash = xxh3_64(line);
+        first_seen.entry(hash).or_insert(hashes.len() as u64);
+        hashes.push(hash);
+    })?;
+
+    let mut ops: Vec<PatchOp> = vec![];
+    for_each_line(File::open(new.as_ref())?, |line| {
+        let hash = xxh3_64(line);
+        // Runs of copied lines are extended as long as the old lines follow
+        if let Some(PatchOp::Copy { start, count }) = ops.last_mut() {
+            if hashes.get((*start + *count) as usize) == Some(&hash) {
+                *count += 1;
+                return;
+            }
+        }
+        match (first_seen.get(&hash), ops.last_mut()) {
+            (Some(&start), _) => ops.push(PatchOp::Copy { start, count: 1 }),
+            (None, Some(PatchOp::Insert(lines))) => lines.extend_from_slice(line),
+            (None, _) => ops.push(PatchOp::Insert(line.to_vec())),
+        }
+    })?;
+
+    Ok(Patch {
+        base_len,
+        base_hash: base_hasher.digest(),
+        ops,
+    })
+}
+
+// Writes the file patch was made to produce from base to out, returning the
+// number of bytes written. Fails if base isn't the file the patch was made
+// against.
+pub fn apply_patch<P: AsRef<Path>, W: Write>(
+    base: P,
+    patch: &Patch,
+    mut out: W,
+) -> Result<u64, Error> {
+    let mut base = File::open(base.as_ref())?;
+    let mut hasher = Xxh3::new();
+    let mut offsets = vec![0];
+    let mut len = 0;
+    for_each_line(&mut base, |line| {
+        hasher.update(line);
+        len += line.len() as u64;
+        offsets.push(len);
+    })?;
+    if len != patch.base_len || hasher.digest() != patch.base_hash {
+        return Err(Error::PatchBase);
+    }
+
+    let mut written = 0;
+    for op in &patch.ops {
+        match op {
+            PatchOp::Copy { start, count } => {
+                let end = start.checked_add(*count).ok_or(Error::InvalidPatch)?;
+                let (start, end) = (*start as usize, end as usize);
+                if end >= offsets.len() {
+                    return Err(Error::InvalidPatch);
+                }
+                base.seek(SeekFrom::Start(offsets[start]))?;
+                let len = offsets[end] - offsets[start];
+                written += io::copy(&mut (&mut base).take(len), &mut out)?;
+            }
+            PatchOp::Insert(lines) => {
+                out.write_all(lines)?;
+                written += lines.len() as u64;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(written)
+}
+
+// Calls f with every line of input, its '\n' included when it has one.
+fn for_each_line<R: Read, F: FnMut(&[u8])>(input: R, mut f: F) -> Result<(), Error> {
+    let mut reader = BufReader::new(input);
+    let mut line = vec![];
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        f(&line);
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, Error> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(Error::InvalidPatch)?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err(Error::InvalidPatch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+    use std::fs;
+
+    #[test]
+    fn test_patch() {
+        let old = Fixture::numbered(1000).create().unwrap();
+        let mut contents = fs::read_to_string(old.path()).unwrap();
+        contents = contents.replacen("line 10\n", "", 1);
+        contents = contents.replacen("line 500\n", "line 500\nadded\n", 1);
+        contents.push_str("tail without newline");
+        let new = Fixture::lines(["placeholder"]).create().unwrap();
+        fs::write(new.path(), &contents).unwrap();
+
+        let patch = make_patch(old.path(), new.path()).unwrap();
+        let bytes = patch.to_bytes();
+        assert!(bytes.len() < 100);
+        let patch = Patch::from_bytes(&bytes).unwrap();
+
+        let mut out = vec![];
+        let written = apply_patch(old.path(), &patch, &mut out).unwrap();
+        assert_eq!(written, contents.len() as u64);
+        assert_eq!(out, contents.as_bytes());
+
+        assert!(matches!(
+            apply_patch(new.path(), &patch, vec![]),
+            Err(Error::PatchBase)
+        ));
+        assert!(matches!(
+            Patch::from_bytes(&bytes[..bytes.len() - 3]),
+            Err(Error::InvalidPatch)
+        ));
+    }
+}
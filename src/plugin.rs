@@ -0,0 +1,176 @@
+use crate::{Error, WalkItem};
+use std::{fs::File, io::Read, path::Path};
+use wasmi::{Engine, Linker, Memory, Module, Store, TypedFunc};
+
+// Plugin is a line transform compiled to WebAssembly and loaded at runtime,
+// so parsing or redaction logic can be shipped without rebuilding the host.
+// A plugin module exports:
+//
+//   memory                      its linear memory
+//   alloc(len: i32) -> i32      where the host is to write a line of len bytes
+//   transform(ptr: i32, len: i32) -> i64
+//
+// transform is called with each line, without its terminator, and returns
+// where the line to yield is in memory, its offset in the high 32 bits and
+// its length in the low 32, or -1 to drop the line. The module imports
+// nothing, so it can't reach the host beyond the lines it is handed.
+pub struct Plugin {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    transform: TypedFunc<(i32, i32), i64>,
+}
+
+impl Plugin {
+    // Loads the plugin compiled to the .wasm file at path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut bytes = vec![];
+        File::open(path.as_ref())?.read_to_end(&mut bytes)?;
+        Plugin::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let engine = Engine::default();
+        let module = Module::new(&engine, bytes)?;
+        let mut store = Store::new(&engine, ());
+        let instance = Linker::new(&engine)
+            .instantiate(&mut store, &module)?
+            .start(&mut store)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| Error::PluginExport {
+                name: "memory".to_string(),
+            })?;
+        let alloc = instance.get_typed_func(&store, "alloc")?;
+        let transform = instance.get_typed_func(&store, "transform")?;
+        Ok(Plugin {
+            store,
+            memory,
+            alloc,
+            transform,
+        })
+    }
+
+    // Runs the plugin on line, returning the line it yields or None when it
+    // drops it.
+    pub fn apply(&mut self, line: &str) -> Result<Option<String>, Error> {
+        let len = i32::try_from(line.len()).map_err(|_| Error::PluginOutput)?;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(wasmi::Error::from)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, line.as_bytes())
+            .map_err(wasmi::Error::from)?;
+        let out = self
+            .transform
+            .call(&mut self.store, (ptr, len))
+            .map_err(wasmi::Error::from)?;
+        if out < 0 {
+            return Ok(None);
+        }
+
+        let (ptr, len) = ((out >> 32) as usize, out as u32 as usize);
+        let mut bytes = vec![0; len];
+        self.memory
+            .read(&self.store, ptr, &mut bytes)
+            .map_err(wasmi::Error::from)?;
+        String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|_| Error::PluginOutput)
+    }
+}
+
+// Plugged is the iterator returned by WalkExt::plugin
+pub struct Plugged<I> {
+    inner: I,
+    plugin: Plugin,
+}
+
+impl<I> Plugged<I> {
+    pub(crate) fn new(inner: I, plugin: Plugin) -> Self {
+        Plugged { inner, plugin }
+    }
+}
+
+impl<I: Iterator<Item: WalkItem>> Iterator for Plugged<I> {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Result<String, Error>> {
+        for item in self.inner.by_ref() {
+            let line = match item.into_line() {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            match self.plugin.apply(&line) {
+                Ok(Some(line)) => return Some(Ok(line)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OpenerBuilder, WalkExt};
+
+    // Drops lines starting with 'h' and capitalizes the rest in place:
+    //
+    // (module
+    //   (memory (export "memory") 1)
+    //   (func (export "alloc") (param i32) (result i32) i32.const 1024)
+    //   (func (export "transform") (param i32 i32) (result i64)
+    //     (if (result i64) (i32.eq (i32.load8_u (local.get 0)) (i32.const 104))
+    //       (then i64.const -1)
+    //       (else
+    //         (i32.store8 (local.get 0)
+    //           (i32.sub (i32.load8_u (local.get 0)) (i32.const 32)))
+    //         (i64.or
+    //           (i64.shl (i64.extend_i32_u (local.get 0)) (i64.const 32))
+    //           (i64.extend_i32_u (local.get 1)))))))
+    const CAPITALIZE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x0c, 0x02, 0x60, 0x01, 0x7f, 0x01, 0x7f, 0x60, 0x02, 0x7f, 0x7f, 0x01,
+        0x7e, // types
+        0x03, 0x03, 0x02, 0x00, 0x01, // functions
+        0x05, 0x03, 0x01, 0x00, 0x01, // memory
+        0x07, 0x1e, 0x03, // exports
+        0x06, b'm', b'e', b'm', b'o', b'r', b'y', 0x02, 0x00, //
+        0x05, b'a', b'l', b'l', b'o', b'c', 0x00, 0x00, //
+        0x09, b't', b'r', b'a', b'n', b's', b'f', b'o', b'r', b'm', 0x00, 0x01, //
+        0x0a, 0x30, 0x02, // code
+        0x05, 0x00, 0x41, 0x80, 0x08, 0x0b, // alloc
+        0x28, 0x00, 0x20, 0x00, 0x2d, 0x00, 0x00, 0x41, 0xe8, 0x00, 0x46, 0x04, 0x7e, 0x42, 0x7f,
+        0x05, 0x20, 0x00, 0x20, 0x00, 0x2d, 0x00, 0x00, 0x41, 0x20, 0x6b, 0x3a, 0x00, 0x00, 0x20,
+        0x00, 0xad, 0x42, 0x20, 0x86, 0x20, 0x01, 0xad, 0x84, 0x0b, 0x0b, // transform
+    ];
+
+    #[test]
+    fn test_plugin() {
+        let plugin = Plugin::from_bytes(CAPITALIZE).unwrap();
+        let lines: Vec<String> = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .plugin(plugin)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["There", "Whats", "Up"]);
+
+        // A module without the exports isn't a plugin
+        let empty = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        assert!(matches!(
+            Plugin::from_bytes(&empty),
+            Err(Error::PluginExport { .. })
+        ));
+        assert!(matches!(
+            Plugin::from_bytes(b"not wasm"),
+            Err(Error::Plugin(_))
+        ));
+    }
+}
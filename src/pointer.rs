@@ -0,0 +1,300 @@
+use crate::Error;
+
+// JsonPointer selects a value inside a JSON document by an RFC 6901 pointer
+// such as "/request/status". Lines are scanned rather than deserialized, so
+// only the bytes up to the selected value are looked at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPointer {
+    tokens: Vec<String>,
+}
+
+impl JsonPointer {
+    // Parses a pointer. The empty pointer selects the whole document.
+    pub fn parse(pointer: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidPointer {
+            pointer: pointer.to_string(),
+        };
+        if pointer.is_empty() {
+            return Ok(JsonPointer { tokens: vec![] });
+        }
+        let tokens = pointer.strip_prefix('/').ok_or_else(invalid)?;
+        let tokens = tokens
+            .split('/')
+            .map(|token| {
+                // "~1" is an escaped '/', and "~0" an escaped '~'
+                let mut decoded = String::with_capacity(token.len());
+                let mut chars = token.chars();
+                while let Some(c) = chars.next() {
+                    if c != '~' {
+                        decoded.push(c);
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('0') => decoded.push('~'),
+                        Some('1') => decoded.push('/'),
+                        _ => return Err(invalid()),
+                    }
+                }
+                Ok(decoded)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(JsonPointer { tokens })
+    }
+
+    // Returns the JSON text of the selected value, e.g. "\"error\"" for a
+    // string or "{...}" for an object, or None when the line has no such
+    // value or is not JSON.
+    pub fn find<'a>(&self, json: &'a str) -> Option<&'a str> {
+        let bytes = json.as_bytes();
+        let mut pos = skip_whitespace(bytes, 0);
+        for token in &self.tokens {
+            pos = match bytes.get(pos)? {
+                b'{' => find_member(bytes, pos, token)?,
+                b'[' => find_element(bytes, pos, parse_index(token)?)?,
+                _ => return None,
+            };
+        }
+        let end = skip_value(bytes, pos)?;
+        Some(&json[pos..end])
+    }
+
+    // Like find, but strings are returned unquoted and unescaped.
+    pub fn extract(&self, json: &str) -> Option<String> {
+        let value = self.find(json)?;
+        match value.starts_with('"') {
+            true => unescape(&value[1..value.len() - 1]),
+            false => Some(value.to_string()),
+        }
+    }
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while matches!(bytes.get(pos), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+        pos += 1;
+    }
+    pos
+}
+
+// Array indices are plain decimals without leading zeros.
+fn parse_index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    if !token.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+// Returns where the value of the member named key starts, given the offset
+// of the object's opening brace.
+fn find_member(bytes: &[u8], open: usize, key: &str) -> Option<usize> {
+    let mut pos = skip_whitespace(bytes, open + 1);
+    if bytes.get(pos) == Some(&b'}') {
+        return None;
+    }
+    loop {
+        if bytes.get(pos) != Some(&b'"') {
+            return None;
+        }
+        let end = skip_string(bytes, pos)?;
+        let name = &bytes[pos + 1..end - 1];
+        let matched = match name.contains(&b'\\') {
+            true => unescape(std::str::from_utf8(name).ok()?)? == key,
+            false => name == key.as_bytes(),
+        };
+
+        pos = skip_whitespace(bytes, end);
+        if bytes.get(pos) != Some(&b':') {
+            return None;
+        }
+        pos = skip_whitespace(bytes, pos + 1);
+        if matched {
+            return Some(pos);
+        }
+
+        pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+        match bytes.get(pos)? {
+            b',' => pos = skip_whitespace(bytes, pos + 1),
+            _ => return None,
+        }
+    }
+}
+
+// Returns where the element at index starts, given the offset of the
+// array's opening bracket.
+fn find_element(bytes: &[u8], open: usize, index: usize) -> Option<usize> {
+    let mut pos = skip_whitespace(bytes, open + 1);
+    if bytes.get(pos) == Some(&b']') {
+        return None;
+    }
+    for _ in 0..index {
+        pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+        match bytes.get(pos)? {
+            b',' => pos = skip_whitespace(bytes, pos + 1),
+            _ => return None,
+        }
+    }
+    Some(pos)
+}
+
+// Returns the offset just past the value starting at pos.
+fn skip_value(bytes: &[u8], pos: usize) -> Option<usize> {
+    match bytes.get(pos)? {
+        b'"' => skip_string(bytes, pos),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut pos = pos;
+            loop {
+                match bytes.get(pos)? {
+                    b'"' => {
+                        pos = skip_string(bytes, pos)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(pos + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                pos += 1;
+            }
+        }
+        _ => {
+            let len = bytes[pos..]
+                .iter()
+                .position(|byte| matches!(byte, b',' | b'}' | b']' | b' ' | b'\t' | b'\r' | b'\n'))
+                .unwrap_or(bytes.len() - pos);
+            Some(pos + len)
+        }
+    }
+}
+
+// Returns the offset just past the string whose opening quote is at pos.
+fn skip_string(bytes: &[u8], pos: usize) -> Option<usize> {
+    let mut pos = pos + 1;
+    loop {
+        pos += memchr::memchr2(b'"', b'\\', &bytes[pos..])?;
+        match bytes[pos] {
+            b'"' => return Some(pos + 1),
+            _ => pos += 2,
+        }
+        if pos > bytes.len() {
+            return None;
+        }
+    }
+}
+
+// Decodes the escapes of a JSON string's contents.
+fn unescape(raw: &str) -> Option<String> {
+    if !raw.contains('\\') {
+        return Some(raw.to_string());
+    }
+    let mut decoded = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            decoded.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '"' => decoded.push('"'),
+            '\\' => decoded.push('\\'),
+            '/' => decoded.push('/'),
+            'b' => decoded.push('\u{8}'),
+            'f' => decoded.push('\u{c}'),
+            'n' => decoded.push('\n'),
+            'r' => decoded.push('\r'),
+            't' => decoded.push('\t'),
+            'u' => {
+                let mut units = vec![hex_unit(&mut chars)?];
+                // A high surrogate is followed by the escape of its low half
+                if (0xd800..0xdc00).contains(&units[0]) {
+                    if chars.next()? != '\\' || chars.next()? != 'u' {
+                        return None;
+                    }
+                    units.push(hex_unit(&mut chars)?);
+                }
+                decoded.push(char::decode_utf16(units).next()?.ok()?);
+            }
+            _ => return None,
+        }
+    }
+    Some(decoded)
+}
+
+fn hex_unit(chars: &mut std::str::Chars) -> Option<u16> {
+    let hex: String = chars.take(4).collect();
+    match hex.len() {
+        4 => u16::from_str_radix(&hex, 16).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, Case, WalkExt};
+
+    #[test]
+    fn test_find() {
+        let line = r#"{"ts": 1, "msg": "a \"b\" }", "req": {"path": "/x", "tags": ["a", {"k/v": 2}]}, "lvl": "warn"}"#;
+        let find = |pointer: &str| JsonPointer::parse(pointer).unwrap().find(line);
+        assert_eq!(find("/ts"), Some("1"));
+        assert_eq!(find("/msg"), Some(r#""a \"b\" }""#));
+        assert_eq!(find("/lvl"), Some(r#""warn""#));
+        assert_eq!(find("/req/tags/1/k~1v"), Some("2"));
+        assert_eq!(find("/req/tags/2"), None);
+        assert_eq!(find("/req/tags/01"), None);
+        assert_eq!(find("/missing"), None);
+        assert_eq!(find(""), Some(line));
+        assert_eq!(
+            JsonPointer::parse("/msg").unwrap().extract(line),
+            Some(r#"a "b" }"#.to_string())
+        );
+        assert_eq!(
+            JsonPointer::parse("/s").unwrap().extract(r#"{"s":"é😀"}"#),
+            Some("é😀".to_string())
+        );
+        assert_eq!(JsonPointer::parse("/a").unwrap().find("not json"), None);
+        assert!(JsonPointer::parse("level").is_err());
+        assert!(JsonPointer::parse("/a~2").is_err());
+    }
+
+    #[test]
+    fn test_pointer_adapters() {
+        let fixture = Fixture::lines([
+            r#"{"level": "info", "msg": "started"}"#,
+            r#"{"level": "error", "msg": "failed"}"#,
+            r#"{"msg": "no level"}"#,
+        ])
+        .create()
+        .unwrap();
+        let lines = || fixture.opener().open().unwrap();
+
+        let levels: Vec<String> = lines()
+            .extract_pointer(JsonPointer::parse("/level").unwrap())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(levels, vec!["info", "error"]);
+
+        let errors: Vec<String> = lines()
+            .pointer_equals(JsonPointer::parse("/level").unwrap(), "error")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(errors, vec![r#"{"level": "error", "msg": "failed"}"#]);
+
+        let pointer = JsonPointer::parse("/level").unwrap();
+        let matched = |case: Case| {
+            lines()
+                .pointer_equals(pointer.clone(), "Error")
+                .case(case)
+                .count()
+        };
+        assert_eq!(matched(Case::Insensitive), 1);
+        assert_eq!(matched(Case::Smart), 0);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{
+    auto::{format_of, PEEK_LEN},
+    bom, estimate_lines, read_line_ending, Direction, Error, Format, LineEnding, Position,
+    TextEncoding, WalkConfig,
+};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+// Bytes sampled to estimate the line count of a previewed file
+const SAMPLE_BYTES: u64 = 64 * 1024;
+
+// Preview is a quick look at a file, as returned by preview
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preview {
+    // Up to n lines from the start and from the end, both in file order.
+    // They overlap when the file has fewer than 2n lines.
+    pub head: Vec<String>,
+    pub tail: Vec<String>,
+    pub size: u64,
+    // Told by the byte order mark, UTF-8 without one
+    pub encoding: TextEncoding,
+    pub format: Format,
+    pub line_ending: LineEnding,
+    // Exact for files of up to 64KiB, sampled beyond
+    pub estimated_lines: usize,
+}
+
+// Reads the first and last n lines of the file at path along with what can
+// be told about it cheaply, for file inspector panels. Only the lines
+// returned and a few sampled blocks are read, however large the file.
+pub fn preview<T: AsRef<Path>>(path: T, n: usize) -> Result<Preview, Error> {
+    let path = path.as_ref();
+    let mut input = File::open(path)?;
+    let size = input.metadata()?.len();
+    let mut head = Vec::with_capacity(PEEK_LEN);
+    (&mut input).take(PEEK_LEN as u64).read_to_end(&mut head)?;
+    let (encoding, mark) = bom::detect(&head).unwrap_or((TextEncoding::Utf8, 0));
+    input.seek(SeekFrom::Start(0))?;
+
+    // Byte positions keep the walks from counting every line first
+    let lines = |position: Position, direction: Direction| -> Result<Vec<String>, Error> {
+        let config = WalkConfig {
+            position,
+            direction,
+            ..WalkConfig::default()
+        };
+        config.open(path)?.take(n).collect()
+    };
+    let mut tail = lines(Position::Byte(size), Direction::Backward)?;
+    tail.reverse();
+
+    Ok(Preview {
+        head: lines(Position::Byte(0), Direction::Forward)?,
+        tail,
+        size,
+        encoding,
+        format: format_of(path, &[], &head[mark as usize..]),
+        line_ending: read_line_ending(input)?,
+        estimated_lines: estimate_lines(path, SAMPLE_BYTES)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+
+    #[test]
+    fn test_preview() {
+        let fixture = Fixture::numbered(100).create().unwrap();
+        let quick = preview(fixture.path(), 2).unwrap();
+        assert_eq!(quick.head, vec!["line 1", "line 2"]);
+        assert_eq!(quick.tail, vec!["line 99", "line 100"]);
+        assert_eq!(quick.size, 792);
+        assert_eq!(quick.encoding, TextEncoding::Utf8);
+        assert_eq!(quick.format, Format::Text);
+        assert_eq!(quick.line_ending, LineEnding::Lf);
+        assert_eq!(quick.estimated_lines, 100);
+
+        let fixture = Fixture::lines([r#"{"a": 1}"#]).bom(true).create().unwrap();
+        let quick = preview(fixture.path(), 5).unwrap();
+        assert_eq!(quick.head, vec![r#"{"a": 1}"#]);
+        assert_eq!(quick.tail, quick.head);
+        assert_eq!(quick.format, Format::JsonLines);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{reclaim, Error, Reclaim, SidecarStore, StateStore};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+const DEFAULT_GROUP: &str = "default";
+
+// FileQueue is a minimal durable queue backed by an append-only file. Lines
+// are pushed to the end of the file and popped in batches from a consumer
+// offset persisted in a sidecar file, so a consumer that crashes before
+// acknowledging a batch gets it again after restarting. Every consumer group
+// keeps its own offset, so several processors can read one file at their
+// own pace.
+pub struct FileQueue {
+    path: PathBuf,
+    store: Box<dyn StateStore>,
+    offset_key: String,
+    writer: File,
+    acked: u64,
+    pending: u64,
+}
+
+impl FileQueue {
+    // Opens the queue at path as the default consumer group, creating it if
+    // it doesn't exist. The offset is kept next to it in "<name>.offset".
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::open_group(path, DEFAULT_GROUP)
+    }
+
+    // Opens the queue at path as the named consumer group, whose offset is
+    // kept in "<name>.<group>.offset".
+    pub fn open_group<P: AsRef<Path>>(path: P, group: &str) -> Result<Self, Error> {
+        Self::open_in(&SidecarStore, path, group)
+    }
+
+    // Opens the queue at path as the named consumer group, keeping its
+    // offsets in store instead of next to the file.
+    pub fn open_in<S, P>(store: &S, path: P, group: &str) -> Result<Self, Error>
+    where
+        S: StateStore + Clone + 'static,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let writer = OpenOptions::new().create(true).append(true).open(path)?;
+        let offset_key = offset_key(group)?;
+        let acked = read_offset(store, path, &offset_key)?;
+
+        Ok(FileQueue {
+            path: path.to_path_buf(),
+            store: Box::new(store.clone()),
+            offset_key,
+            writer,
+            acked,
+            pending: acked,
+        })
+    }
+
+    pub fn push<T: AsRef<str>>(&mut self, line: T) -> Result<(), Error> {
+        let mut record = line.as_ref().as_bytes().to_vec();
+        record.push(b'\n');
+        self.writer.write_all(&record)?;
+        self.writer.sync_data()?;
+        Ok(())
+    }
+
+    // Returns up to n lines after the last popped batch. Lines that are still
+    // being written (no newline yet) are left for a later call.
+    pub fn pop_batch(&mut self, n: usize) -> Result<Vec<String>, Error> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        reader.seek(SeekFrom::Start(self.pending))?;
+
+        let mut lines = vec![];
+        let mut buf = String::new();
+        while lines.len() < n {
+            buf.clear();
+            let read = reader.read_line(&mut buf)?;
+            if read == 0 || !buf.ends_with('\n') {
+                break;
+            }
+            self.pending += read as u64;
+            lines.push(buf.trim_end_matches('\n').to_string());
+        }
+
+        Ok(lines)
+    }
+
+    // Durably records that every popped batch has been processed.
+    pub fn ack(&mut self) -> Result<(), Error> {
+        write_offset(
+            self.store.as_ref(),
+            &self.path,
+            &self.offset_key,
+            self.pending,
+        )?;
+        self.acked = self.pending;
+        Ok(())
+    }
+
+    // Forgets popped but unacknowledged batches so they are popped again.
+    pub fn rewind(&mut self) {
+        self.pending = self.acked;
+    }
+
+    // Frees the space used by lines every consumer group has acknowledged.
+    // With TruncatePrefix the file and all offsets are rewritten, so no other
+    // process may use the queue while it runs, and the whole remaining file
+    // is copied: compaction is O(file size) rather than O(bytes freed). Stores that support locking
+    // are locked meanwhile.
+    pub fn compact(&mut self, mode: Reclaim) -> Result<(), Error> {
+        let _lock = self.store.lock(&self.path)?;
+        let groups = Self::groups_in(self.store.as_ref(), &self.path)?;
+        let consumed = groups
+            .iter()
+            .map(|(_, offset)| *offset)
+            .min()
+            .unwrap_or(0)
+            .min(self.acked);
+        reclaim(&self.path, consumed, mode)?;
+        if mode == Reclaim::TruncatePrefix {
+            for (group, offset) in groups {
+                let key = offset_key(&group)?;
+                write_offset(self.store.as_ref(), &self.path, &key, offset - consumed)?;
+            }
+            self.pending -= consumed;
+            self.acked -= consumed;
+            self.writer = OpenOptions::new().append(true).open(&self.path)?;
+        }
+        Ok(())
+    }
+
+    // Lists every consumer group of the queue at path with its acknowledged
+    // offset, sorted by name.
+    pub fn groups<P: AsRef<Path>>(path: P) -> Result<Vec<(String, u64)>, Error> {
+        Self::groups_in(&SidecarStore, path)
+    }
+
+    // Like groups, for queues keeping their offsets in store.
+    pub fn groups_in<P: AsRef<Path>>(
+        store: &dyn StateStore,
+        path: P,
+    ) -> Result<Vec<(String, u64)>, Error> {
+        let path = path.as_ref();
+        let mut groups = vec![];
+        for key in store.keys(path)? {
+            let group = match key.strip_suffix("offset") {
+                Some("") => DEFAULT_GROUP,
+                Some(rest) => match rest.strip_suffix('.') {
+                    Some(group) if !group.is_empty() && !group.contains('.') => group,
+                    _ => continue,
+                },
+                None => continue,
+            };
+            groups.push((group.to_string(), read_offset(store, path, &key)?));
+        }
+        groups.sort();
+        Ok(groups)
+    }
+
+    // Moves the acknowledged offset of a consumer group, e.g. back to 0 to
+    // replay the whole file. Queues already open for the group keep using
+    // the offset they loaded.
+    pub fn reset<P: AsRef<Path>>(path: P, group: &str, offset: u64) -> Result<(), Error> {
+        Self::reset_in(&SidecarStore, path, group, offset)
+    }
+
+    // Like reset, for queues keeping their offsets in store.
+    pub fn reset_in<P: AsRef<Path>>(
+        store: &dyn StateStore,
+        path: P,
+        group: &str,
+        offset: u64,
+    ) -> Result<(), Error> {
+        write_offset(store, path.as_ref(), &offset_key(group)?, offset)
+    }
+}
+
+fn offset_key(group: &str) -> Result<String, Error> {
+    if group == DEFAULT_GROUP {
+        return Ok("offset".to_string());
+    }
+    if group.is_empty() || group.contains(['/', '\\', '.']) {
+        return Err(Error::File(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid consumer group name {:?}", group),
+        )));
+    }
+    Ok(format!("{}.offset", group))
+}
+
+fn read_offset(store: &dyn StateStore, path: &Path, key: &str) -> Result<u64, Error> {
+    Ok(store
+        .get(path, key)?
+        .and_then(|value| String::from_utf8_lossy(&value).trim().parse().ok())
+        .unwrap_or(0))
+}
+
+fn write_offset(store: &dyn StateStore, path: &Path, key: &str, offset: u64) -> Result<(), Error> {
+    store.put(path, key, format!("{}\n", offset).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+    use std::fs;
+
+    #[test]
+    fn test_queue() {
+        let fixture = Fixture::lines(Vec::<String>::new()).create().unwrap();
+        let mut queue = FileQueue::open(fixture.path()).unwrap();
+        for line in ["a", "b", "c"] {
+            queue.push(line).unwrap();
+        }
+
+        assert_eq!(queue.pop_batch(2).unwrap(), vec!["a", "b"]);
+        queue.ack().unwrap();
+        assert_eq!(queue.pop_batch(2).unwrap(), vec!["c"]);
+
+        // The last batch was never acknowledged, so it is delivered again.
+        let mut queue = FileQueue::open(fixture.path()).unwrap();
+        assert_eq!(queue.pop_batch(5).unwrap(), vec!["c"]);
+        queue.ack().unwrap();
+        assert!(queue.pop_batch(5).unwrap().is_empty());
+
+        queue.compact(Reclaim::TruncatePrefix).unwrap();
+        queue.push("d").unwrap();
+        assert_eq!(queue.pop_batch(5).unwrap(), vec!["d"]);
+        assert_eq!(fs::read_to_string(fixture.path()).unwrap(), "d\n");
+        SidecarStore.remove(fixture.path(), "offset").unwrap();
+    }
+
+    #[test]
+    fn test_consumer_groups() {
+        let fixture = Fixture::lines(["a", "b", "c"]).create().unwrap();
+        let mut fast = FileQueue::open_group(fixture.path(), "fast").unwrap();
+        let mut slow = FileQueue::open_group(fixture.path(), "slow").unwrap();
+        assert!(FileQueue::open_group(fixture.path(), "../up").is_err());
+
+        assert_eq!(fast.pop_batch(3).unwrap(), vec!["a", "b", "c"]);
+        fast.ack().unwrap();
+        assert_eq!(slow.pop_batch(1).unwrap(), vec!["a"]);
+        slow.ack().unwrap();
+        assert_eq!(
+            FileQueue::groups(fixture.path()).unwrap(),
+            vec![("fast".to_string(), 6), ("slow".to_string(), 2)]
+        );
+
+        // Only the prefix both groups have acknowledged is dropped.
+        fast.compact(Reclaim::TruncatePrefix).unwrap();
+        assert_eq!(fs::read_to_string(fixture.path()).unwrap(), "b\nc\n");
+        assert_eq!(
+            FileQueue::groups(fixture.path()).unwrap(),
+            vec![("fast".to_string(), 4), ("slow".to_string(), 0)]
+        );
+
+        FileQueue::reset(fixture.path(), "fast", 0).unwrap();
+        let mut fast = FileQueue::open_group(fixture.path(), "fast").unwrap();
+        assert_eq!(fast.pop_batch(1).unwrap(), vec!["b"]);
+
+        for group in ["fast", "slow"] {
+            SidecarStore
+                .remove(fixture.path(), &offset_key(group).unwrap())
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{snap_backward, snap_forward, Error};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    ops::{Range, RangeInclusive},
+};
+
+// ByteRange is the slice of a file returned by Opener::read_bytes, for
+// serving Range requests over log files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteRange {
+    // The bytes read, clamped to the file and snapped to line boundaries
+    // when asked to
+    pub range: Range<u64>,
+    pub bytes: Vec<u8>,
+    // The lines the bytes touch, partly or whole. Empty when bytes is.
+    pub lines: RangeInclusive<usize>,
+    // The size of the whole file, as a Content-Range header reports it
+    pub len: u64,
+}
+
+pub(crate) fn read_bytes(
+    mut input: File,
+    range: Range<u64>,
+    to_lines: bool,
+    delimiter: u8,
+) -> Result<ByteRange, Error> {
+    let len = input.metadata()?.len();
+    let mut start = range.start.min(len);
+    let mut end = range.end.clamp(start, len);
+    if to_lines && start < end {
+        start = snap_backward(&mut input, start, delimiter)?;
+        end = snap_forward(&mut input, end, delimiter)?;
+    }
+
+    input.seek(SeekFrom::Start(0))?;
+    let first_line = count_delimiters((&mut input).take(start), delimiter)? + 1;
+    let mut bytes = Vec::with_capacity((end - start) as usize);
+    (&mut input).take(end - start).read_to_end(&mut bytes)?;
+    // A delimiter at the very end closes the last line rather than starting
+    // another one
+    let inner = match bytes.split_last() {
+        Some((_, inner)) => inner,
+        None => &[],
+    };
+    let last_line = first_line + memchr::memchr_iter(delimiter, inner).count();
+    let lines = match bytes.is_empty() {
+        true => first_line..=first_line - 1,
+        false => first_line..=last_line,
+    };
+
+    Ok(ByteRange {
+        range: start..end,
+        bytes,
+        lines,
+        len,
+    })
+}
+
+fn count_delimiters<R: Read>(input: R, delimiter: u8) -> Result<usize, Error> {
+    let mut reader = BufReader::new(input);
+    let mut count = 0;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(count);
+        }
+        count += memchr::memchr_iter(delimiter, buf).count();
+        let consumed = buf.len();
+        reader.consume(consumed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fixtures::Fixture, OpenerBuilder};
+
+    #[test]
+    fn test_read_bytes() {
+        // "hello\nthere\nwhats\nup"
+        let opener = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .build()
+            .unwrap();
+
+        let slice = opener.read_bytes(8..14, false).unwrap();
+        assert_eq!(slice.bytes, b"ere\nwh");
+        assert_eq!(slice.lines, 2..=3);
+        assert_eq!(slice.len, 20);
+
+        let slice = opener.read_bytes(8..14, true).unwrap();
+        assert_eq!(slice.range, 6..18);
+        assert_eq!(slice.bytes, b"there\nwhats\n");
+        assert_eq!(slice.lines, 2..=3);
+
+        let slice = opener.read_bytes(18..100, true).unwrap();
+        assert_eq!(slice.bytes, b"up");
+        assert_eq!(slice.lines, 4..=4);
+        assert!(opener.read_bytes(30..40, false).unwrap().lines.is_empty());
+
+        let fixture = Fixture::lines(["a", "b"]).create().unwrap();
+        let slice = fixture.opener().read_bytes(0..2, true).unwrap();
+        assert_eq!(slice.bytes, b"a\n");
+        assert_eq!(slice.lines, 1..=1);
+    }
+}
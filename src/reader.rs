@@ -0,0 +1,76 @@
+use crate::Error;
+use std::io::{self, Read};
+
+// LinesReader turns lines back into a byte stream, each one followed by a
+// '\n', for APIs that take a Read such as CSV parsers or upload clients.
+// An error from the lines is returned by the read that reaches it.
+pub(crate) struct LinesReader<I> {
+    lines: I,
+    // The line being read out, its '\n' included, and how much of it has been
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl<I> LinesReader<I> {
+    pub(crate) fn new(lines: I) -> Self {
+        LinesReader {
+            lines,
+            line: vec![],
+            pos: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<String, Error>>> Read for LinesReader<I> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.line.len() {
+            match self.lines.next() {
+                Some(Ok(line)) => {
+                    self.line = line.into_bytes();
+                    self.line.push(b'\n');
+                    self.pos = 0;
+                }
+                Some(Err(Error::File(e))) => return Err(e),
+                Some(Err(e)) => return Err(io::Error::other(e)),
+                None => return Ok(0),
+            }
+        }
+
+        let read = buf.len().min(self.line.len() - self.pos);
+        buf[..read].copy_from_slice(&self.line[self.pos..self.pos + read]);
+        self.pos += read;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OpenerBuilder, WalkExt};
+    use std::io::{BufRead, BufReader, Read};
+
+    #[test]
+    fn test_into_reader() {
+        let opener = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .position("end")
+            .direction("backward")
+            .build()
+            .unwrap();
+        let mut contents = String::new();
+        opener
+            .open()
+            .unwrap()
+            .into_reader()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "up\nwhats\nthere\nhello\n");
+
+        // Read in small pieces through adapters
+        let lines = opener.open().unwrap().limit(2);
+        let mut reader = BufReader::with_capacity(3, lines.into_reader());
+        let mut first = String::new();
+        reader.read_line(&mut first).unwrap();
+        assert_eq!(first, "up\n");
+        assert_eq!(reader.lines().count(), 1);
+    }
+}
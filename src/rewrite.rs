@@ -0,0 +1,202 @@
+use crate::{walk_file, AtomicFile, Error, Position, WalkOptions};
+use regex::{Regex, RegexBuilder};
+use std::{io::Write, path::Path};
+
+// Rewrite is a regular expression and the template its matches are replaced
+// with. Templates use the regex crate's syntax ($1, ${name}), or sed's (\1,
+// &) when parsed from an s/// expression.
+#[derive(Debug, Clone)]
+pub struct Rewrite {
+    re: Regex,
+    template: String,
+    all: bool,
+}
+
+impl Rewrite {
+    // Replaces the first match on every line.
+    pub fn new<T: Into<String>>(re: Regex, template: T) -> Self {
+        Rewrite {
+            re,
+            template: template.into(),
+            all: false,
+        }
+    }
+
+    // Replaces every match on a line instead of just the first.
+    pub fn all(mut self) -> Self {
+        self.all = true;
+        self
+    }
+
+    // Parses a sed-style s/pattern/replacement/flags expression. Any
+    // character may be used as the delimiter, and the g (every match) and
+    // i (case-insensitive) flags are supported.
+    pub fn parse(expr: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidRewrite {
+            expr: expr.to_string(),
+        };
+        let mut chars = expr.chars();
+        if chars.next() != Some('s') {
+            return Err(invalid());
+        }
+        let delimiter = chars.next().ok_or_else(invalid)?;
+        let rest: String = chars.collect();
+        let parts = split_unescaped(&rest, delimiter);
+        let [pattern, replacement, flags] = parts.as_slice() else {
+            return Err(invalid());
+        };
+
+        let mut builder = RegexBuilder::new(pattern);
+        let mut all = false;
+        for flag in flags.chars() {
+            match flag {
+                'g' => all = true,
+                'i' => {
+                    builder.case_insensitive(true);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(Rewrite {
+            re: builder.build()?,
+            template: sed_template(replacement),
+            all,
+        })
+    }
+
+    fn apply(&self, line: &str) -> String {
+        let limit = if self.all { 0 } else { 1 };
+        self.re
+            .replacen(line, limit, self.template.as_str())
+            .into_owned()
+    }
+}
+
+// Splits on every delimiter not preceded by a backslash. An escaped
+// delimiter stands for itself: in the pattern, the first part, it is
+// escaped for the regex, and the rest keep the backslash for sed_template.
+fn split_unescaped(input: &str, delimiter: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek() == Some(&delimiter) {
+            let literal = match parts.len() {
+                1 => regex::escape(&delimiter.to_string()),
+                _ => format!("\\{}", delimiter),
+            };
+            parts.last_mut().unwrap().push_str(&literal);
+            chars.next();
+        } else if c == delimiter {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+            if c == '\\' {
+                if let Some(next) = chars.next() {
+                    parts.last_mut().unwrap().push(next);
+                }
+            }
+        }
+    }
+    parts
+}
+
+// Converts a sed replacement (\1, &, \&) into the regex crate's syntax.
+fn sed_template(replacement: &str) -> String {
+    let mut template = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(d) if d.is_ascii_digit() => template.push_str(&format!("${{{}}}", d)),
+                Some('n') => template.push('\n'),
+                Some('t') => template.push('\t'),
+                Some('$') => template.push_str("$$"),
+                Some(other) => template.push(other),
+                None => template.push('\\'),
+            },
+            '&' => template.push_str("${0}"),
+            '$' => template.push_str("$$"),
+            _ => template.push(c),
+        }
+    }
+    template
+}
+
+// Walks the file at path, applies every rule in order to each line and
+// writes the results to output, ending each line the way the input does.
+// Returns the number of lines written.
+pub fn rewrite<T: AsRef<Path>, P: AsRef<Path>>(
+    path: T,
+    output: P,
+    rules: &[Rewrite],
+) -> Result<usize, Error> {
+    let mut out = AtomicFile::create(output)?;
+    let mut written = 0;
+    let lines = walk_file(path, Position::Start, None, None, WalkOptions::default())?;
+    let line_ending = lines.line_ending();
+    for line in lines {
+        let line = rules.iter().fold(line?, |line, rule| rule.apply(&line));
+        out.write_all(line.as_bytes())?;
+        out.write_all(line_ending.terminator())?;
+        written += 1;
+    }
+
+    out.commit()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, LineEnding};
+    use std::fs;
+
+    #[test]
+    fn test_parse() {
+        let rule = Rewrite::parse(r"s/(\w+)=(\d+)/\2:\1 [&]/g").unwrap();
+        assert_eq!(rule.apply("a=1 b=2"), "1:a [a=1] 2:b [b=2]");
+
+        let rule = Rewrite::parse(r"s|/var/LOG|\|$HOME|i").unwrap();
+        assert_eq!(rule.apply("/var/log/x /var/log"), "|$HOME/x /var/log");
+
+        // Escaped delimiters are literal on both sides
+        let rule = Rewrite::parse(r"s|a\|b|x\|y|").unwrap();
+        assert_eq!(rule.apply("a|b ab"), "x|y ab");
+        let rule = Rewrite::parse(r"s,a\,b,x,").unwrap();
+        assert_eq!(rule.apply("a,b"), "x");
+        let rule = Rewrite::parse(r"s&a&\&&g").unwrap();
+        assert_eq!(rule.apply("aba"), "&b&");
+        let rule = Rewrite::parse(r"s$a$\$$").unwrap();
+        assert_eq!(rule.apply("ab"), "$b");
+
+        assert!(Rewrite::parse("s/a/b").is_err());
+        assert!(Rewrite::parse("s/a/b/x").is_err());
+        assert!(Rewrite::parse("s/(/b/").is_err());
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let output = Fixture::lines(Vec::<String>::new()).create().unwrap();
+        let rules = [
+            Rewrite::new(Regex::new("^(h)(\\w)").unwrap(), "$2$1"),
+            Rewrite::parse("s/e/E/g").unwrap(),
+        ];
+        let written = rewrite("./testfiles/1.txt", output.path(), &rules).unwrap();
+        assert_eq!(written, 4);
+        assert_eq!(
+            fs::read_to_string(output.path()).unwrap(),
+            "Ehllo\nthErE\nwhats\nup\n"
+        );
+
+        let input = Fixture::lines(["hello", "there"])
+            .line_ending(LineEnding::CrLf)
+            .create()
+            .unwrap();
+        rewrite(input.path(), output.path(), &rules).unwrap();
+        assert_eq!(
+            fs::read_to_string(output.path()).unwrap(),
+            "Ehllo\r\nthErE\r\n"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{bom, Error, TextEncoding};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+};
+
+// RawLine is a line read by round_trip along with the bytes it was stored
+// as, so it can be written back exactly as it was unless its text changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawLine {
+    text: String,
+    // The line as stored, and the line break after it, both in the file's
+    // encoding. The last line has no break if the file doesn't end with one.
+    bytes: Vec<u8>,
+    terminator: Vec<u8>,
+    modified: bool,
+}
+
+impl RawLine {
+    // The text of the line, invalid sequences replaced with U+FFFD.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // Replaces the text of the line. The line keeps its line break.
+    pub fn set_text<T: Into<String>>(&mut self, text: T) {
+        let text = text.into();
+        self.modified |= text != self.text;
+        self.text = text;
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn terminator(&self) -> &[u8] {
+        &self.terminator
+    }
+
+    // Whether the text was changed since the line was read, in which case it
+    // is written back re-encoded rather than as stored.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+}
+
+// RoundTrip is the iterator returned by round_trip
+pub struct RoundTrip {
+    reader: BufReader<File>,
+    encoding: TextEncoding,
+    mark: Vec<u8>,
+}
+
+// Reads the file at path line by line, keeping the bytes each line was
+// stored as, its line break and the file's encoding, so a filtered or edited
+// copy written with RoundTrip::writer is byte-identical to the original
+// wherever lines were left alone. Mixed line breaks and invalid sequences
+// survive as well. Lines end at '\n', with a '\r' before it kept as part of
+// the break; the encoding is told by the byte order mark, UTF-8 without one.
+pub fn round_trip<P: AsRef<Path>>(path: P) -> Result<RoundTrip, Error> {
+    let mut input = File::open(path.as_ref())?;
+    let (encoding, mark) = bom::read(&mut input)?.unwrap_or((TextEncoding::Utf8, 0));
+    let mut reader = BufReader::new(input);
+    let mut marked = vec![0; mark as usize];
+    reader.read_exact(&mut marked)?;
+    Ok(RoundTrip {
+        reader,
+        encoding,
+        mark: marked,
+    })
+}
+
+impl RoundTrip {
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+
+    // A writer producing a file in this one's encoding, byte order mark
+    // included.
+    pub fn writer<W: Write>(&self, out: W) -> RoundTripWriter<W> {
+        RoundTripWriter {
+            out,
+            encoding: self.encoding,
+            mark: Some(self.mark.clone()),
+            terminator: encode(self.encoding, "\n"),
+            unterminated: false,
+        }
+    }
+
+    // Reads up to and including the next '\n' code unit.
+    fn read_raw(&mut self) -> Result<Vec<u8>, Error> {
+        let newline = encode(self.encoding, "\n");
+        let mut line = vec![];
+        loop {
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(line);
+            }
+            // A '\n' byte may be half of a UTF-16 code unit, so whole units
+            // are read before checking for a line break
+            if line.len() % newline.len() != 0 {
+                (&mut self.reader).take(1).read_to_end(&mut line)?;
+            }
+            if line.len() % newline.len() == 0 && line.ends_with(&newline) {
+                return Ok(line);
+            }
+        }
+    }
+}
+
+impl Iterator for RoundTrip {
+    type Item = Result<RawLine, Error>;
+
+    fn next(&mut self) -> Option<Result<RawLine, Error>> {
+        let mut bytes = match self.read_raw() {
+            Ok(bytes) if bytes.is_empty() => return None,
+            Ok(bytes) => bytes,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut split = bytes.len();
+        for ending in ["\n", "\r"] {
+            let unit = encode(self.encoding, ending);
+            if bytes[..split].ends_with(&unit) && split % unit.len() == 0 {
+                split -= unit.len();
+            }
+        }
+        let terminator = bytes.split_off(split);
+        Some(Ok(RawLine {
+            text: decode(self.encoding, &bytes),
+            bytes,
+            terminator,
+            modified: false,
+        }))
+    }
+}
+
+// RoundTripWriter writes lines back in the encoding they were read in, as
+// returned by RoundTrip::writer
+pub struct RoundTripWriter<W> {
+    out: W,
+    encoding: TextEncoding,
+    // The byte order mark, until it is written
+    mark: Option<Vec<u8>>,
+    // The line break of the last line written, given to inserted lines
+    terminator: Vec<u8>,
+    // Whether the last line written had no line break
+    unterminated: bool,
+}
+
+impl<W: Write> RoundTripWriter<W> {
+    // Writes line as it was stored, or its text re-encoded if modified.
+    pub fn write_line(&mut self, line: &RawLine) -> Result<(), Error> {
+        self.write_mark()?;
+        match line.modified {
+            true => self.out.write_all(&encode(self.encoding, &line.text))?,
+            false => self.out.write_all(&line.bytes)?,
+        }
+        self.out.write_all(&line.terminator)?;
+        self.unterminated = line.terminator.is_empty();
+        if !self.unterminated {
+            self.terminator.clone_from(&line.terminator);
+        }
+        Ok(())
+    }
+
+    // Writes a new line, ending it like the last line written. A line break
+    // is added first when the last line written had none.
+    pub fn insert(&mut self, text: &str) -> Result<(), Error> {
+        self.write_mark()?;
+        if self.unterminated {
+            self.out.write_all(&self.terminator)?;
+            self.unterminated = false;
+        }
+        self.out.write_all(&encode(self.encoding, text))?;
+        self.out.write_all(&self.terminator)?;
+        Ok(())
+    }
+
+    // Flushes the lines written, and returns the output.
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.write_mark()?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write_mark(&mut self) -> Result<(), Error> {
+        if let Some(mark) = self.mark.take() {
+            self.out.write_all(&mark)?;
+        }
+        Ok(())
+    }
+}
+
+fn encode(encoding: TextEncoding, text: &str) -> Vec<u8> {
+    match encoding {
+        TextEncoding::Utf8 => text.as_bytes().to_vec(),
+        TextEncoding::Utf16Le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        TextEncoding::Utf16Be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+    }
+}
+
+fn decode(encoding: TextEncoding, bytes: &[u8]) -> String {
+    let units = bytes.chunks(2).map(|unit| match (encoding, unit) {
+        (TextEncoding::Utf16Le, [low, high]) => u16::from_le_bytes([*low, *high]),
+        (_, [high, low]) => u16::from_be_bytes([*high, *low]),
+        // A dangling byte at the end of the file
+        _ => 0xfffd,
+    });
+    match encoding {
+        TextEncoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        _ => char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{Fixture, FixtureEncoding};
+    use std::fs;
+
+    #[test]
+    fn test_round_trip() {
+        let fixture = Fixture::lines(["placeholder"]).create().unwrap();
+        let original = b"keep = 1\r\nchange = 2\nbad \xff\r\ndrop = 3\nlast";
+        fs::write(fixture.path(), original).unwrap();
+
+        let lines = round_trip(fixture.path()).unwrap();
+        let mut writer = lines.writer(vec![]);
+        for line in lines {
+            let mut line = line.unwrap();
+            match line.text() {
+                "drop = 3" => continue,
+                "change = 2" => line.set_text("change = 20"),
+                _ => {}
+            }
+            writer.write_line(&line).unwrap();
+        }
+        writer.insert("added = 4").unwrap();
+        let written = writer.finish().unwrap();
+        assert_eq!(
+            written,
+            b"keep = 1\r\nchange = 20\nbad \xff\r\nlast\r\nadded = 4\r\n"
+        );
+
+        let fixture = Fixture::lines(["één", "twee"])
+            .encoding(FixtureEncoding::Utf16Le)
+            .bom(true)
+            .create()
+            .unwrap();
+        let lines = round_trip(fixture.path()).unwrap();
+        assert_eq!(lines.encoding(), TextEncoding::Utf16Le);
+        let mut writer = lines.writer(vec![]);
+        for line in lines {
+            writer.write_line(&line.unwrap()).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), fs::read(fixture.path()).unwrap());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{count_lines, detect_line_ending, open_lines, AtomicFile, Error};
+use std::{fs::File, io::Write, path::Path};
+
+// Redaction is a rule applied to every sampled line before it is exported.
+// All rules keep the line length and punctuation intact so parsing issues
+// can still be reproduced from the anonymized sample.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Redaction {
+    // Replaces lowercase letters with 'x' and uppercase letters with 'X'
+    Letters,
+    // Replaces every digit with '0'
+    Digits,
+    // Replaces every occurrence of the literal with '*' of the same length
+    Literal(String),
+}
+
+impl Redaction {
+    fn apply(&self, line: &str) -> String {
+        match self {
+            Redaction::Letters => line
+                .chars()
+                .map(|c| {
+                    if c.is_uppercase() {
+                        'X'
+                    } else if c.is_alphabetic() {
+                        'x'
+                    } else {
+                        c
+                    }
+                })
+                .collect(),
+            Redaction::Digits => line
+                .chars()
+                .map(|c| if c.is_numeric() { '0' } else { c })
+                .collect(),
+            Redaction::Literal(pattern) if !pattern.is_empty() => {
+                line.replace(pattern.as_str(), &"*".repeat(pattern.chars().count()))
+            }
+            Redaction::Literal(_) => line.to_string(),
+        }
+    }
+}
+
+// Picks up to n lines spread evenly across the file at path, applies the
+// redaction rules in order and writes the result to output. Returns the
+// number of lines written. The lines are counted first so the sample can
+// be picked, then written out in a single streaming pass.
+pub fn export_sample<T: AsRef<Path>, P: AsRef<Path>>(
+    path: T,
+    output: P,
+    n: usize,
+    rules: &[Redaction],
+) -> Result<usize, Error> {
+    let path = path.as_ref();
+    let delimiter = detect_line_ending(path)?.delimiter();
+    let total = count_lines(File::open(path)?, delimiter)?;
+    let mut indices = sample_indices(total, n).into_iter().peekable();
+    let mut out = AtomicFile::create(output)?;
+    let mut written = 0;
+    for (idx, line) in open_lines(path, None, None, None)?.enumerate() {
+        let line = line?;
+        let Some(&next) = indices.peek() else {
+            break;
+        };
+        if idx != next {
+            continue;
+        }
+        indices.next();
+        let line = rules.iter().fold(line, |line, rule| rule.apply(&line));
+        writeln!(out, "{}", line)?;
+        written += 1;
+    }
+
+    out.commit()?;
+    Ok(written)
+}
+
+fn sample_indices(total: usize, n: usize) -> Vec<usize> {
+    if n >= total {
+        return (0..total).collect();
+    }
+
+    let mut indices: Vec<usize> = (0..n).map(|i| i * total / n).collect();
+    indices.dedup();
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+
+    #[test]
+    fn test_redaction() {
+        let line = "User Bob42 logged in from 10.0.0.7";
+        assert_eq!(
+            Redaction::Letters.apply(line),
+            "Xxxx Xxx42 xxxxxx xx xxxx 10.0.0.7"
+        );
+        assert_eq!(
+            Redaction::Digits.apply(line),
+            "User Bob00 logged in from 00.0.0.0"
+        );
+        assert_eq!(
+            Redaction::Literal("Bob".to_string()).apply(line),
+            "User ***42 logged in from 10.0.0.7"
+        );
+    }
+
+    #[test]
+    fn test_export_sample() {
+        let output = Fixture::lines(["unused"]).create().unwrap();
+        let written =
+            export_sample("./testfiles/1.txt", output.path(), 2, &[Redaction::Letters]).unwrap();
+        let contents = std::fs::read_to_string(output.path()).unwrap();
+
+        assert_eq!(written, 2);
+        assert_eq!(contents, "xxxxx\nxxxxx\n");
+        assert_eq!(sample_indices(10, 3), vec![0, 3, 6]);
+
+        let fixture = Fixture::numbered(100).create().unwrap();
+        export_sample(fixture.path(), output.path(), 4, &[]).unwrap();
+        let contents = std::fs::read_to_string(output.path()).unwrap();
+        assert_eq!(contents, "line 1\nline 26\nline 51\nline 76\n");
+        assert_eq!(sample_indices(2, 5), vec![0, 1]);
+    }
+}
@@ -0,0 +1,267 @@
+use crate::{Error, LineEnding};
+use std::io::{self, BufRead};
+
+// Split is where Opener::segments cuts lines that are too long to handle
+// whole
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Split {
+    // After every occurrence of the delimiter, which stays at the end of the
+    // segment it closes, e.g. "},{" for a minified JSON array
+    Delimiter(String),
+    // Every n bytes, moved back to the previous character boundary
+    Chunk(usize),
+}
+
+impl Split {
+    fn validate(&self) -> Result<(), Error> {
+        let valid = match self {
+            Split::Delimiter(delimiter) => !delimiter.is_empty(),
+            Split::Chunk(size) => *size > 0,
+        };
+        if !valid {
+            return Err(Error::File(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot split lines by {:?}", self),
+            )));
+        }
+        Ok(())
+    }
+}
+
+// Segment is a piece of a line along with where it sits: the line number in
+// the file and the byte offset of the piece within the line. Concatenating
+// the segments of a line gives the line back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub line: usize,
+    pub offset: usize,
+    pub text: String,
+    // Whether this is the final segment of its line
+    pub last: bool,
+}
+
+// Segments is the iterator returned by Opener::segments. Only the segment
+// being cut is held in memory, however long the line is.
+pub struct Segments {
+    reader: Box<dyn BufRead + Send>,
+    line_ending: LineEnding,
+    split: Split,
+    // Number of the line being cut and the last line to cut
+    line: usize,
+    last_line: usize,
+    // Bytes of the current line read but not yet yielded, where they start
+    // in the line, and where in buf the delimiter can start at the earliest
+    buf: Vec<u8>,
+    offset: usize,
+    searched: usize,
+    line_ended: bool,
+    done: bool,
+}
+
+impl Segments {
+    pub(crate) fn new(
+        reader: Box<dyn BufRead + Send>,
+        line_ending: LineEnding,
+        split: Split,
+        first_line: usize,
+        last_line: usize,
+    ) -> Result<Self, Error> {
+        split.validate()?;
+        Ok(Segments {
+            reader,
+            line_ending,
+            split,
+            line: first_line,
+            last_line,
+            buf: vec![],
+            offset: 0,
+            searched: 0,
+            line_ended: false,
+            done: first_line > last_line,
+        })
+    }
+
+    // Finds where the next segment ends within buf, if it can be cut yet.
+    fn cut(&mut self) -> Option<usize> {
+        // A carriage return may be the start of the terminator still to come
+        let mut available = self.buf.len();
+        if !self.line_ended
+            && self.line_ending == LineEnding::CrLf
+            && self.buf.last() == Some(&b'\r')
+        {
+            available -= 1;
+        }
+
+        match &self.split {
+            Split::Delimiter(delimiter) => {
+                let delimiter = delimiter.as_bytes();
+                let from = self.searched.min(available);
+                match memchr::memmem::find(&self.buf[from..available], delimiter) {
+                    Some(idx) => {
+                        self.searched = from + idx;
+                        Some(from + idx + delimiter.len())
+                    }
+                    None => {
+                        self.searched = from.max(available.saturating_sub(delimiter.len() - 1));
+                        None
+                    }
+                }
+            }
+            Split::Chunk(size) if available >= *size => {
+                let mut end = *size;
+                while end > 0 && self.buf.get(end).is_some_and(|byte| is_continuation(*byte)) {
+                    end -= 1;
+                }
+                Some(if end == 0 { *size } else { end })
+            }
+            Split::Chunk(_) => None,
+        }
+    }
+
+    // Reads up to the end of the current line or of the reader's buffer,
+    // whichever comes first. Returns false once the file is exhausted.
+    fn fill(&mut self) -> Result<bool, Error> {
+        let delimiter = self.line_ending.delimiter();
+        let available = self.reader.fill_buf()?;
+        if available.is_empty() {
+            self.line_ended = true;
+            return Ok(!self.buf.is_empty() || self.offset > 0);
+        }
+        let (used, ended) = match memchr::memchr(delimiter, available) {
+            Some(idx) => (idx + 1, true),
+            None => (available.len(), false),
+        };
+        self.buf.extend_from_slice(&available[..used]);
+        self.reader.consume(used);
+        if ended {
+            self.line_ending.strip(&mut self.buf);
+            self.line_ended = true;
+        }
+        Ok(true)
+    }
+
+    fn segment(&mut self, end: usize) -> Result<Segment, Error> {
+        let rest = self.buf.split_off(end);
+        let bytes = std::mem::replace(&mut self.buf, rest);
+        let text = String::from_utf8(bytes).map_err(|_| Error::InvalidUtf8 { line: self.line })?;
+        let segment = Segment {
+            line: self.line,
+            offset: self.offset,
+            last: self.line_ended && self.buf.is_empty(),
+            text,
+        };
+        self.offset += end;
+        self.searched = 0;
+        if segment.last {
+            self.line += 1;
+            self.offset = 0;
+            self.line_ended = false;
+            self.done = self.line > self.last_line;
+        }
+        Ok(segment)
+    }
+
+    fn next_segment(&mut self) -> Result<Option<Segment>, Error> {
+        loop {
+            // A segment reaching the end of buf waits for the next read, to
+            // tell whether it is the last of its line
+            if let Some(end) = self.cut().filter(|end| *end < self.buf.len()) {
+                return self.segment(end).map(Some);
+            }
+            if self.line_ended {
+                let end = self.buf.len();
+                return self.segment(end).map(Some);
+            }
+            if !self.fill()? {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+impl Iterator for Segments {
+    type Item = Result<Segment, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let segment = self.next_segment();
+        if !matches!(segment, Ok(Some(_))) {
+            self.done = true;
+        }
+        segment.transpose()
+    }
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+
+    fn cut(fixture: &Fixture, split: Split) -> Vec<(usize, usize, String, bool)> {
+        fixture
+            .opener()
+            .segments(split)
+            .unwrap()
+            .map(|segment| {
+                let segment = segment.unwrap();
+                (segment.line, segment.offset, segment.text, segment.last)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_delimiter_segments() {
+        let fixture = Fixture::lines([r#"[{"a":1},{"b":2},{"c":3}]"#, "", "tail"])
+            .line_ending(LineEnding::CrLf)
+            .create()
+            .unwrap();
+        assert_eq!(
+            cut(&fixture, Split::Delimiter("},{".to_string())),
+            vec![
+                (1, 0, r#"[{"a":1},{"#.to_string(), false),
+                (1, 10, r#""b":2},{"#.to_string(), false),
+                (1, 18, r#""c":3}]"#.to_string(), true),
+                (2, 0, "".to_string(), true),
+                (3, 0, "tail".to_string(), true),
+            ]
+        );
+
+        // Lines much longer than the read buffer are cut as they stream in
+        let long = "x},{".repeat(10_000);
+        let fixture = Fixture::lines([long.as_str()]).create().unwrap();
+        let segments = cut(&fixture, Split::Delimiter("},{".to_string()));
+        assert_eq!(segments.len(), 10_000);
+        assert_eq!(segments[9_999], (1, 39_996, "x},{".to_string(), true));
+    }
+
+    #[test]
+    fn test_chunk_segments() {
+        let fixture = Fixture::lines(["abcdé", "fg"]).create().unwrap();
+        assert_eq!(
+            cut(&fixture, Split::Chunk(5)),
+            vec![
+                (1, 0, "abcd".to_string(), false),
+                (1, 4, "é".to_string(), true),
+                (2, 0, "fg".to_string(), true),
+            ]
+        );
+
+        let segments = fixture
+            .builder()
+            .position(2)
+            .build()
+            .unwrap()
+            .segments(Split::Chunk(1))
+            .unwrap()
+            .map(|segment| segment.unwrap().text)
+            .collect::<Vec<_>>();
+        assert_eq!(segments, vec!["f", "g"]);
+        assert!(fixture.opener().segments(Split::Chunk(0)).is_err());
+    }
+}
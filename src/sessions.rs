@@ -0,0 +1,120 @@
+use crate::{Direction, Error, LineIter, Position, WalkConfig};
+use std::{cmp::Reverse, collections::VecDeque, fs, path::PathBuf};
+
+// SessionItem is an item yielded by Sessions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionItem {
+    // Marks where the lines of the next file start
+    File(PathBuf),
+    Line(String),
+}
+
+// Sessions is the iterator returned by Opener::sessions. It walks a set of
+// rotated logs newest first, each one backward from its end, the way a UI
+// scrolls back through history.
+pub struct Sessions {
+    config: WalkConfig,
+    // Files still to walk, the next one first
+    pending: VecDeque<PathBuf>,
+    current: Option<LineIter>,
+}
+
+impl Sessions {
+    // Walks paths in the order given, with config's settings other than
+    // where to start and which way to go.
+    pub(crate) fn new(paths: Vec<PathBuf>, mut config: WalkConfig) -> Self {
+        config.position = Position::End;
+        config.direction = Direction::Backward;
+        config.max_position = None;
+        config.follow = false;
+        Sessions {
+            config,
+            pending: paths.into(),
+            current: None,
+        }
+    }
+}
+
+impl Iterator for Sessions {
+    type Item = Result<SessionItem, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(lines) = &mut self.current {
+            match lines.next() {
+                Some(line) => return Some(line.map(SessionItem::Line)),
+                None => self.current = None,
+            }
+        }
+
+        // Errors opening one file don't end the walk
+        let path = self.pending.pop_front()?;
+        Some(self.config.open(&path).map(|lines| {
+            self.current = Some(lines);
+            SessionItem::File(path)
+        }))
+    }
+}
+
+// Orders rotated logs newest first: by the number logrotate appends, such
+// as app.log.2 or app.log.2.gz, with unnumbered files first, and then by
+// modification time for files named by date instead.
+pub(crate) fn newest_first(mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.sort_by_cached_key(|path| {
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        (rotation_number(path), Reverse(modified))
+    });
+    paths
+}
+
+fn rotation_number(path: &std::path::Path) -> u64 {
+    path.file_name()
+        .map(|name| name.to_string_lossy())
+        .and_then(|name| name.rsplit('.').take(2).find_map(|part| part.parse().ok()))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::FixtureDir, OpenerBuilder};
+
+    #[test]
+    fn test_sessions() {
+        let dir = FixtureDir::create().unwrap();
+        let root = dir.path();
+        fs::write(root.join("app.log.2"), "a1\na2\n").unwrap();
+        fs::write(root.join("app.log"), "c1\nc2\n").unwrap();
+        fs::write(root.join("app.log.1"), "b1\nb2\n").unwrap();
+
+        let opener = OpenerBuilder::default()
+            .path(root.join("app.log*"))
+            .build()
+            .unwrap();
+        let items: Vec<SessionItem> = opener
+            .sessions()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let file = |name: &str| SessionItem::File(root.join(name));
+        let line = |text: &str| SessionItem::Line(text.to_string());
+        assert_eq!(
+            items,
+            vec![
+                file("app.log"),
+                line("c2"),
+                line("c1"),
+                file("app.log.1"),
+                line("b2"),
+                line("b1"),
+                file("app.log.2"),
+                line("a2"),
+                line("a1"),
+            ]
+        );
+        assert_eq!(rotation_number("app.log.3.gz".as_ref()), 3);
+        assert_eq!(rotation_number("app-2024-01-02.log".as_ref()), 0);
+    }
+}
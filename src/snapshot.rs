@@ -0,0 +1,194 @@
+use crate::Error;
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use xxhash_rust::xxh3::Xxh3;
+
+// FileEntry holds the metadata captured for a single file in a snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub hash: Option<u64>,
+}
+
+impl FileEntry {
+    fn differs_from(&self, other: &FileEntry) -> bool {
+        if self.size != other.size {
+            return true;
+        }
+
+        match (self.hash, other.hash) {
+            (Some(a), Some(b)) => a != b,
+            _ => self.modified != other.modified,
+        }
+    }
+}
+
+// TreeSnapshot records the state of every file under a directory at a point in time
+#[derive(Debug, Clone, Default)]
+pub struct TreeSnapshot {
+    root: PathBuf,
+    entries: BTreeMap<PathBuf, FileEntry>,
+}
+
+impl TreeSnapshot {
+    // Captures sizes and modification times of every file under root.
+    pub fn capture<P: AsRef<Path>>(root: P) -> Result<Self, Error> {
+        Self::capture_inner(root.as_ref(), false)
+    }
+
+    // Same as capture, but also hashes the contents of every file.
+    pub fn capture_hashed<P: AsRef<Path>>(root: P) -> Result<Self, Error> {
+        Self::capture_inner(root.as_ref(), true)
+    }
+
+    fn capture_inner(root: &Path, hashed: bool) -> Result<Self, Error> {
+        let mut entries = BTreeMap::new();
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+
+                let metadata = fs::metadata(&path)?;
+                if !metadata.is_file() {
+                    continue;
+                }
+
+                let hash = if hashed {
+                    Some(hash_file(&path)?)
+                } else {
+                    None
+                };
+                let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+                entries.insert(
+                    relative,
+                    FileEntry {
+                        size: metadata.len(),
+                        modified: metadata.modified().ok(),
+                        hash,
+                    },
+                );
+            }
+        }
+
+        Ok(TreeSnapshot {
+            root: root.to_path_buf(),
+            entries,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // Entries are keyed by their path relative to the snapshot root.
+    pub fn entries(&self) -> &BTreeMap<PathBuf, FileEntry> {
+        &self.entries
+    }
+
+    // Reports what changed between this snapshot and a newer one.
+    pub fn diff(&self, newer: &TreeSnapshot) -> TreeDiff {
+        let mut diff = TreeDiff::default();
+        for (path, entry) in &newer.entries {
+            match self.entries.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(old) if old.differs_from(entry) => diff.modified.push(path.clone()),
+                _ => {}
+            }
+        }
+
+        for path in self.entries.keys() {
+            if !newer.entries.contains_key(path) {
+                diff.removed.push(path.clone());
+            }
+        }
+
+        diff
+    }
+}
+
+// TreeDiff lists the relative paths that changed between two snapshots
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeDiff {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+}
+
+impl TreeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    // Files that need to be walked again: everything added or modified.
+    pub fn changed(&self) -> impl Iterator<Item = &PathBuf> {
+        self.added.iter().chain(self.modified.iter())
+    }
+}
+
+// Hashes the contents with XXH3, the same as HashAlgo::Xxh3, so snapshots
+// taken by different builds can still be compared.
+fn hash_file(path: &Path) -> Result<u64, Error> {
+    let mut file = File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+
+    Ok(hasher.digest())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unchanged_tree() {
+        let before = TreeSnapshot::capture_hashed("./testfiles").unwrap();
+        let after = TreeSnapshot::capture_hashed("./testfiles").unwrap();
+        assert_eq!(
+            hash_file(Path::new("./testfiles/1.txt")).unwrap(),
+            crate::HashAlgo::Xxh3.hash(&fs::read_to_string("./testfiles/1.txt").unwrap())
+        );
+        assert!(before.entries().contains_key(Path::new("1.txt")));
+        assert!(before.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut before = TreeSnapshot::capture("./testfiles").unwrap();
+        let after = before.clone();
+        before.entries.remove(Path::new("1.txt"));
+        before.entries.insert(
+            PathBuf::from("gone.txt"),
+            FileEntry {
+                size: 0,
+                modified: None,
+                hash: None,
+            },
+        );
+        before.entries.get_mut(Path::new("2.txt")).unwrap().size += 1;
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.added, vec![PathBuf::from("1.txt")]);
+        assert_eq!(diff.removed, vec![PathBuf::from("gone.txt")]);
+        assert_eq!(diff.modified, vec![PathBuf::from("2.txt")]);
+        assert_eq!(diff.changed().count(), 2);
+    }
+}
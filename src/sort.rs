@@ -0,0 +1,187 @@
+use crate::{walk_file, AtomicFile, Error, Position, WalkOptions};
+use std::{cmp::Ordering, io::Write, path::Path};
+
+// SortKey selects how lines, or the chosen field of each line, are compared
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SortKey {
+    // Byte-wise comparison, like `sort` with LC_ALL=C
+    #[default]
+    Lexical,
+    // Compares the leading number of the key, like `sort -n`
+    Numeric,
+    // Compares sizes such as 512, 1.2K or 3G, like `sort -h`
+    HumanSize,
+    // Collates by the rules of the given BCP 47 locale, e.g. "de" or "sv"
+    #[cfg(feature = "icu")]
+    Locale(String),
+}
+
+enum Comparator {
+    Lexical,
+    Numeric,
+    HumanSize,
+    #[cfg(feature = "icu")]
+    Locale(Box<icu_collator::Collator>),
+}
+
+impl SortKey {
+    fn comparator(&self) -> Result<Comparator, Error> {
+        Ok(match self {
+            SortKey::Lexical => Comparator::Lexical,
+            SortKey::Numeric => Comparator::Numeric,
+            SortKey::HumanSize => Comparator::HumanSize,
+            #[cfg(feature = "icu")]
+            SortKey::Locale(tag) => {
+                let unsupported = || Error::Locale {
+                    locale: tag.clone(),
+                };
+                let locale: icu_locid::Locale = tag.parse().map_err(|_| unsupported())?;
+                let collator = icu_collator::Collator::try_new(
+                    &(&locale).into(),
+                    icu_collator::CollatorOptions::new(),
+                )
+                .map_err(|_| unsupported())?;
+                Comparator::Locale(Box::new(collator))
+            }
+        })
+    }
+}
+
+impl Comparator {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            Comparator::Lexical => a.cmp(b),
+            Comparator::Numeric => parse_numeric(a)
+                .unwrap_or(0.0)
+                .total_cmp(&parse_numeric(b).unwrap_or(0.0)),
+            Comparator::HumanSize => parse_human_size(a)
+                .unwrap_or(0.0)
+                .total_cmp(&parse_human_size(b).unwrap_or(0.0)),
+            #[cfg(feature = "icu")]
+            Comparator::Locale(collator) => collator.compare(a, b),
+        }
+    }
+}
+
+// Parses the number at the start of s after any leading whitespace, e.g.
+// 42 from "42 requests" or -1.5 from " -1.5s".
+pub fn parse_numeric(s: &str) -> Option<f64> {
+    let s = s.trim_start();
+    let mut end = 0;
+    for (idx, c) in s.char_indices() {
+        let sign = idx == 0 && (c == '-' || c == '+');
+        if !(c.is_ascii_digit() || c == '.' || sign) {
+            break;
+        }
+        end = idx + c.len_utf8();
+    }
+    s[..end].parse().ok()
+}
+
+// Parses a size with an optional binary suffix (K, M, G, T, P, E), e.g.
+// 1.2K or 3G, into a number of units.
+pub fn parse_human_size(s: &str) -> Option<f64> {
+    let number = parse_numeric(s)?;
+    let s = s.trim_start();
+    let digits = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(s.len());
+    let exponent = match s[digits..].chars().next().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => 1,
+        Some('M') => 2,
+        Some('G') => 3,
+        Some('T') => 4,
+        Some('P') => 5,
+        Some('E') => 6,
+        _ => 0,
+    };
+    Some(number * 1024f64.powi(exponent))
+}
+
+// Returns the 1-based whitespace-separated field n of line.
+pub fn field(line: &str, n: usize) -> Option<&str> {
+    line.split_whitespace().nth(n.checked_sub(1)?)
+}
+
+// Sorts the lines of the file at path by key, comparing only the given
+// field of each line when set, and writes them to output. Lines with equal
+// keys are ordered byte-wise. Returns the number of lines written.
+pub fn sort_file<T: AsRef<Path>, P: AsRef<Path>>(
+    path: T,
+    output: P,
+    key: &SortKey,
+    field_number: Option<usize>,
+) -> Result<usize, Error> {
+    let comparator = key.comparator()?;
+    let mut lines = walk_file(path, Position::Start, None, None, WalkOptions::default())?
+        .collect::<Result<Vec<_>, _>>()?;
+    let key_of = |line: &str| -> String {
+        match field_number {
+            Some(n) => field(line, n).unwrap_or_default().to_string(),
+            None => line.to_string(),
+        }
+    };
+    lines.sort_by(|a, b| {
+        comparator
+            .compare(&key_of(a), &key_of(b))
+            .then_with(|| a.cmp(b))
+    });
+
+    let mut out = AtomicFile::create(output)?;
+    for line in &lines {
+        writeln!(out, "{}", line)?;
+    }
+    out.commit()?;
+    Ok(lines.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::Fixture;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(parse_numeric(" -1.5s"), Some(-1.5));
+        assert_eq!(parse_numeric("42 requests"), Some(42.0));
+        assert_eq!(parse_numeric("n/a"), None);
+        assert_eq!(parse_human_size("1.5K"), Some(1536.0));
+        assert_eq!(parse_human_size("3g"), Some(3.0 * 1024.0 * 1024.0 * 1024.0));
+        assert_eq!(parse_human_size("512"), Some(512.0));
+        assert_eq!(field("GET /a 1.2K", 3), Some("1.2K"));
+        assert_eq!(field("GET /a", 0), None);
+    }
+
+    #[test]
+    fn test_sort_file() {
+        let fixture = Fixture::lines(["b 2G", "a 10", "c 1.5M", "d 900K"])
+            .create()
+            .unwrap();
+        let output = Fixture::lines(["unused"]).create().unwrap();
+
+        sort_file(fixture.path(), output.path(), &SortKey::HumanSize, Some(2)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(output.path()).unwrap(),
+            "a 10\nd 900K\nc 1.5M\nb 2G\n"
+        );
+
+        sort_file(fixture.path(), output.path(), &SortKey::Numeric, Some(2)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(output.path()).unwrap(),
+            "c 1.5M\nb 2G\na 10\nd 900K\n"
+        );
+    }
+
+    #[cfg(feature = "icu")]
+    #[test]
+    fn test_locale_collation() {
+        let comparator = SortKey::Locale("sv".to_string()).comparator().unwrap();
+        // Swedish sorts ä after z, unlike German
+        assert_eq!(comparator.compare("ä", "z"), Ordering::Greater);
+        let comparator = SortKey::Locale("de".to_string()).comparator().unwrap();
+        assert_eq!(comparator.compare("ä", "z"), Ordering::Less);
+        assert!(SortKey::Locale("not a locale".to_string())
+            .comparator()
+            .is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use crate::Error;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static SPOOL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Copies a stream into an anonymous temp file and rewinds it, for walks over
+// decoded contents that need to seek. This costs reading the whole stream
+// before the first line.
+pub(crate) fn spool<R: Read>(mut reader: R) -> Result<File, Error> {
+    let mut spooled = spool_file()?;
+    io::copy(&mut reader, &mut spooled)?;
+    spooled.seek(SeekFrom::Start(0))?;
+    Ok(spooled)
+}
+
+// Creates a temp file that is gone once its handle is closed.
+pub(crate) fn spool_file() -> Result<File, Error> {
+    let path = std::env::temp_dir().join(format!(
+        "filewalker-spool-{}-{}",
+        std::process::id(),
+        SPOOL_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create_new(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        const FILE_FLAG_DELETE_ON_CLOSE: u32 = 0x0400_0000;
+        options.custom_flags(FILE_FLAG_DELETE_ON_CLOSE);
+    }
+    let file = options.open(&path)?;
+    #[cfg(not(windows))]
+    fs::remove_file(&path)?;
+    Ok(file)
+}
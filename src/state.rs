@@ -0,0 +1,183 @@
+use crate::{store::source_id, Error, HashAlgo};
+use std::{
+    fs::{self, File, OpenOptions},
+    path::{Path, PathBuf},
+};
+
+const SOURCE_FILE: &str = ".source";
+const LOCK_FILE: &str = ".lock";
+
+// StateDir keeps the sidecar files of many source files (such as queue
+// offsets) under one directory instead of next to each source. Every source
+// gets its own entry, which can be locked across processes and is garbage
+// collected once the source is gone.
+#[derive(Debug, Clone)]
+pub struct StateDir {
+    root: PathBuf,
+}
+
+// StateLock holds the exclusive lock on a source's entry until dropped
+#[derive(Debug)]
+pub struct StateLock {
+    _file: File,
+}
+
+impl StateDir {
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Self, Error> {
+        fs::create_dir_all(root.as_ref())?;
+        Ok(StateDir {
+            root: root.as_ref().to_path_buf(),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // Blocks until this process holds the lock on the entry of source.
+    pub fn lock<P: AsRef<Path>>(&self, source: P) -> Result<StateLock, Error> {
+        let dir = self.entry(source.as_ref())?;
+        let file = open_lock(&dir)?;
+        file.lock()?;
+        Ok(StateLock { _file: file })
+    }
+
+    // Removes the entries of sources that no longer exist, skipping those
+    // locked by someone else. Returns the number of entries removed.
+    pub fn gc(&self) -> Result<usize, Error> {
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.root)? {
+            let dir = entry?.path();
+            if !dir.is_dir() {
+                continue;
+            }
+            let source = fs::read_to_string(dir.join(SOURCE_FILE)).ok();
+            if source.is_some_and(|source| Path::new(&source).exists()) {
+                continue;
+            }
+            let lock = open_lock(&dir)?;
+            if lock.try_lock().is_err() {
+                continue;
+            }
+            fs::remove_dir_all(&dir)?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    pub(crate) fn sidecars(&self, source: &Path) -> Result<Sidecars, Error> {
+        Ok(Sidecars {
+            dir: self.entry(source)?,
+            prefix: file_name(source),
+        })
+    }
+
+    // Returns the entry directory of source, creating it if needed. Entries
+    // are named after the source and a hash of its absolute path.
+    fn entry(&self, source: &Path) -> Result<PathBuf, Error> {
+        let absolute = source_id(source)?;
+        let dir = self.root.join(format!(
+            "{}-{:016x}",
+            file_name(source),
+            HashAlgo::Xxh3.hash(&absolute)
+        ));
+        if !dir.join(SOURCE_FILE).exists() {
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join(SOURCE_FILE), absolute.as_bytes())?;
+        }
+        Ok(dir)
+    }
+}
+
+fn open_lock(dir: &Path) -> Result<File, Error> {
+    Ok(OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(LOCK_FILE))?)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+// Sidecars is where the sidecar files of one source live: either next to
+// it or in its StateDir entry. Files are named "<prefix>.<suffix>".
+#[derive(Debug, Clone)]
+pub(crate) struct Sidecars {
+    dir: PathBuf,
+    prefix: String,
+}
+
+impl Sidecars {
+    pub(crate) fn beside(source: &Path) -> Self {
+        let dir = match source.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        Sidecars {
+            dir,
+            prefix: file_name(source),
+        }
+    }
+
+    pub(crate) fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", self.prefix, key))
+    }
+
+    // Lists the keys of the sidecars named "<prefix>.<key>".
+    pub(crate) fn keys(&self) -> Result<Vec<String>, Error> {
+        let mut keys = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let file_name = entry?.file_name().to_string_lossy().into_owned();
+            if let Some(key) = file_name
+                .strip_prefix(self.prefix.as_str())
+                .and_then(|rest| rest.strip_prefix('.'))
+            {
+                if !key.is_empty() {
+                    keys.push(key.to_string());
+                }
+            }
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{Fixture, FixtureDir},
+        FileQueue,
+    };
+
+    #[test]
+    fn test_state_dir() {
+        let root = FixtureDir::create().unwrap();
+        let state = StateDir::new(root.path()).unwrap();
+        let fixture = Fixture::lines(["a", "b"]).create().unwrap();
+
+        let mut queue = FileQueue::open_in(&state, fixture.path(), "reader").unwrap();
+        assert_eq!(queue.pop_batch(1).unwrap(), vec!["a"]);
+        queue.ack().unwrap();
+        assert_eq!(
+            FileQueue::groups_in(&state, fixture.path()).unwrap(),
+            vec![("reader".to_string(), 2)]
+        );
+        // Nothing is written next to the source
+        assert!(FileQueue::groups(fixture.path()).unwrap().is_empty());
+
+        let lock = state.lock(fixture.path()).unwrap();
+        assert_eq!(state.gc().unwrap(), 0);
+        drop(lock);
+
+        let path = fixture.path().to_path_buf();
+        drop(fixture);
+        assert!(!path.exists());
+        assert_eq!(state.gc().unwrap(), 1);
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 0);
+    }
+}
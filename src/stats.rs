@@ -0,0 +1,324 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+// Lag is how far a forward walk is behind the end of the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lag {
+    pub bytes: u64,
+    // Estimated from the average length of the lines read so far
+    pub lines: u64,
+}
+
+type LagCallback = Box<dyn Fn(Lag) + Send + Sync>;
+
+// WalkStats counts what a walk has read so far. It is cheap to clone, and
+// clones share the same counters, so one handle can be given to the Opener
+// while another is used for reporting.
+#[derive(Clone)]
+pub struct WalkStats {
+    inner: Arc<StatsInner>,
+}
+
+struct StatsInner {
+    bytes_read: AtomicU64,
+    lines_read: AtomicU64,
+    started: Instant,
+    offset: AtomicU64,
+    file_len: AtomicU64,
+    lag_threshold: AtomicU64,
+    lagging: AtomicBool,
+    on_lag: Mutex<Option<LagCallback>>,
+}
+
+impl fmt::Debug for WalkStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalkStats")
+            .field("bytes_read", &self.bytes_read())
+            .field("lines_read", &self.lines_read())
+            .field("lag", &self.lag())
+            .finish()
+    }
+}
+
+impl Default for WalkStats {
+    fn default() -> Self {
+        WalkStats {
+            inner: Arc::new(StatsInner {
+                bytes_read: AtomicU64::new(0),
+                lines_read: AtomicU64::new(0),
+                started: Instant::now(),
+                offset: AtomicU64::new(0),
+                file_len: AtomicU64::new(0),
+                lag_threshold: AtomicU64::new(u64::MAX),
+                lagging: AtomicBool::new(false),
+                on_lag: Mutex::new(None),
+            }),
+        }
+    }
+}
+
+impl WalkStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.inner.bytes_read.load(Ordering::Relaxed)
+    }
+
+    pub fn lines_read(&self) -> u64 {
+        self.inner.lines_read.load(Ordering::Relaxed)
+    }
+
+    // Average lines read per second since the stats were created.
+    pub fn lines_per_sec(&self) -> f64 {
+        let elapsed = self.inner.started.elapsed().as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        self.lines_read() as f64 / elapsed
+    }
+
+    // How far the walk is behind the end of the file, as of the last time
+    // the file length was observed.
+    pub fn lag(&self) -> Lag {
+        let bytes = self
+            .inner
+            .file_len
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.inner.offset.load(Ordering::Relaxed));
+        let lines = match (self.bytes_read(), self.lines_read()) {
+            (0, _) | (_, 0) => 0,
+            (read, lines) => bytes * lines / read,
+        };
+        Lag { bytes, lines }
+    }
+
+    // Calls callback whenever the lag grows past threshold_bytes. It fires
+    // once per crossing and is re-armed when the walk catches back up.
+    pub fn on_lag<F: Fn(Lag) + Send + Sync + 'static>(&self, threshold_bytes: u64, callback: F) {
+        *self.inner.on_lag.lock().unwrap() = Some(Box::new(callback));
+        self.inner
+            .lag_threshold
+            .store(threshold_bytes, Ordering::Relaxed);
+    }
+
+    // Records where a forward walk starts and how long the file is.
+    pub(crate) fn record_start(&self, offset: u64, file_len: u64) {
+        self.inner.offset.store(offset, Ordering::Relaxed);
+        self.record_len(file_len);
+    }
+
+    // Records a newly observed file length, e.g. after the file grew.
+    pub(crate) fn record_len(&self, file_len: u64) {
+        self.inner.file_len.store(file_len, Ordering::Relaxed);
+        self.check_lag();
+    }
+
+    pub(crate) fn record_line(&self, bytes: usize) {
+        self.inner
+            .bytes_read
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        self.inner.lines_read.fetch_add(1, Ordering::Relaxed);
+        self.inner.offset.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.check_lag();
+    }
+
+    fn check_lag(&self) {
+        let lag = self.lag();
+        let lagging = lag.bytes > self.inner.lag_threshold.load(Ordering::Relaxed);
+        if self.inner.lagging.swap(lagging, Ordering::Relaxed) || !lagging {
+            return;
+        }
+
+        if let Some(callback) = self.inner.on_lag.lock().unwrap().as_ref() {
+            callback(lag);
+        }
+    }
+
+    // Registers gauges and counters for these stats with a prometheus
+    // registry, labelled with the given walker name. Values are read from
+    // the shared counters whenever the registry is scraped.
+    #[cfg(feature = "prometheus")]
+    pub fn register(
+        &self,
+        registry: &prometheus::Registry,
+        walker: &str,
+    ) -> Result<(), crate::Error> {
+        let opts = |name: &str, help: &str| {
+            prometheus::Opts::new(name, help).const_label("walker", walker)
+        };
+        let collector = StatsCollector {
+            stats: self.clone(),
+            bytes_read: prometheus::IntCounter::with_opts(opts(
+                "filewalker_bytes_read_total",
+                "Bytes read by the walker.",
+            ))?,
+            lines_read: prometheus::IntCounter::with_opts(opts(
+                "filewalker_lines_read_total",
+                "Lines read by the walker.",
+            ))?,
+            lines_per_sec: prometheus::Gauge::with_opts(opts(
+                "filewalker_lines_per_second",
+                "Average lines read per second.",
+            ))?,
+            lag_bytes: prometheus::IntGauge::with_opts(opts(
+                "filewalker_lag_bytes",
+                "Bytes between the walker and the end of the file.",
+            ))?,
+        };
+        registry.register(Box::new(collector))?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "prometheus")]
+struct StatsCollector {
+    stats: WalkStats,
+    bytes_read: prometheus::IntCounter,
+    lines_read: prometheus::IntCounter,
+    lines_per_sec: prometheus::Gauge,
+    lag_bytes: prometheus::IntGauge,
+}
+
+#[cfg(feature = "prometheus")]
+impl prometheus::core::Collector for StatsCollector {
+    fn desc(&self) -> Vec<&prometheus::core::Desc> {
+        let mut descs = self.bytes_read.desc();
+        descs.extend(self.lines_read.desc());
+        descs.extend(self.lines_per_sec.desc());
+        descs.extend(self.lag_bytes.desc());
+        descs
+    }
+
+    fn collect(&self) -> Vec<prometheus::proto::MetricFamily> {
+        self.bytes_read.reset();
+        self.bytes_read.inc_by(self.stats.bytes_read());
+        self.lines_read.reset();
+        self.lines_read.inc_by(self.stats.lines_read());
+        self.lines_per_sec.set(self.stats.lines_per_sec());
+        self.lag_bytes.set(self.stats.lag().bytes as i64);
+
+        let mut families = self.bytes_read.collect();
+        families.extend(self.lines_read.collect());
+        families.extend(self.lines_per_sec.collect());
+        families.extend(self.lag_bytes.collect());
+        families
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, OpenerBuilder};
+    use std::{fs::OpenOptions, io::Write};
+
+    #[test]
+    fn test_stats() {
+        let stats = WalkStats::new();
+        OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .stats(stats.clone())
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .count();
+
+        assert_eq!(stats.lines_read(), 4);
+        assert_eq!(stats.bytes_read(), 20);
+    }
+
+    #[test]
+    fn test_lag_alert() {
+        let stats = WalkStats::new();
+        let alerts = Arc::new(Mutex::new(vec![]));
+        let sink = alerts.clone();
+        stats.on_lag(50, move |lag| sink.lock().unwrap().push(lag));
+
+        stats.record_start(0, 100);
+        stats.record_line(10);
+        assert_eq!(
+            stats.lag(),
+            Lag {
+                bytes: 90,
+                lines: 9
+            }
+        );
+        stats.record_line(50);
+        stats.record_len(200);
+        stats.record_len(300);
+
+        assert_eq!(
+            *alerts.lock().unwrap(),
+            vec![
+                Lag {
+                    bytes: 100,
+                    lines: 0
+                },
+                Lag {
+                    bytes: 140,
+                    lines: 4
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follow_lag() {
+        let fixture = Fixture::lines(["one"]).create().unwrap();
+        let stats = WalkStats::new();
+        let alerts = Arc::new(Mutex::new(vec![]));
+        let sink = alerts.clone();
+        stats.on_lag(2, move |lag| sink.lock().unwrap().push(lag.bytes));
+        let mut lines = fixture
+            .builder()
+            .position("end")
+            .follow(true)
+            .stats(stats.clone())
+            .build()
+            .unwrap()
+            .open()
+            .unwrap();
+
+        // The length is seen on reaching the end, with the line being
+        // written past where the walk has yielded up to
+        let path = fixture.path().to_path_buf();
+        let writer = std::thread::spawn(move || {
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(b"two\nthr").unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(150));
+            file.write_all(b"ee\n").unwrap();
+        });
+        assert_eq!(lines.next().unwrap().unwrap(), "two");
+        assert_eq!(lines.next().unwrap().unwrap(), "three");
+        writer.join().unwrap();
+        assert_eq!(*alerts.lock().unwrap(), vec![3]);
+        assert_eq!(stats.lag().bytes, 0);
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn test_prometheus() {
+        use prometheus::Encoder;
+
+        let stats = WalkStats::new();
+        let registry = prometheus::Registry::new();
+        stats.register(&registry, "test").unwrap();
+        stats.record_line(10);
+
+        let mut out = vec![];
+        prometheus::TextEncoder::new()
+            .encode(&registry.gather(), &mut out)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("filewalker_bytes_read_total{walker=\"test\"} 10"));
+        assert!(out.contains("filewalker_lines_read_total{walker=\"test\"} 1"));
+    }
+}
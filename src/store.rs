@@ -0,0 +1,262 @@
+use crate::{
+    state::{Sidecars, StateLock},
+    AtomicFile, Error, StateDir,
+};
+use std::{fs, io::Write, path::Path};
+
+// StateStore persists small per-file values such as queue offsets. Keys are
+// scoped to the source file they describe.
+pub trait StateStore: Send + Sync {
+    fn get(&self, source: &Path, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    // Replaces the value of key atomically, so a crash keeps either the old
+    // or the new value.
+    fn put(&self, source: &Path, key: &str, value: &[u8]) -> Result<(), Error>;
+
+    fn remove(&self, source: &Path, key: &str) -> Result<(), Error>;
+
+    // Lists the keys stored for source, in no particular order.
+    fn keys(&self, source: &Path) -> Result<Vec<String>, Error>;
+
+    // Locks the values of source against other processes, for stores that
+    // support it.
+    fn lock(&self, _source: &Path) -> Result<Option<StateLock>, Error> {
+        Ok(None)
+    }
+}
+
+// SidecarStore keeps every value in a file next to its source, named
+// "<source>.<key>". It is the default store.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SidecarStore;
+
+impl StateStore for SidecarStore {
+    fn get(&self, source: &Path, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Sidecars::beside(source).get(key)
+    }
+
+    fn put(&self, source: &Path, key: &str, value: &[u8]) -> Result<(), Error> {
+        Sidecars::beside(source).put(key, value)
+    }
+
+    fn remove(&self, source: &Path, key: &str) -> Result<(), Error> {
+        Sidecars::beside(source).remove(key)
+    }
+
+    fn keys(&self, source: &Path) -> Result<Vec<String>, Error> {
+        Sidecars::beside(source).keys()
+    }
+}
+
+impl StateStore for StateDir {
+    fn get(&self, source: &Path, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.sidecars(source)?.get(key)
+    }
+
+    fn put(&self, source: &Path, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.sidecars(source)?.put(key, value)
+    }
+
+    fn remove(&self, source: &Path, key: &str) -> Result<(), Error> {
+        self.sidecars(source)?.remove(key)
+    }
+
+    fn keys(&self, source: &Path) -> Result<Vec<String>, Error> {
+        self.sidecars(source)?.keys()
+    }
+
+    fn lock(&self, source: &Path) -> Result<Option<StateLock>, Error> {
+        StateDir::lock(self, source).map(Some)
+    }
+}
+
+impl Sidecars {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        match fs::read(self.path(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let mut file = AtomicFile::create(self.path(key))?;
+        file.write_all(value)?;
+        file.commit()
+    }
+
+    fn remove(&self, key: &str) -> Result<(), Error> {
+        match fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Identifies a source across stores by its absolute path.
+pub(crate) fn source_id(source: &Path) -> Result<String, Error> {
+    let absolute = fs::canonicalize(source).or_else(|_| std::path::absolute(source))?;
+    Ok(absolute.to_string_lossy().into_owned())
+}
+
+// SledStore keeps the values of every source in one sled database.
+#[cfg(feature = "sled")]
+#[derive(Debug, Clone)]
+pub struct SledStore {
+    db: sled::Db,
+}
+
+#[cfg(feature = "sled")]
+impl SledStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(SledStore {
+            db: sled::open(path)?,
+        })
+    }
+
+    fn key(source: &Path, key: &str) -> Result<String, Error> {
+        Ok(format!("{}\0{}", source_id(source)?, key))
+    }
+}
+
+#[cfg(feature = "sled")]
+impl StateStore for SledStore {
+    fn get(&self, source: &Path, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.db.get(Self::key(source, key)?)?.map(|v| v.to_vec()))
+    }
+
+    fn put(&self, source: &Path, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.db.insert(Self::key(source, key)?, value)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn remove(&self, source: &Path, key: &str) -> Result<(), Error> {
+        self.db.remove(Self::key(source, key)?)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn keys(&self, source: &Path) -> Result<Vec<String>, Error> {
+        let prefix = Self::key(source, "")?;
+        self.db
+            .scan_prefix(prefix.as_bytes())
+            .keys()
+            .map(|key| Ok(String::from_utf8_lossy(&key?[prefix.len()..]).into_owned()))
+            .collect()
+    }
+}
+
+// SqliteStore keeps the values of every source in one SQLite database.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    conn: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let conn = rusqlite::Connection::open(path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS state (
+                source TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value BLOB NOT NULL,
+                PRIMARY KEY (source, key)
+            )",
+            (),
+        )?;
+        Ok(SqliteStore {
+            conn: std::sync::Arc::new(std::sync::Mutex::new(conn)),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, rusqlite::Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl StateStore for SqliteStore {
+    fn get(&self, source: &Path, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        use rusqlite::OptionalExtension;
+
+        Ok(self
+            .conn()
+            .query_row(
+                "SELECT value FROM state WHERE source = ?1 AND key = ?2",
+                (source_id(source)?, key),
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn put(&self, source: &Path, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO state (source, key, value) VALUES (?1, ?2, ?3)",
+            (source_id(source)?, key, value),
+        )?;
+        Ok(())
+    }
+
+    fn remove(&self, source: &Path, key: &str) -> Result<(), Error> {
+        self.conn().execute(
+            "DELETE FROM state WHERE source = ?1 AND key = ?2",
+            (source_id(source)?, key),
+        )?;
+        Ok(())
+    }
+
+    fn keys(&self, source: &Path) -> Result<Vec<String>, Error> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT key FROM state WHERE source = ?1")?;
+        let keys = stmt
+            .query_map([source_id(source)?], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, FileQueue};
+
+    fn check_store<S: StateStore + Clone + 'static>(store: S) {
+        let fixture = Fixture::lines(["a", "b", "c"]).create().unwrap();
+        let source = fixture.path();
+        assert_eq!(store.get(source, "missing").unwrap(), None);
+
+        let mut queue = FileQueue::open_in(&store, source, "reader").unwrap();
+        assert_eq!(queue.pop_batch(2).unwrap(), vec!["a", "b"]);
+        queue.ack().unwrap();
+        let mut queue = FileQueue::open_in(&store, source, "reader").unwrap();
+        assert_eq!(queue.pop_batch(2).unwrap(), vec!["c"]);
+        assert_eq!(
+            FileQueue::groups_in(&store, source).unwrap(),
+            vec![("reader".to_string(), 4)]
+        );
+
+        store.remove(source, "reader.offset").unwrap();
+        assert!(store.keys(source).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sidecar_store() {
+        check_store(SidecarStore);
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn test_sled_store() {
+        let dir = crate::fixtures::FixtureDir::create().unwrap();
+        check_store(SledStore::open(dir.path().join("sled")).unwrap());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store() {
+        check_store(SqliteStore::open(":memory:").unwrap());
+    }
+}
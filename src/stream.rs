@@ -0,0 +1,117 @@
+use crate::{walk_file, Direction, Error, LineIter, Position, WalkOptions};
+use futures_core::Stream;
+use std::{
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::{sync::mpsc, task};
+
+// How many lines may be read ahead of the consumer of a stream.
+const READ_AHEAD: usize = 64;
+
+// LineStream yields the lines of a walk to async code. The walk itself runs
+// on tokio's blocking pool, the same way tokio::fs::File does its IO, so it
+// follows exactly the same Position and Direction rules as the sync API.
+// Dropping the stream stops the walk.
+pub struct LineStream {
+    rx: mpsc::Receiver<Result<String, Error>>,
+}
+
+impl Stream for LineStream {
+    type Item = Result<String, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+// Opens the walk on the blocking pool, so errors about the position or
+// direction are returned here rather than from the stream.
+pub(crate) async fn spawn<F>(open: F) -> Result<LineStream, Error>
+where
+    F: FnOnce() -> Result<LineIter, Error> + Send + 'static,
+{
+    let lines = task::spawn_blocking(open).await.map_err(join_error)??;
+    let (tx, rx) = mpsc::channel(READ_AHEAD);
+    task::spawn_blocking(move || {
+        for line in lines {
+            if tx.blocking_send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(LineStream { rx })
+}
+
+fn join_error(e: task::JoinError) -> Error {
+    Error::File(std::io::Error::other(e))
+}
+
+// The async counterpart of open_file.
+pub async fn open_file_async<T: AsRef<Path>, P: Into<Position>, D: Into<Direction>>(
+    path: T,
+    position: P,
+    direction: D,
+    max_position: Option<Position>,
+) -> Result<LineStream, Error> {
+    let path = path.as_ref().to_path_buf();
+    let position = position.into();
+    let direction = direction.into();
+    spawn(move || {
+        walk_file(
+            path,
+            position,
+            direction,
+            max_position,
+            WalkOptions::default(),
+        )
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenerBuilder;
+
+    async fn collect(mut stream: LineStream) -> Vec<String> {
+        let mut lines = vec![];
+        while let Some(line) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        {
+            lines.push(line.unwrap());
+        }
+        lines
+    }
+
+    #[test]
+    fn test_open_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let stream = open_file_async("./testfiles/1.txt", Position::Middle(2), None, None)
+                .await
+                .unwrap();
+            assert_eq!(collect(stream).await, vec!["there", "whats", "up"]);
+
+            let stream = OpenerBuilder::default()
+                .path("./testfiles/1.txt")
+                .position("end")
+                .direction("backward")
+                .max_position(Position::Middle(3))
+                .build()
+                .unwrap()
+                .open_async()
+                .await
+                .unwrap();
+            assert_eq!(collect(stream).await, vec!["up", "whats"]);
+
+            assert!(matches!(
+                open_file_async("./testfiles/1.txt", "start", "backward", None).await,
+                Err(Error::InvalidDirection { .. })
+            ));
+        });
+    }
+}
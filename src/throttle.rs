@@ -0,0 +1,191 @@
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    thread,
+    time::Duration,
+};
+
+// How many bytes may be read between pauses when nice_io is enabled.
+const BURST_BYTES: u64 = 256 * 1024;
+const BACKOFF: Duration = Duration::from_millis(1);
+
+// Throttled keeps a walk from starving other IO on the same disk when
+// nice_io is set. It sits under the walk's buffer, so it only acts once per
+// block read: reads pause briefly after every burst, and on Linux run in the
+// idle IO scheduling class. The class belongs to a thread, and a walk can be
+// moved to another one between reads, so it is set and restored around each
+// read rather than held for the life of the walk.
+pub(crate) struct Throttled<R> {
+    inner: R,
+    nice: bool,
+    since_pause: u64,
+}
+
+// Idle keeps the current thread in the idle IO class until dropped.
+struct Idle {
+    #[cfg(target_os = "linux")]
+    previous_priority: Option<libc::c_long>,
+}
+
+#[cfg(target_os = "linux")]
+const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+
+impl<R> Throttled<R> {
+    // Wraps inner, passing reads straight through unless nice is set.
+    pub(crate) fn new(inner: R, nice: bool) -> Self {
+        Throttled {
+            inner,
+            nice,
+            since_pause: 0,
+        }
+    }
+
+    fn consumed(&mut self, bytes: usize) {
+        self.since_pause += bytes as u64;
+        if self.since_pause >= BURST_BYTES {
+            self.since_pause = 0;
+            thread::sleep(BACKOFF);
+        }
+    }
+}
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.nice {
+            return self.inner.read(buf);
+        }
+        let idle = Idle::lower();
+        let read = self.inner.read(buf);
+        drop(idle);
+        if let Ok(read) = read {
+            self.consumed(read);
+        }
+        read
+    }
+}
+
+impl<R: Seek> Seek for Throttled<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl Idle {
+    // Lowers the IO priority of the current thread until dropped.
+    fn lower() -> Self {
+        Idle {
+            #[cfg(target_os = "linux")]
+            previous_priority: lower_priority(),
+        }
+    }
+}
+
+impl Drop for Idle {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Some(priority) = self.previous_priority {
+            unsafe {
+                libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, priority);
+            }
+        }
+    }
+}
+
+// Moves the current thread into the idle IO class, returning the priority to
+// restore afterwards, or None if it couldn't be changed.
+#[cfg(target_os = "linux")]
+fn lower_priority() -> Option<libc::c_long> {
+    unsafe {
+        let previous = libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, 0);
+        if previous < 0 {
+            return None;
+        }
+
+        let idle = IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT;
+        if libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, idle) < 0 {
+            return None;
+        }
+        Some(previous)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenerBuilder;
+
+    #[test]
+    fn test_nice_io() {
+        let lines: Vec<String> = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .nice_io(true)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines.len(), 4);
+
+        let mut throttle = Throttled::new(io::empty(), true);
+        throttle.consumed(BURST_BYTES as usize - 1);
+        assert_eq!(throttle.since_pause, BURST_BYTES - 1);
+        throttle.consumed(1);
+        assert_eq!(throttle.since_pause, 0);
+    }
+
+    #[test]
+    fn test_nice_io_per_block() {
+        // Throttling, and the priority change with it, happens once per
+        // read of the source, not once per line
+        struct Counting(io::Cursor<Vec<u8>>, usize);
+        impl Read for Counting {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.1 += 1;
+                self.0.read(buf)
+            }
+        }
+        let text = "line\n".repeat(10_000).into_bytes();
+        let source = Throttled::new(Counting(io::Cursor::new(text), 0), true);
+        let mut reader = io::BufReader::with_capacity(8192, source);
+        assert_eq!(io::BufRead::lines(&mut reader).count(), 10_000);
+        assert_eq!(reader.into_inner().inner.1, 8);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_nice_io_across_threads() {
+        let priority = || unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, 0) };
+        let before = priority();
+        let idle = Idle::lower();
+        if idle.previous_priority.is_some() {
+            assert_eq!(priority(), IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT);
+        }
+        drop(idle);
+        assert_eq!(priority(), before);
+
+        // Each thread that reads from the walk gets its priority back
+        let mut lines = OpenerBuilder::default()
+            .path("./testfiles/1.txt")
+            .nice_io(true)
+            .build()
+            .unwrap()
+            .open()
+            .unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "hello");
+        assert_eq!(priority(), before);
+        let reader = std::thread::spawn(move || {
+            let before = priority();
+            let line = lines.next().unwrap().unwrap();
+            (line, before, priority(), lines)
+        });
+        let (line, their_before, their_after, lines) = reader.join().unwrap();
+        assert_eq!(line, "there");
+        assert_eq!(their_after, their_before);
+        drop(lines);
+        assert_eq!(priority(), before);
+    }
+}
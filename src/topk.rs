@@ -0,0 +1,182 @@
+use std::collections::{BTreeSet, HashMap};
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+// Dimensions of the count-min sketch. Counts are overestimated by at most
+// about 2 / SKETCH_WIDTH of the total with a probability of 1 - 2^-SKETCH_DEPTH,
+// using SKETCH_WIDTH * SKETCH_DEPTH * 8 bytes (2 MiB) whatever the input size.
+const SKETCH_WIDTH: usize = 1 << 16;
+const SKETCH_DEPTH: usize = 4;
+
+// TopK tracks the most frequent keys of a stream in bounded memory: counts
+// live in a count-min sketch and only the current k heaviest keys are kept.
+pub(crate) struct TopK {
+    k: usize,
+    sketch: Vec<u64>,
+    counts: HashMap<String, u64>,
+    // The tracked keys ordered by count, to find the lightest one to evict
+    ranked: BTreeSet<(u64, String)>,
+}
+
+impl TopK {
+    pub(crate) fn new(k: usize) -> Self {
+        TopK {
+            k,
+            sketch: vec![0; SKETCH_WIDTH * SKETCH_DEPTH],
+            counts: HashMap::with_capacity(k + 1),
+            ranked: BTreeSet::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, key: String) {
+        let mut estimate = u64::MAX;
+        for row in 0..SKETCH_DEPTH {
+            let column = xxh3_64_with_seed(key.as_bytes(), row as u64) as usize % SKETCH_WIDTH;
+            let cell = &mut self.sketch[row * SKETCH_WIDTH + column];
+            *cell += 1;
+            estimate = estimate.min(*cell);
+        }
+
+        if self.k == 0 {
+            return;
+        }
+        if let Some(count) = self.counts.get_mut(&key) {
+            self.ranked.remove(&(*count, key.clone()));
+            *count = estimate;
+            self.ranked.insert((estimate, key));
+            return;
+        }
+        if self.counts.len() == self.k {
+            match self.ranked.first() {
+                Some((lightest, _)) if *lightest < estimate => {
+                    let (_, evicted) = self.ranked.pop_first().unwrap();
+                    self.counts.remove(&evicted);
+                }
+                _ => return,
+            }
+        }
+        self.counts.insert(key.clone(), estimate);
+        self.ranked.insert((estimate, key));
+    }
+
+    // Returns the tracked keys with their estimated counts, most frequent first.
+    pub(crate) fn into_sorted(self) -> Vec<(String, u64)> {
+        let mut top: Vec<(String, u64)> = self.counts.into_iter().collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top
+    }
+}
+
+// Returns the k most frequent keys extracted from lines, with their
+// estimated counts, in a single pass and bounded memory. Lines for which
+// key_fn returns None are skipped.
+pub fn top_k<I, F>(lines: I, mut key_fn: F, k: usize) -> Vec<(String, u64)>
+where
+    I: IntoIterator<Item = String>,
+    F: FnMut(&str) -> Option<&str>,
+{
+    let mut top = TopK::new(k);
+    for line in lines {
+        if let Some(key) = key_fn(&line) {
+            top.insert(key.to_string());
+        }
+    }
+    top.into_sorted()
+}
+
+// CountDiff is what changed between the counts of two runs of the same
+// aggregation, such as top_k over a log scanned on a schedule
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CountDiff {
+    // Keys only counted by the newer run, e.g. new error signatures
+    pub added: Vec<(String, u64)>,
+    pub removed: Vec<(String, u64)>,
+    // Keys counted by both runs, with the older and the newer count
+    pub changed: Vec<(String, u64, u64)>,
+}
+
+impl CountDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+// Reports what changed between the counts of an older run and a newer one,
+// keeping the order of the run each key comes from. A key that falls out of
+// the top k counts as removed.
+pub fn diff_counts(older: &[(String, u64)], newer: &[(String, u64)]) -> CountDiff {
+    let old_counts: HashMap<&str, u64> = older
+        .iter()
+        .map(|(key, count)| (key.as_str(), *count))
+        .collect();
+    let new_counts: HashMap<&str, u64> = newer
+        .iter()
+        .map(|(key, count)| (key.as_str(), *count))
+        .collect();
+
+    let mut diff = CountDiff::default();
+    for (key, count) in newer {
+        match old_counts.get(key.as_str()) {
+            None => diff.added.push((key.clone(), *count)),
+            Some(old) if old != count => diff.changed.push((key.clone(), *old, *count)),
+            _ => {}
+        }
+    }
+    for (key, count) in older {
+        if !new_counts.contains_key(key.as_str()) {
+            diff.removed.push((key.clone(), *count));
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Fixture, WalkExt};
+
+    #[test]
+    fn test_top_k() {
+        let mut lines = vec![];
+        for (ip, hits) in [("10.0.0.1", 50), ("10.0.0.2", 5), ("10.0.0.3", 20)] {
+            for _ in 0..hits {
+                lines.push(format!("{} GET /", ip));
+            }
+        }
+        for i in 0..1000 {
+            lines.push(format!("10.1.{}.{} GET /", i / 256, i % 256));
+        }
+        let fixture = Fixture::lines(lines).create().unwrap();
+
+        let top = fixture
+            .opener()
+            .open()
+            .unwrap()
+            .top_k(|line| line.split(' ').next(), 2)
+            .unwrap();
+        assert_eq!(
+            top,
+            vec![("10.0.0.1".to_string(), 50), ("10.0.0.3".to_string(), 20)]
+        );
+        assert!(top_k(Vec::new(), |line| Some(line), 3).is_empty());
+    }
+
+    #[test]
+    fn test_diff_counts() {
+        let run = |lines: &[&str]| {
+            let lines = lines.iter().map(|line| line.to_string());
+            top_k(lines, |line| line.split(':').next(), 3)
+        };
+        let older = run(&["timeout: a", "timeout: b", "refused: c", "reset: d"]);
+        let newer = run(&["timeout: a", "refused: c", "refused: e", "denied: f"]);
+
+        let diff = diff_counts(&older, &newer);
+        assert_eq!(diff.added, vec![("denied".to_string(), 1)]);
+        assert_eq!(diff.removed, vec![("reset".to_string(), 1)]);
+        assert_eq!(
+            diff.changed,
+            vec![("refused".to_string(), 1, 2), ("timeout".to_string(), 2, 1)]
+        );
+        assert!(diff_counts(&newer, &newer).is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{reader::LinesReader, Error, LineIter, OpenerTemplate};
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+// Walker walks every file under a directory in turn, in path order, using
+// the settings of an OpenerTemplate for each one. It yields the path, line
+// number and text of every selected line. Symlinked directories are not
+// followed, so the walk always ends.
+pub struct Walker {
+    template: OpenerTemplate,
+    // Paths still to visit, the next one last
+    pending: Vec<PathBuf>,
+    current: Option<(PathBuf, LineIter)>,
+}
+
+impl Walker {
+    pub fn new<P: AsRef<Path>>(root: P, template: OpenerTemplate) -> Self {
+        Walker {
+            template,
+            pending: vec![root.as_ref().to_path_buf()],
+            current: None,
+        }
+    }
+
+    // Reads the lines of every file as bytes, each one followed by a '\n', as
+    // if the files were concatenated.
+    pub fn into_reader(self) -> impl Read {
+        LinesReader::new(self.map(|item| item.map(|(_, _, line)| line)))
+    }
+
+    // Moves on to the next file, expanding directories on the way.
+    fn open_next(&mut self) -> Option<Result<(), Error>> {
+        while let Some(path) = self.pending.pop() {
+            let metadata = match fs::symlink_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if metadata.is_dir() {
+                if let Err(e) = self.expand(&path) {
+                    return Some(Err(e));
+                }
+                continue;
+            }
+            if metadata.is_symlink() && path.is_dir() {
+                continue;
+            }
+
+            return Some(
+                self.template
+                    .opener(&path)
+                    .open_counted()
+                    .map(|lines| self.current = Some((path, lines))),
+            );
+        }
+        None
+    }
+
+    fn expand(&mut self, dir: &Path) -> Result<(), Error> {
+        let mut children = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        children.sort();
+        self.pending.extend(children.into_iter().rev());
+        Ok(())
+    }
+}
+
+impl Iterator for Walker {
+    type Item = Result<(PathBuf, usize, String), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((path, lines)) = &mut self.current {
+                match lines.next_numbered() {
+                    Some(Ok((number, line))) => return Some(Ok((path.clone(), number, line))),
+                    Some(Err(e)) => {
+                        self.current = None;
+                        return Some(Err(e));
+                    }
+                    None => self.current = None,
+                }
+            }
+
+            // Errors opening one file don't end the walk
+            if let Err(e) = self.open_next()? {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::FixtureDir, OpenerTemplateBuilder};
+
+    #[test]
+    fn test_walker() {
+        let dir = FixtureDir::create().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("a.log"), "one\ntwo\n").unwrap();
+        fs::write(root.join("b").join("c.log"), "three\n").unwrap();
+        fs::write(root.join("b").join("empty.log"), "").unwrap();
+
+        let template = OpenerTemplateBuilder::default()
+            .position("end")
+            .direction("backward")
+            .build()
+            .unwrap();
+        let items: Vec<(PathBuf, usize, String)> = Walker::new(root, template.clone())
+            .filter_map(Result::ok)
+            .collect();
+        let errors = Walker::new(root, OpenerTemplate::default())
+            .filter(Result::is_err)
+            .count();
+        let mut contents = String::new();
+        Walker::new(root, template)
+            .into_reader()
+            .read_to_string(&mut contents)
+            .unwrap();
+
+        assert_eq!(
+            items,
+            vec![
+                (root.join("a.log"), 2, "two".to_string()),
+                (root.join("a.log"), 1, "one".to_string()),
+                (root.join("b").join("c.log"), 1, "three".to_string()),
+            ]
+        );
+        assert_eq!(errors, 0);
+        assert_eq!(contents, "two\none\nthree\n");
+    }
+}
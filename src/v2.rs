@@ -0,0 +1,68 @@
+// The streaming-first API. A walk is described once by a WalkConfig, and
+// every entry point reads lines on demand, each one a Result, so nothing is
+// read up front and a read error surfaces at the line it hits. The rest of
+// the crate keeps working alongside it, so callers can move over one call
+// at a time: WalkConfig converts from an Opener's settings and back into an
+// OpenerBuilder.
+use std::{
+    io::{Read, Seek},
+    path::Path,
+};
+
+pub use crate::{
+    AutoLines, Backend, BlankLines, Direction, Error, LineEnding, LineIndex, LineIter, Newlines,
+    PauseHandle, Position, Removal, Utf8Policy, WalkConfig, WalkConfigBuilder,
+    WalkConfigBuilderError, WalkStats,
+};
+
+// Walks the file at path with config. Replaces open_file, which reads every
+// line before returning.
+pub fn open<P: AsRef<Path>>(path: P, config: &WalkConfig) -> Result<LineIter, Error> {
+    config.open(path)
+}
+
+// Walks any seekable source with config.
+pub fn open_reader<R: Read + Seek + Send + 'static>(
+    reader: R,
+    config: &WalkConfig,
+) -> Result<LineIter, Error> {
+    config.open_reader(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenerBuilder;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_v2() {
+        let config = WalkConfigBuilder::default()
+            .position("end")
+            .direction("backward")
+            .build()
+            .unwrap();
+        let lines: Vec<String> = open("./testfiles/1.txt", &config)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["up", "whats", "there", "hello"]);
+
+        let reader = Cursor::new(b"a\nb".to_vec());
+        let lines: Vec<String> = open_reader(reader, &config)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["b", "a"]);
+
+        // Settings survive the trip through an Opener and back
+        let opener = OpenerBuilder::from(config.clone())
+            .path("./testfiles/1.txt")
+            .build()
+            .unwrap();
+        assert_eq!(opener.open().unwrap().count(), 4);
+        let back = WalkConfig::from(&opener);
+        assert_eq!(back.position, config.position);
+        assert_eq!(back.direction, config.direction);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    byte_offset_of_line, count_lines, detect_line_ending, open_lines, Direction, Error, LineIter,
+    Position, Record,
+};
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+// FileWalk is the interface shared by walker implementations, so downstream
+// code can accept `impl FileWalk` and wrap walkers with caching, metrics or
+// mocks without depending on how the lines are read.
+pub trait FileWalk {
+    // Opens the file at path with the next record being the line at position.
+    fn open_at<T: AsRef<Path>>(
+        path: T,
+        position: Position,
+        direction: Direction,
+    ) -> Result<Self, Error>
+    where
+        Self: Sized;
+
+    // Returns the next line along with its line number in the file.
+    fn next_record(&mut self) -> Option<Result<Record, Error>>;
+
+    // The line number of the record next_record will return.
+    fn cursor(&self) -> usize;
+
+    // Moves the cursor, keeping the walk direction.
+    fn seek(&mut self, position: Position) -> Result<(), Error>;
+}
+
+// LineWalker is the built-in FileWalk implementation on top of open_lines.
+// Lines are streamed rather than read up front, so opening and seeking only
+// scan as far as the line the walk starts at.
+pub struct LineWalker {
+    path: PathBuf,
+    direction: Direction,
+    lines: LineIter,
+    cursor: usize,
+}
+
+impl FileWalk for LineWalker {
+    fn open_at<T: AsRef<Path>>(
+        path: T,
+        position: Position,
+        direction: Direction,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        // Records are numbered by line, so a byte offset is resolved to the
+        // line it snaps to
+        let position = match position {
+            Position::Byte(_) | Position::Percent(_) => {
+                let offset = position.byte_offset(fs::metadata(&path)?.len());
+                Position::Middle(line_at(&path, offset, direction)?)
+            }
+            position => position,
+        };
+        let cursor = match position {
+            Position::Start => 1,
+            Position::Middle(line) => line,
+            _ => {
+                let delimiter = detect_line_ending(&path)?.delimiter();
+                let total_lines = count_lines(File::open(&path)?, delimiter)?;
+                match position.resolve(total_lines) {
+                    Position::Middle(line) => line,
+                    _ => total_lines,
+                }
+            }
+        };
+        // The walk is reopened at the byte offset the cursor's line starts
+        // at, or going backward, the offset it ends at
+        let offset = match direction {
+            Direction::Forward => byte_offset_of_line(&path, cursor)?,
+            Direction::Backward if cursor == 0 => 0,
+            Direction::Backward => byte_offset_of_line(&path, cursor + 1)?,
+        };
+        let lines = open_lines(&path, Position::Byte(offset), direction, None)?;
+
+        Ok(LineWalker {
+            path,
+            direction,
+            lines,
+            cursor,
+        })
+    }
+
+    fn next_record(&mut self) -> Option<Result<Record, Error>> {
+        let text = match self.lines.next()? {
+            Ok(text) => text,
+            Err(err) => return Some(Err(err)),
+        };
+        let number = self.cursor;
+        self.cursor = match self.direction {
+            Direction::Forward => self.cursor + 1,
+            Direction::Backward => self.cursor - 1,
+        };
+        Some(Ok(Record { number, text }))
+    }
+
+    fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn seek(&mut self, position: Position) -> Result<(), Error> {
+        *self = LineWalker::open_at(&self.path, position, self.direction)?;
+        Ok(())
+    }
+}
+
+// Returns the line a walk from offset starts at: the line starting there or
+// after it going forward, and the line ending there or before it going
+// backward.
+fn line_at(path: &Path, offset: u64, direction: Direction) -> Result<usize, Error> {
+    let delimiter = detect_line_ending(path)?.delimiter();
+    let mut reader = BufReader::new(File::open(path)?.take(offset));
+    let (mut ended, mut at_boundary) = (0, true);
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        ended += memchr::memchr_iter(delimiter, buf).count();
+        at_boundary = buf.last() == Some(&delimiter);
+        let consumed = buf.len();
+        reader.consume(consumed);
+    }
+    Ok(match direction {
+        Direction::Backward => ended,
+        Direction::Forward if at_boundary => ended + 1,
+        Direction::Forward => ended + 2,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers<W: FileWalk>(walker: &mut W) -> Vec<usize> {
+        let mut numbers = vec![];
+        while let Some(record) = walker.next_record() {
+            numbers.push(record.unwrap().number);
+        }
+        numbers
+    }
+
+    #[test]
+    fn test_line_walker() {
+        let mut walker =
+            LineWalker::open_at("./testfiles/1.txt", Position::End, Direction::Backward).unwrap();
+        assert_eq!(walker.cursor(), 4);
+        assert_eq!(
+            walker.next_record().unwrap().unwrap(),
+            Record {
+                number: 4,
+                text: "up".to_string()
+            }
+        );
+        assert_eq!(numbers(&mut walker), vec![3, 2, 1]);
+
+        walker.seek(Position::Middle(2)).unwrap();
+        assert_eq!(walker.cursor(), 2);
+        assert_eq!(numbers(&mut walker), vec![2, 1]);
+        walker.seek(Position::Byte(8)).unwrap();
+        assert_eq!(numbers(&mut walker), vec![1]);
+
+        let mut walker =
+            LineWalker::open_at("./testfiles/1.txt", Position::Byte(8), Direction::Forward)
+                .unwrap();
+        assert_eq!(walker.cursor(), 3);
+        assert_eq!(numbers(&mut walker), vec![3, 4]);
+        walker.seek(Position::FromEnd(3)).unwrap();
+        assert_eq!(numbers(&mut walker), vec![2, 3, 4]);
+        walker.seek(Position::Middle(9)).unwrap();
+        assert_eq!(numbers(&mut walker), Vec::<usize>::new());
+    }
+}
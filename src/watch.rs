@@ -0,0 +1,144 @@
+use crate::{Error, LineEnding};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// AppendHandle controls the thread started by Opener::on_append. Dropping it
+// stops the thread without waiting for it.
+pub struct AppendHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<(), Error>>>,
+}
+
+impl AppendHandle {
+    // Stops watching and waits for the thread, returning the error that
+    // ended it early if there was one.
+    pub fn shutdown(mut self) -> Result<(), Error> {
+        self.stop.store(true, Ordering::Relaxed);
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+}
+
+impl Drop for AppendHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// Polls path for lines appended after offset and hands every batch of
+// complete lines to callback. Truncation restarts reading from the top.
+pub(crate) fn spawn<F>(
+    path: PathBuf,
+    offset: u64,
+    line_ending: LineEnding,
+    mut callback: F,
+) -> AppendHandle
+where
+    F: FnMut(Vec<String>) + Send + 'static,
+{
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let mut offset = offset;
+            while !stop.load(Ordering::Relaxed) {
+                let batch = read_appended(&path, &mut offset, line_ending)?;
+                if batch.is_empty() {
+                    thread::sleep(POLL_INTERVAL);
+                } else {
+                    callback(batch);
+                }
+            }
+            Ok(())
+        })
+    };
+
+    AppendHandle {
+        stop,
+        thread: Some(thread),
+    }
+}
+
+fn read_appended(
+    path: &Path,
+    offset: &mut u64,
+    line_ending: LineEnding,
+) -> Result<Vec<String>, Error> {
+    let file = File::open(path)?;
+    if file.metadata()?.len() < *offset {
+        *offset = 0;
+    }
+
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(*offset))?;
+    let delimiter = line_ending.delimiter();
+    let mut lines = vec![];
+    let mut buf = vec![];
+    loop {
+        buf.clear();
+        let read = reader.read_until(delimiter, &mut buf)?;
+        // A line without its delimiter is still being written.
+        if read == 0 || buf.last() != Some(&delimiter) {
+            break;
+        }
+        *offset += read as u64;
+        line_ending.strip(&mut buf);
+        lines.push(String::from_utf8_lossy(&buf).into_owned());
+    }
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::Fixture;
+    use std::{fs::OpenOptions, io::Write, sync::mpsc, time::Duration};
+
+    #[test]
+    fn test_on_append() {
+        let fixture = Fixture::lines(["old"]).create().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let handle = fixture
+            .opener()
+            .on_append(move |batch| tx.send(batch).unwrap())
+            .unwrap();
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(fixture.path())
+            .unwrap();
+        file.write_all(b"new 1\nnew 2\npart").unwrap();
+        let mut lines = vec![];
+        while lines.len() < 2 {
+            lines.extend(rx.recv_timeout(Duration::from_secs(5)).unwrap());
+        }
+        assert_eq!(lines, vec!["new 1", "new 2"]);
+
+        file.write_all(b"ial\n").unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            vec!["partial"]
+        );
+
+        assert!(handle.is_running());
+        handle.shutdown().unwrap();
+    }
+}